@@ -0,0 +1,6 @@
+CREATE TABLE
+    IF NOT EXISTS "schema_cache" (
+        connection_id INTEGER PRIMARY KEY REFERENCES "connection" (id) ON DELETE CASCADE,
+        schema TEXT NOT NULL,
+        updated_at DATETIME DEFAULT CURRENT_TIMESTAMP
+    );
\ No newline at end of file
@@ -0,0 +1 @@
+ALTER TABLE "connection" ADD COLUMN pragmas TEXT NOT NULL DEFAULT '{}';
\ No newline at end of file
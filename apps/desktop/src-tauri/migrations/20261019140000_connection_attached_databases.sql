@@ -0,0 +1 @@
+ALTER TABLE "connection" ADD COLUMN attached_databases TEXT NOT NULL DEFAULT '[]';
\ No newline at end of file
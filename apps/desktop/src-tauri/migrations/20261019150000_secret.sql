@@ -0,0 +1,8 @@
+CREATE TABLE
+    IF NOT EXISTS "secret" (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        value TEXT NOT NULL,
+        created_at DATETIME DEFAULT CURRENT_TIMESTAMP
+    );
+
+ALTER TABLE "connection" ADD COLUMN sqlite_key_secret_id INTEGER REFERENCES "secret" (id) ON DELETE SET NULL;
\ No newline at end of file
@@ -0,0 +1 @@
+ALTER TABLE "connection" ADD COLUMN sqlite_extensions TEXT NOT NULL DEFAULT '[]';
\ No newline at end of file
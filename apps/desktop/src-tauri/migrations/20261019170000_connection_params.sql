@@ -0,0 +1,3 @@
+ALTER TABLE "connection" ADD COLUMN params TEXT;
+
+ALTER TABLE "connection" ADD COLUMN password_secret_id INTEGER REFERENCES "secret" (id) ON DELETE SET NULL;
\ No newline at end of file
@@ -0,0 +1,8 @@
+ALTER TABLE "secret" ADD COLUMN encrypted BOOLEAN NOT NULL DEFAULT FALSE;
+
+CREATE TABLE
+    IF NOT EXISTS "master_password" (
+        id INTEGER PRIMARY KEY CHECK (id = 1),
+        salt TEXT NOT NULL,
+        verifier TEXT NOT NULL
+    );
\ No newline at end of file
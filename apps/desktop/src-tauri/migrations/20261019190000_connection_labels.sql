@@ -0,0 +1,7 @@
+ALTER TABLE "connection" ADD COLUMN group_name TEXT;
+
+ALTER TABLE "connection" ADD COLUMN tags TEXT NOT NULL DEFAULT '[]';
+
+ALTER TABLE "connection" ADD COLUMN color TEXT;
+
+ALTER TABLE "connection" ADD COLUMN environment TEXT;
\ No newline at end of file
@@ -0,0 +1 @@
+ALTER TABLE "connection" ADD COLUMN safety_level TEXT NOT NULL DEFAULT 'normal';
\ No newline at end of file
@@ -0,0 +1 @@
+ALTER TABLE "connection" ADD COLUMN ssh_secret_id INTEGER REFERENCES "secret" (id) ON DELETE SET NULL;
\ No newline at end of file
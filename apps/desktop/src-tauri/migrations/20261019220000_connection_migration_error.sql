@@ -0,0 +1,2 @@
+ALTER TABLE "connection" ADD COLUMN migration_error TEXT;
+ALTER TABLE "connection" ADD COLUMN connection_string_secret_id INTEGER REFERENCES "secret" (id) ON DELETE SET NULL;
\ No newline at end of file
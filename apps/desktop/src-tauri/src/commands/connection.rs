@@ -1,6 +1,6 @@
 use crate::{
     AppState,
-    state::{MetaXStatus, SharedState, Storage},
+    state::{ConnectionFilter, ConnectionSort, MetaXStatus, SharedState, Storage},
 };
 use std::sync::Arc;
 #[cfg(feature = "metax")]
@@ -14,14 +14,39 @@ use tauri_specta::Event;
 use tx_handlers::DatabaseConnection;
 use tx_lib::{
     Result, TxError,
-    events::ConnectionsChanged,
-    types::{ConnConfig, Drivers},
+    connection::{ConnectOptions, ConnectionParams, SshLogin, SshTunnel},
+    events::{ConnectionsChanged, SchemaChanged},
+    safety::SafetyLevel,
+    types::{
+        AttachedDatabase, ConnConfig, ConnectionLabels, Drivers, SecretString, SqliteExtension,
+        SqliteOptions, SqlitePragmas,
+    },
 };
 
+/// Ping a database before its connection is saved, `key` is the SQLCipher key of an
+/// encrypted SQLite database. With `ssh`, the database is reached through the tunnel,
+/// `ssh_secret` is its password or key passphrase.
 #[tauri::command]
 #[specta::specta]
-pub async fn test_connection(conn_string: String, driver: Drivers) -> Result<()> {
-    DatabaseConnection::ping(conn_string.as_str(), &driver).await
+pub async fn test_connection(
+    conn_string: String,
+    driver: Drivers,
+    key: Option<String>,
+    ssh: Option<SshTunnel>,
+    ssh_secret: Option<String>,
+) -> Result<()> {
+    let options = ConnectOptions {
+        sqlite: SqliteOptions {
+            key: key.map(SecretString::new),
+            ..Default::default()
+        },
+        ssh: ssh.map(|tunnel| SshLogin {
+            tunnel,
+            secret: ssh_secret.map(SecretString::new),
+        }),
+        ..Default::default()
+    };
+    DatabaseConnection::ping(conn_string.as_str(), &driver, &options).await
 }
 
 #[tauri::command]
@@ -32,14 +57,139 @@ pub async fn create_connection_record(
     conn_name: String,
     driver: Drivers,
 ) -> Result<i64> {
+    let (params, password) = ConnectionParams::from_url(&conn_string)?;
+    if params.driver() != driver {
+        return Err(TxError::DriverMismatch(driver, params.driver()));
+    }
     let id = storage
-        .insert_connection(conn_string, conn_name, driver)
+        .insert_connection(conn_name, &params, password.as_ref())
         .await?;
     log::info!(id = id ;"New connection created");
 
     Ok(id)
 }
 
+/// Replace the name and settings of a saved connection. The password is kept when
+/// `password` is `None`, and removed when it's empty. With `test`, the connection is
+/// pinged first and nothing is saved if it fails.
+#[tauri::command]
+#[specta::specta]
+pub async fn update_connection(
+    storage: State<'_, Storage>,
+    app: tauri::AppHandle,
+    conn_id: i64,
+    conn_name: String,
+    params: ConnectionParams,
+    password: Option<String>,
+    test: bool,
+) -> Result<()> {
+    let password = password.map(SecretString::new);
+    if test {
+        let current = storage.get_connection_by_id(conn_id).await?;
+        let password = password
+            .as_ref()
+            .or(current.password.as_ref())
+            .filter(|password| !password.expose().is_empty());
+        let options = ConnectOptions {
+            ssh: params.ssh().map(|tunnel| SshLogin {
+                tunnel: tunnel.clone(),
+                secret: current.ssh_secret.clone(),
+            }),
+            ..current.connect_options()
+        };
+        DatabaseConnection::ping(&params.to_url(password)?, &params.driver(), &options).await?;
+    }
+    storage
+        .update_connection(conn_id, conn_name, &params, password.as_ref())
+        .await?;
+    log::info!(id = conn_id; "Connection updated");
+
+    ConnectionsChanged.emit(&app).unwrap();
+    log::debug!("Event emitted: {:?}", ConnectionsChanged);
+
+    Ok(())
+}
+
+/// Copy a saved connection with its options and secrets, `conn_name` defaults to the
+/// original's name followed by "(copy)".
+#[tauri::command]
+#[specta::specta]
+pub async fn duplicate_connection(
+    storage: State<'_, Storage>,
+    app: tauri::AppHandle,
+    conn_id: i64,
+    conn_name: Option<String>,
+) -> Result<i64> {
+    let id = storage.duplicate_connection(conn_id, conn_name).await?;
+    log::info!(id = id; "Connection {conn_id} duplicated");
+
+    ConnectionsChanged.emit(&app).unwrap();
+    log::debug!("Event emitted: {:?}", ConnectionsChanged);
+
+    Ok(id)
+}
+
+/// Set the group, tags, color and environment of a saved connection.
+#[tauri::command]
+#[specta::specta]
+pub async fn set_connection_labels(
+    storage: State<'_, Storage>,
+    app: tauri::AppHandle,
+    conn_id: i64,
+    labels: ConnectionLabels,
+) -> Result<()> {
+    storage.update_connection_labels(conn_id, labels).await?;
+    log::info!(id = conn_id; "Connection labels updated");
+
+    ConnectionsChanged.emit(&app).unwrap();
+    log::debug!("Event emitted: {:?}", ConnectionsChanged);
+
+    Ok(())
+}
+
+/// Set which statements are allowed on a saved connection. If it's the open connection
+/// and it becomes read-only or stops being so, it's re-established so the database
+/// session's read-only flag matches.
+#[tauri::command]
+#[specta::specta]
+pub async fn set_connection_safety_level(
+    storage: State<'_, Storage>,
+    state: AppState<'_>,
+    app: tauri::AppHandle,
+    conn_id: i64,
+    safety_level: SafetyLevel,
+) -> Result<()> {
+    storage.update_safety_level(conn_id, safety_level).await?;
+    log::info!(id = conn_id; "Connection safety level set to {safety_level}");
+
+    let mut state = state.lock().await;
+    let mut reconnect = false;
+    if state.conn_id == Some(conn_id) {
+        reconnect = (state.safety_level == SafetyLevel::ReadOnly)
+            != (safety_level == SafetyLevel::ReadOnly);
+        state.safety_level = safety_level;
+    }
+    drop(state);
+
+    ConnectionsChanged.emit(&app).unwrap();
+    log::debug!("Event emitted: {:?}", ConnectionsChanged);
+
+    if reconnect {
+        establish_connection(app, storage, conn_id).await?;
+        log::info!(id = conn_id; "Connection re-established with its new safety level");
+    }
+
+    Ok(())
+}
+
+/// Split a connection string into its settings, its password is left out.
+#[tauri::command]
+#[specta::specta]
+pub async fn parse_connection_string(conn_string: String) -> Result<ConnectionParams> {
+    let (params, _) = ConnectionParams::from_url(&conn_string)?;
+    Ok(params)
+}
+
 #[tauri::command]
 #[specta::specta]
 pub async fn delete_connection_record(
@@ -57,6 +207,108 @@ pub async fn delete_connection_record(
     Ok(String::from("Successfully deleted connection"))
 }
 
+/// Replace the pragmas of a saved SQLite connection, they're applied the next time it's
+/// established.
+#[tauri::command]
+#[specta::specta]
+pub async fn set_connection_pragmas(
+    storage: State<'_, Storage>,
+    app: tauri::AppHandle,
+    conn_id: i64,
+    pragmas: SqlitePragmas,
+) -> Result<()> {
+    storage.update_connection_pragmas(conn_id, &pragmas).await?;
+    log::info!(id = conn_id; "Connection pragmas updated");
+
+    ConnectionsChanged.emit(&app).unwrap();
+    log::debug!("Event emitted: {:?}", ConnectionsChanged);
+
+    Ok(())
+}
+
+/// Replace the databases attached to a saved SQLite connection, they're attached the next
+/// time it's established. The cached schema is dropped since it lists their tables.
+#[tauri::command]
+#[specta::specta]
+pub async fn set_attached_databases(
+    storage: State<'_, Storage>,
+    app: tauri::AppHandle,
+    conn_id: i64,
+    databases: Vec<AttachedDatabase>,
+) -> Result<()> {
+    storage
+        .update_attached_databases(conn_id, &databases)
+        .await?;
+    storage.delete_schema_cache(conn_id).await?;
+    log::info!(id = conn_id; "Connection attached databases updated");
+
+    ConnectionsChanged.emit(&app).unwrap();
+    log::debug!("Event emitted: {:?}", ConnectionsChanged);
+
+    Ok(())
+}
+
+/// Replace the extensions loaded by a saved SQLite connection, they're loaded the next time
+/// it's established.
+#[tauri::command]
+#[specta::specta]
+pub async fn set_sqlite_extensions(
+    storage: State<'_, Storage>,
+    app: tauri::AppHandle,
+    conn_id: i64,
+    extensions: Vec<SqliteExtension>,
+) -> Result<()> {
+    storage
+        .update_sqlite_extensions(conn_id, &extensions)
+        .await?;
+    log::info!(id = conn_id; "Connection extensions updated");
+
+    ConnectionsChanged.emit(&app).unwrap();
+    log::debug!("Event emitted: {:?}", ConnectionsChanged);
+
+    Ok(())
+}
+
+/// Replace the SQLCipher key of a saved SQLite connection, `None` removes it. The key is
+/// kept in the secret store rather than in the connection.
+#[tauri::command]
+#[specta::specta]
+pub async fn set_sqlite_key(
+    storage: State<'_, Storage>,
+    app: tauri::AppHandle,
+    conn_id: i64,
+    key: Option<String>,
+) -> Result<()> {
+    let key = key.map(SecretString::new);
+    storage.update_sqlite_key(conn_id, key.as_ref()).await?;
+    log::info!(id = conn_id; "Connection key updated");
+
+    ConnectionsChanged.emit(&app).unwrap();
+    log::debug!("Event emitted: {:?}", ConnectionsChanged);
+
+    Ok(())
+}
+
+/// Replace the password or key passphrase of a saved connection's SSH tunnel, `None`
+/// removes it.
+#[tauri::command]
+#[specta::specta]
+pub async fn set_ssh_secret(
+    storage: State<'_, Storage>,
+    app: tauri::AppHandle,
+    conn_id: i64,
+    secret: Option<String>,
+) -> Result<()> {
+    let secret = secret.map(SecretString::new);
+    storage.update_ssh_secret(conn_id, secret.as_ref()).await?;
+    log::info!(id = conn_id; "Connection SSH secret updated");
+
+    ConnectionsChanged.emit(&app).unwrap();
+    log::debug!("Event emitted: {:?}", ConnectionsChanged);
+
+    Ok(())
+}
+
 #[tauri::command]
 #[specta::specta]
 pub async fn establish_connection(
@@ -65,49 +317,140 @@ pub async fn establish_connection(
     conn_id: i64,
 ) -> Result<()> {
     let connection = storage.get_connection_by_id(conn_id).await?;
-    let conn =
-        DatabaseConnection::connect(&connection.connection_string, &connection.driver).await?;
+    let url = connection.url()?;
+    let options = connection.connect_options();
+    let cached_schema = storage.get_schema_cache(conn_id).await?;
+    let is_cached = cached_schema.is_some();
+    let conn = match cached_schema {
+        Some(schema) => {
+            DatabaseConnection::connect_with_schema(&url, &connection.driver, &options, schema)
+                .await?
+        }
+        None => {
+            let conn = DatabaseConnection::connect(&url, &connection.driver, &options).await?;
+            save_schema_cache(&storage, conn_id, &conn).await;
+            conn
+        }
+    };
     let main_state = app.state::<Arc<Mutex<SharedState>>>();
     let mut state = main_state.lock().await;
 
+    disconnect(&storage, &mut state).await;
     state.conn = Some(conn);
+    state.conn_id = Some(conn_id);
+    state.safety_level = connection.safety_level;
+
+    if is_cached {
+        tauri::async_runtime::spawn(revalidate_schema_cache(app.clone(), conn_id));
+    }
 
     #[cfg(feature = "metax")]
     {
         use crate::state::{MetaXState, MetaXStatus};
 
-        let (command_child, status) =
-            match spawn_sidecar(&app, &connection.driver, connection.connection_string) {
-                Err(_) => (None, MetaXStatus::Exited),
-                Ok((mut rx, child)) => {
-                    let shared = main_state.inner().clone();
-                    tauri::async_runtime::spawn(async move {
-                        while let Some(event) = rx.recv().await {
-                            match event {
-                                CommandEvent::Error(_) | CommandEvent::Terminated(_) => {
-                                    let mut shared = shared.lock().await;
-                                    shared.metax.status = MetaXStatus::Exited;
-                                    shared.metax.command_child = None;
-                                    break;
-                                }
-                                CommandEvent::Stderr(_) | CommandEvent::Stdout(_) => {}
-                                _ => todo!(),
+        let (command_child, status) = match spawn_sidecar(&app, &connection) {
+            Err(_) => (None, MetaXStatus::Exited),
+            Ok((mut rx, child)) => {
+                let shared = main_state.inner().clone();
+                tauri::async_runtime::spawn(async move {
+                    while let Some(event) = rx.recv().await {
+                        match event {
+                            CommandEvent::Error(_) | CommandEvent::Terminated(_) => {
+                                let mut shared = shared.lock().await;
+                                shared.metax.status = MetaXStatus::Exited;
+                                shared.metax.command_child = None;
+                                break;
                             }
+                            CommandEvent::Stderr(_) | CommandEvent::Stdout(_) => {}
+                            _ => todo!(),
                         }
-                    });
-                    (Some(child), MetaXStatus::Active)
-                }
-            };
+                    }
+                });
+                (Some(child), MetaXStatus::Active)
+            }
+        };
         state.metax = MetaXState::new(command_child, status);
     }
 
     Ok(())
 }
 
+/// Compare the cached schema's fingerprint with the database's, refreshing the
+/// schema and its cache if the database changed since it was cached.
+async fn revalidate_schema_cache(app: AppHandle, conn_id: i64) {
+    let main_state = app.state::<Arc<Mutex<SharedState>>>();
+    // Discovery can be slow, so it uses a clone sharing the connection's pool
+    // instead of holding the state's lock.
+    let mut conn = {
+        let state = main_state.lock().await;
+        match (state.conn_id, state.conn.as_ref()) {
+            (Some(id), Some(conn)) if id == conn_id => conn.clone(),
+            // The connection was dropped or replaced in the meantime.
+            _ => return,
+        }
+    };
+
+    let fingerprint = match conn.schema_fingerprint().await {
+        Ok(fingerprint) => fingerprint,
+        Err(e) => {
+            log::warn!(id = conn_id; "Failed to revalidate schema cache: {e}");
+            return;
+        }
+    };
+    if conn.get_schema().await.fingerprint.as_ref() == Some(&fingerprint) {
+        log::debug!(id = conn_id; "Schema cache is up to date");
+        return;
+    }
+
+    if let Err(e) = conn.refresh_schema(None).await {
+        log::warn!(id = conn_id; "Failed to refresh outdated schema: {e}");
+        return;
+    }
+    save_schema_cache(&app.state::<Storage>(), conn_id, &conn).await;
+
+    let mut state = main_state.lock().await;
+    if state.conn_id != Some(conn_id) {
+        return;
+    }
+    let Some(current) = state.conn.as_mut() else {
+        return;
+    };
+    current.set_schema(conn.get_schema().await);
+    drop(state);
+    log::info!(id = conn_id; "Schema cache was outdated and has been refreshed");
+
+    SchemaChanged.emit(&app).unwrap();
+    log::debug!("Event emitted: {:?}", SchemaChanged);
+}
+
+/// Persist the connection's schema, a failure is only logged since the cache is optional.
+pub(crate) async fn save_schema_cache(storage: &Storage, conn_id: i64, conn: &DatabaseConnection) {
+    if let Err(e) = storage
+        .save_schema_cache(conn_id, &conn.get_schema().await)
+        .await
+    {
+        log::warn!(id = conn_id; "Failed to save schema cache: {e}");
+    }
+}
+
+/// Close the open connection and reset the state tied to it.
+pub(crate) async fn disconnect(storage: &Storage, state: &mut SharedState) {
+    if let Some(conn) = state.conn.take() {
+        // Keep the tables discovered during the session for the next time.
+        if let Some(conn_id) = state.conn_id {
+            save_schema_cache(storage, conn_id, &conn).await;
+        }
+        conn.close().await;
+    }
+    state.conn_id = None;
+    state.safety_level = SafetyLevel::default();
+    state.cleanup();
+}
+
 #[tauri::command]
 #[specta::specta]
-pub async fn drop_connection(state: AppState<'_>) -> Result<()> {
-    state.lock().await.cleanup();
+pub async fn drop_connection(storage: State<'_, Storage>, state: AppState<'_>) -> Result<()> {
+    disconnect(&storage, &mut *state.lock().await).await;
 
     Ok(())
 }
@@ -136,27 +479,26 @@ pub async fn start_metax(
         let mut state = _state.lock().await;
         let connection = storage.get_connection_by_id(conn_id).await?;
 
-        let (command_child, status) =
-            match spawn_sidecar(&app, &connection.driver, connection.connection_string) {
-                Err(_) => (None, MetaXStatus::Exited),
-                Ok((mut rx, child)) => {
-                    tauri::async_runtime::spawn(async move {
-                        while let Some(event) = rx.recv().await {
-                            match event {
-                                CommandEvent::Error(_) | CommandEvent::Terminated(_) => {
-                                    let mut shared = shared.lock().await;
-                                    shared.metax.status = MetaXStatus::Exited;
-                                    shared.metax.command_child = None;
-                                    break;
-                                }
-                                CommandEvent::Stderr(_) | CommandEvent::Stdout(_) => {}
-                                _ => todo!(),
+        let (command_child, status) = match spawn_sidecar(&app, &connection) {
+            Err(_) => (None, MetaXStatus::Exited),
+            Ok((mut rx, child)) => {
+                tauri::async_runtime::spawn(async move {
+                    while let Some(event) = rx.recv().await {
+                        match event {
+                            CommandEvent::Error(_) | CommandEvent::Terminated(_) => {
+                                let mut shared = shared.lock().await;
+                                shared.metax.status = MetaXStatus::Exited;
+                                shared.metax.command_child = None;
+                                break;
                             }
+                            CommandEvent::Stderr(_) | CommandEvent::Stdout(_) => {}
+                            _ => todo!(),
                         }
-                    });
-                    (Some(child), MetaXStatus::Active)
-                }
-            };
+                    }
+                });
+                (Some(child), MetaXStatus::Active)
+            }
+        };
 
         state.metax = MetaXState::new(command_child, status);
     }
@@ -182,27 +524,27 @@ pub async fn get_metax_status(_state: AppState<'_>) -> Result<MetaXStatus> {
 #[cfg(feature = "metax")]
 fn spawn_sidecar(
     app: &AppHandle,
-    driver: &Drivers,
-    conn_string: String,
+    connection: &ConnConfig,
 ) -> Result<(Receiver<CommandEvent>, CommandChild)> {
-    let args = match driver {
-        Drivers::SQLite => {
-            let (_, after) = conn_string.split_once(':').unwrap();
-            vec!["sqlite3", "-f", after]
+    let args = match &connection.params {
+        ConnectionParams::SQLite(params) => {
+            vec![
+                "sqlite3".to_string(),
+                "-f".to_string(),
+                params.file_path.clone(),
+            ]
         }
-        Drivers::PostgreSQL => vec!["pg", "--url", conn_string.as_str()],
-        Drivers::MySQL => {
-            let stripped_conn_string = conn_string.strip_prefix("mysql://").unwrap();
-
-            use regex::Regex;
-
-            let re = Regex::new(r"@(.+?)/").unwrap();
-
-            re.replace_all(stripped_conn_string, |caps: &regex::Captures| {
-                format!("@tcp({})/", &caps[1])
-            });
-
-            vec!["mysql", "--url", stripped_conn_string]
+        ConnectionParams::PostgreSQL(_) => {
+            vec!["pg".to_string(), "--url".to_string(), connection.url()?]
+        }
+        // Go's MySQL driver expects a DSN rather than a URL.
+        ConnectionParams::MySQL(params) => {
+            let password = connection.password.as_ref().map_or("", |p| p.expose());
+            let dsn = format!(
+                "{}:{password}@tcp({}:{})/{}",
+                params.user, params.host, params.port, params.database
+            );
+            vec!["mysql".to_string(), "--url".to_string(), dsn]
         }
     };
 
@@ -245,6 +587,18 @@ pub async fn get_connections(storage: State<'_, Storage>) -> Result<Vec<ConnConf
     Ok(connections)
 }
 
+/// Saved connections matching `filter`, sorted by `sort` then by name.
+#[tauri::command]
+#[specta::specta]
+pub async fn filter_connections(
+    storage: State<'_, Storage>,
+    filter: ConnectionFilter,
+    sort: ConnectionSort,
+) -> Result<Vec<ConnConfig>> {
+    let connections = storage.filter_connections(&filter, &sort).await?;
+    Ok(connections)
+}
+
 #[tauri::command]
 #[specta::specta]
 pub async fn get_connection_details(
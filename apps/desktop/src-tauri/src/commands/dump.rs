@@ -0,0 +1,83 @@
+use crate::AppState;
+use tauri::AppHandle;
+use tauri_specta::Event;
+use tx_handlers::{DumpOptions, DumpSummary, RestoreOptions, RestoreSummary};
+use tx_lib::{
+    Result,
+    events::{DumpProgress, RestoreProgress, SchemaChanged, TableContentsChanged},
+};
+
+/// Write a SQL script recreating the open connection's database.
+#[tauri::command]
+#[specta::specta]
+pub async fn dump_database(
+    app: AppHandle,
+    state: AppState<'_>,
+    options: DumpOptions,
+) -> Result<DumpSummary> {
+    let mut conn = state.lock().await.conn.clone().unwrap();
+
+    let summary = tx_handlers::dump_database(&mut conn, &options, |rows_dumped| {
+        let progress = DumpProgress {
+            path: options.path.clone(),
+            rows_dumped,
+        };
+        progress.emit(&app).unwrap();
+        log::debug!("Event emitted: {:?}", progress);
+    })
+    .await?;
+    log::info!(
+        "Dumped {} tables and {} rows to {}",
+        summary.tables.len(),
+        summary.rows_dumped,
+        options.path
+    );
+
+    Ok(summary)
+}
+
+/// Execute the statements of a SQL script on the open connection. The script can drop
+/// tables, so it must be `confirmed` on connections confirming destructive statements.
+#[tauri::command]
+#[specta::specta]
+pub async fn restore_database(
+    app: AppHandle,
+    state: AppState<'_>,
+    options: RestoreOptions,
+    confirmed: bool,
+) -> Result<RestoreSummary> {
+    let mut state = state.lock().await;
+    state
+        .safety_level
+        .confirm_destructive("Restore", confirmed)?;
+    let conn = state.conn.as_mut().unwrap();
+
+    let summary = tx_handlers::restore_database(
+        conn,
+        &options,
+        |statements_executed, bytes_read, total_bytes| {
+            let progress = RestoreProgress {
+                path: options.path.clone(),
+                statements_executed,
+                bytes_read,
+                total_bytes,
+            };
+            progress.emit(&app).unwrap();
+            log::debug!("Event emitted: {:?}", progress);
+        },
+    )
+    .await?;
+    log::info!(
+        "Restored {}, {} statements executed and {} failed",
+        options.path,
+        summary.statements_executed,
+        summary.errors.len()
+    );
+
+    SchemaChanged.emit(&app).unwrap();
+    log::debug!("Event emitted: {:?}", SchemaChanged);
+    TableContentsChanged.emit(&app).unwrap();
+    log::debug!("Event emitted: {:?}", TableContentsChanged);
+
+    Ok(summary)
+}
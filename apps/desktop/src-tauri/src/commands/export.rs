@@ -0,0 +1,93 @@
+use crate::{
+    AppState,
+    commands::row::{FilteringData, SortingData, rows_query},
+};
+use sea_query::Values;
+use sea_query_binder::{SqlxBinder, SqlxValues};
+use tauri::AppHandle;
+use tauri_specta::Event;
+use tx_handlers::{ExportOptions, ExportSummary, export_rows};
+use tx_lib::{Result, events::ExportProgress, safety};
+
+/// Export the rows of a table matching `filtering`, ordered by `sorting`.
+///
+/// The connection is cloned so the state isn't locked for the whole export.
+#[tauri::command]
+#[specta::specta]
+pub async fn export_table(
+    app: AppHandle,
+    state: AppState<'_>,
+    table_name: String,
+    sorting: Vec<SortingData>,
+    filtering: Vec<FilteringData>,
+    options: ExportOptions,
+) -> Result<ExportSummary> {
+    let mut state = state.lock().await;
+    let conn = state.conn.as_mut().unwrap();
+    let table = conn.table_info(&table_name).await?;
+    let conn = conn.clone();
+    drop(state);
+
+    let (stmt, values) = rows_query(&conn, &table_name, &sorting, &filtering)
+        .build_any_sqlx(conn.into_builder().as_ref());
+    let summary = export_rows(
+        &conn,
+        &stmt,
+        values,
+        Some(&table),
+        &options,
+        |rows_exported| emit_progress(&app, &options, rows_exported),
+    )
+    .await?;
+    log::info!(
+        "Exported {} rows of {table_name} to {}",
+        summary.rows_exported,
+        options.path
+    );
+
+    Ok(summary)
+}
+
+/// Export the rows returned by a raw query, on a clone of the connection like
+/// [`export_table`]. Statements other than queries are rejected, they'd run unconfirmed.
+#[tauri::command]
+#[specta::specta]
+pub async fn export_query(
+    app: AppHandle,
+    state: AppState<'_>,
+    query: String,
+    options: ExportOptions,
+) -> Result<ExportSummary> {
+    let (conn, safety_level) = {
+        let state = state.lock().await;
+        (state.conn.clone().unwrap(), state.safety_level)
+    };
+    safety_level.check_sql(&query, &conn.driver())?;
+    safety::check_query(&query, &conn.driver())?;
+
+    let summary = export_rows(
+        &conn,
+        &query,
+        SqlxValues(Values(vec![])),
+        None,
+        &options,
+        |rows_exported| emit_progress(&app, &options, rows_exported),
+    )
+    .await?;
+    log::info!(
+        "Exported {} rows of query to {}",
+        summary.rows_exported,
+        options.path
+    );
+
+    Ok(summary)
+}
+
+fn emit_progress(app: &AppHandle, options: &ExportOptions, rows_exported: u64) {
+    let progress = ExportProgress {
+        path: options.path.clone(),
+        rows_exported,
+    };
+    progress.emit(app).unwrap();
+    log::debug!("Event emitted: {:?}", progress);
+}
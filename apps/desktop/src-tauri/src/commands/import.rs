@@ -0,0 +1,118 @@
+use crate::{AppState, commands::connection::disconnect, state::Storage};
+use std::path::Path;
+use tauri::{AppHandle, State};
+use tauri_specta::Event;
+use tx_handlers::{DataFile, ImportFormat, ImportOptions, ImportPreview, ImportSummary};
+use tx_lib::{
+    Result,
+    events::{ImportProgress, SchemaChanged, TableContentsChanged},
+};
+
+/// Read the first `limit` rows of a file along with the types inferred for its columns.
+#[tauri::command]
+#[specta::specta]
+pub async fn preview_import(
+    path: String,
+    format: ImportFormat,
+    limit: u32,
+) -> Result<ImportPreview> {
+    tx_handlers::preview_import(&path, &format, limit)
+}
+
+/// Insert the rows of a file into a table of the open connection.
+#[tauri::command]
+#[specta::specta]
+pub async fn import_file(
+    app: AppHandle,
+    state: AppState<'_>,
+    options: ImportOptions,
+) -> Result<ImportSummary> {
+    let mut state = state.lock().await;
+    state.safety_level.require_write("Import")?;
+    let conn = state.conn.as_mut().unwrap();
+
+    let summary = tx_handlers::import_file(conn, &options, |rows_imported| {
+        let progress = ImportProgress {
+            path: options.path.clone(),
+            rows_imported,
+        };
+        progress.emit(&app).unwrap();
+        log::debug!("Event emitted: {:?}", progress);
+    })
+    .await?;
+    log::info!(
+        "Imported {} rows of {} into {}, {} failed",
+        summary.rows_imported,
+        options.path,
+        options.table_name,
+        summary.rows_failed
+    );
+
+    if summary.created_table {
+        SchemaChanged.emit(&app).unwrap();
+        log::debug!("Event emitted: {:?}", SchemaChanged);
+    }
+    TableContentsChanged.emit(&app).unwrap();
+    log::debug!("Event emitted: {:?}", TableContentsChanged);
+
+    Ok(summary)
+}
+
+/// Load a CSV, JSON or Parquet file into an in-memory SQLite database and make it the
+/// open connection.
+#[tauri::command]
+#[specta::specta]
+pub async fn open_data_file(
+    app: AppHandle,
+    storage: State<'_, Storage>,
+    state: AppState<'_>,
+    path: String,
+    format: ImportFormat,
+) -> Result<DataFile> {
+    let (conn, file) = tx_handlers::open_data_file(&path, &format, |rows_imported| {
+        let progress = ImportProgress {
+            path: path.clone(),
+            rows_imported,
+        };
+        progress.emit(&app).unwrap();
+        log::debug!("Event emitted: {:?}", progress);
+    })
+    .await?;
+    log::info!(
+        "Opened {path} as table {}, {} rows failed",
+        file.table_name,
+        file.import.rows_failed
+    );
+
+    let mut state = state.lock().await;
+    disconnect(&storage, &mut state).await;
+    state.conn = Some(conn);
+
+    Ok(file)
+}
+
+/// Save the open SQLite database to a file, e.g to keep an opened data file as a `.db`
+/// file. The file is replaced once the copy is complete if it exists.
+#[tauri::command]
+#[specta::specta]
+pub async fn save_database_file(state: AppState<'_>, path: String) -> Result<()> {
+    let conn = state.lock().await.conn.clone().unwrap();
+
+    // `VACUUM INTO` requires a file that doesn't exist, it's written next to the target
+    // so it can be renamed over it.
+    let temp_path = format!("{path}.part");
+    if Path::new(&temp_path).exists() {
+        std::fs::remove_file(&temp_path)?;
+    }
+    let saved = match conn.vacuum_into(&temp_path).await {
+        Ok(()) => std::fs::rename(&temp_path, &path).map_err(Into::into),
+        Err(e) => Err(e),
+    };
+    if let Err(e) = saved {
+        let _ = std::fs::remove_file(&temp_path);
+        return Err(e);
+    }
+    log::info!("Database saved to {path}");
+
+    Ok(())
+}
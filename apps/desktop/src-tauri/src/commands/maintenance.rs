@@ -0,0 +1,104 @@
+use crate::AppState;
+use tauri::AppHandle;
+use tauri_specta::Event;
+use tx_handlers::{
+    BackupSummary, CheckpointMode, IntegrityCheck, Pragma, VacuumSummary, WalCheckpoint,
+};
+use tx_lib::{Result, events::BackupProgress};
+
+/// Rebuild the open SQLite database, releasing the space left by deleted rows.
+#[tauri::command]
+#[specta::specta]
+pub async fn vacuum_database(state: AppState<'_>) -> Result<VacuumSummary> {
+    let state = state.lock().await;
+    state.safety_level.require_write("VACUUM")?;
+    let conn = state.conn.as_ref().unwrap();
+
+    let summary = tx_handlers::vacuum(conn).await?;
+    log::info!(
+        "Vacuumed database, {} bytes reclaimed",
+        summary.bytes_reclaimed
+    );
+
+    Ok(summary)
+}
+
+/// Write a vacuumed copy of the open SQLite database to `path`, which must not exist.
+#[tauri::command]
+#[specta::specta]
+pub async fn vacuum_database_into(state: AppState<'_>, path: String) -> Result<VacuumSummary> {
+    let state = state.lock().await;
+    let conn = state.conn.as_ref().unwrap();
+
+    let summary = tx_handlers::vacuum_into(conn, &path).await?;
+    log::info!("Vacuumed database into {path}");
+
+    Ok(summary)
+}
+
+/// Look for corruption in the open SQLite database.
+#[tauri::command]
+#[specta::specta]
+pub async fn check_integrity(state: AppState<'_>, quick: bool) -> Result<IntegrityCheck> {
+    let state = state.lock().await;
+    let conn = state.conn.as_ref().unwrap();
+
+    tx_handlers::integrity_check(conn, quick).await
+}
+
+/// Gather statistics about the open SQLite database for the query planner.
+#[tauri::command]
+#[specta::specta]
+pub async fn analyze_database(state: AppState<'_>) -> Result<()> {
+    let state = state.lock().await;
+    state.safety_level.require_write("ANALYZE")?;
+    let conn = state.conn.as_ref().unwrap();
+
+    tx_handlers::analyze(conn).await
+}
+
+/// Copy the frames of the open SQLite database's WAL file back into the database.
+#[tauri::command]
+#[specta::specta]
+pub async fn checkpoint_wal(state: AppState<'_>, mode: CheckpointMode) -> Result<WalCheckpoint> {
+    let state = state.lock().await;
+    state.safety_level.require_write("WAL checkpoint")?;
+    let conn = state.conn.as_ref().unwrap();
+
+    tx_handlers::wal_checkpoint(conn, mode).await
+}
+
+/// Copy the open SQLite database to `path` while it's in use, with the online backup API.
+#[tauri::command]
+#[specta::specta]
+pub async fn backup_database(
+    app: AppHandle,
+    state: AppState<'_>,
+    path: String,
+) -> Result<BackupSummary> {
+    let conn = state.lock().await.conn.clone().unwrap();
+
+    let summary = tx_handlers::backup_database(&conn, &path, |pages_copied, total_pages| {
+        let progress = BackupProgress {
+            path: path.clone(),
+            pages_copied,
+            total_pages,
+        };
+        progress.emit(&app).unwrap();
+        log::debug!("Event emitted: {:?}", progress);
+    })
+    .await?;
+    log::info!("Backed up {} pages to {path}", summary.pages_copied);
+
+    Ok(summary)
+}
+
+/// Current values of the open SQLite connection's pragmas.
+#[tauri::command]
+#[specta::specta]
+pub async fn get_pragmas(state: AppState<'_>) -> Result<Vec<Pragma>> {
+    let state = state.lock().await;
+    let conn = state.conn.as_ref().unwrap();
+
+    tx_handlers::list_pragmas(conn).await
+}
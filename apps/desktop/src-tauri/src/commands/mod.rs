@@ -1,4 +1,9 @@
 pub mod connection;
+pub mod dump;
+pub mod export;
 pub mod fs;
+pub mod import;
+pub mod maintenance;
 pub mod row;
+pub mod secret;
 pub mod table;
@@ -1,14 +1,16 @@
 use crate::AppState;
-use sea_query::{Asterisk, Cond, Expr, ExprTrait, Order};
+use sea_query::{Asterisk, Cond, Expr, ExprTrait, Order, SelectStatement};
 use sea_query_binder::SqlxBinder;
 use sea_schema::sea_query;
-use sea_schema::sea_query::{Alias, Iden, Query};
+use sea_schema::sea_query::{Alias, Iden, IntoTableRef, Query, TableRef};
 use serde::{Deserialize, Serialize};
 use serde_json::Value as JsonValue;
 use specta::Type;
 use tauri::AppHandle;
 use tauri_specta::Event;
-use tx_handlers::{CustomColumnType, DecodedRow, ExecResult, RowRecord, decode_raw_rows};
+use tx_handlers::{
+    CustomColumnType, DatabaseConnection, DecodedRow, ExecResult, RowRecord, decode_raw_rows,
+};
 use tx_lib::{Result, events::TableContentsChanged, types::FKRows};
 
 #[derive(Serialize, Deserialize, Default, Debug, Type)]
@@ -24,11 +26,22 @@ impl PaginatedRows {
     }
 }
 
-struct PlainTable(String);
+/// A table name, those qualified by the alias of a SQLite database attached to the
+/// connection (e.g `logs.events`) refer to the table in that database.
+struct PlainTable(TableRef);
 
-impl Iden for PlainTable {
-    fn unquoted(&self, s: &mut dyn std::fmt::Write) {
-        write!(s, "{}", self.0).unwrap();
+impl PlainTable {
+    fn new(conn: &DatabaseConnection, table_name: &str) -> Self {
+        PlainTable(match conn.attached_table(table_name) {
+            Some((alias, table)) => (Alias::new(alias), Alias::new(table)).into_table_ref(),
+            None => Alias::new(table_name).into_table_ref(),
+        })
+    }
+}
+
+impl IntoTableRef for PlainTable {
+    fn into_table_ref(self) -> TableRef {
+        self.0
     }
 }
 
@@ -57,28 +70,28 @@ struct PaginationData {
 }
 
 #[derive(Serialize, Deserialize, Type)]
-struct SortingData {
+pub struct SortingData {
     column: String,
     ordering: ColumnOrdering,
 }
 
-#[derive(Serialize, Deserialize, Type)]
+#[derive(Serialize, Deserialize, Type, Debug)]
 #[serde(rename_all = "camelCase")]
-enum ColumnOrdering {
+pub enum ColumnOrdering {
     Asc,
     Desc,
 }
 
 #[derive(Serialize, Deserialize, Type, Debug)]
 #[serde(rename_all = "camelCase")]
-struct FilteringData {
+pub struct FilteringData {
     column: String,
     filters: Filters,
 }
 
 #[derive(Serialize, Deserialize, Type, Debug)]
 #[serde(rename_all = "camelCase")]
-enum Filters {
+pub enum Filters {
     Gt(JsonValue),
     Gte(JsonValue),
     Lt(JsonValue),
@@ -122,12 +135,36 @@ pub async fn get_paginated_rows(
     let state = state.lock().await;
     let conn = state.conn.as_ref().unwrap();
 
+    let (stmt, values) = rows_query(
+        conn,
+        &payload.table_name,
+        &payload.sorting,
+        &payload.filtering,
+    )
+    .limit(payload.pagination.page_size)
+    .offset(payload.pagination.page_index * payload.pagination.page_size)
+    .build_any_sqlx(conn.into_builder().as_ref());
+
+    let rows = conn.fetch_all(&stmt, values).await?;
+
+    let page_count = rows.len().div_ceil(payload.pagination.page_size as usize);
+
+    let paginated_rows = PaginatedRows::new(decode_raw_rows(rows).unwrap(), page_count);
+
+    Ok(paginated_rows)
+}
+
+/// Query selecting a table's rows matching `filtering`, ordered by `sorting`.
+pub fn rows_query(
+    conn: &DatabaseConnection,
+    table_name: &str,
+    sorting: &[SortingData],
+    filtering: &[FilteringData],
+) -> SelectStatement {
     let mut query = Query::select()
         .column(Asterisk)
-        .from(PlainTable(payload.table_name))
-        .limit(payload.pagination.page_size)
-        .offset(payload.pagination.page_index * payload.pagination.page_size)
-        .order_by_columns(payload.sorting.iter().map(|s| {
+        .from(PlainTable::new(conn, table_name))
+        .order_by_columns(sorting.iter().map(|s| {
             (
                 PlainColumn(s.column.clone()),
                 match s.ordering {
@@ -138,7 +175,7 @@ pub async fn get_paginated_rows(
         }))
         .to_owned();
 
-    payload.filtering.iter().for_each(|f| {
+    filtering.iter().for_each(|f| {
         let expression = Expr::col(PlainColumn(f.column.clone()));
 
         let simple_express = match &f.filters {
@@ -161,15 +198,7 @@ pub async fn get_paginated_rows(
 
         query.and_where(simple_express);
     });
-    let (stmt, values) = query.build_any_sqlx(conn.into_builder().as_ref());
-
-    let rows = conn.fetch_all(&stmt, values).await?;
-
-    let page_count = rows.len().div_ceil(payload.pagination.page_size as usize);
-
-    let paginated_rows = PaginatedRows::new(decode_raw_rows(rows).unwrap(), page_count);
-
-    Ok(paginated_rows)
+    query
 }
 
 #[tauri::command]
@@ -188,18 +217,20 @@ pub async fn delete_rows(
     for matrix in pk_cols {
         let mut sub_condition = Cond::all();
         for record in matrix {
+            let column = PlainColumn(record.column_name.clone());
             sub_condition =
-                sub_condition.add(Expr::col(PlainColumn(record.column_name.clone())).eq(record))
+                sub_condition.add(Expr::col(column).eq(sea_query::Value::try_from(record)?))
         }
 
         delete_condition = delete_condition.add(sub_condition);
     }
 
     let (stmt, values) = Query::delete()
-        .from_table(PlainTable(table_name))
+        .from_table(PlainTable::new(conn, &table_name))
         .cond_where(delete_condition)
         .build_any_sqlx(conn.into_builder().as_ref());
 
+    state.safety_level.check_sql(&stmt, &conn.driver())?;
     let result = conn.execute_with(stmt.as_str(), values).await;
 
     if result.is_ok() {
@@ -220,15 +251,17 @@ pub async fn create_row(
 ) -> Result<ExecResult> {
     let state = state.lock().await;
     let conn = state.conn.as_ref().unwrap();
+    let row_values = data
+        .iter()
+        .map(|val| sea_query::Value::try_from(val.clone()).map(sea_query::SimpleExpr::Value))
+        .collect::<Result<Vec<_>>>()?;
     let (stmt, values) = Query::insert()
-        .into_table(Alias::new(table_name))
+        .into_table(PlainTable::new(conn, &table_name))
         .columns(data.iter().map(|k| PlainColumn(k.column_name.clone())))
-        .values_panic(data.iter().map(|val| {
-            let sea_query_val: sea_query::Value = val.clone().into();
-            sea_query::SimpleExpr::Value(sea_query_val)
-        }))
+        .values_panic(row_values)
         .build_any_sqlx(conn.into_builder().as_ref());
 
+    state.safety_level.check_sql(&stmt, &conn.driver())?;
     let result = conn.execute_with(stmt.as_str(), values).await;
 
     if result.is_ok() {
@@ -266,23 +299,28 @@ pub async fn update_row(
     let mut update_condition = Cond::all();
 
     for record in pk_cols {
+        let column = PlainColumn(record.column_name.clone());
         update_condition =
-            update_condition.add(Expr::col(PlainColumn(record.column_name.clone())).eq(record));
+            update_condition.add(Expr::col(column).eq(sea_query::Value::try_from(record)?));
     }
 
-    let (stmt, values) = Query::update()
-        .table(PlainTable(table_name))
-        .values(data.iter().map(|r| {
-            let sea_query_val: sea_query::Value = r.clone().into();
-            let mut expr = sea_query::SimpleExpr::Value(sea_query_val);
+    let row_values = data
+        .iter()
+        .map(|r| {
+            let mut expr = sea_query::SimpleExpr::Value(sea_query::Value::try_from(r.clone())?);
             if let CustomColumnType::Enum(def) = &r.column_type {
                 expr = expr.as_enum(DynEnum(def.name.clone()))
             }
-            (PlainColumn(r.column_name.clone()), expr)
-        }))
+            Ok((PlainColumn(r.column_name.clone()), expr))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    let (stmt, values) = Query::update()
+        .table(PlainTable::new(conn, &table_name))
+        .values(row_values)
         .cond_where(update_condition)
         .build_any_sqlx(conn.into_builder().as_ref());
 
+    state.safety_level.check_sql(&stmt, &conn.driver())?;
     let result = conn.execute_with(stmt.as_str(), values).await;
 
     if result.is_ok() {
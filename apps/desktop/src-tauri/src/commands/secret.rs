@@ -0,0 +1,47 @@
+use crate::state::Storage;
+use tauri::State;
+use tauri_specta::Event;
+use tx_lib::{Result, events::ConnectionsChanged, types::SecretString};
+
+/// Whether secrets must be unlocked before connecting, with the master password or once
+/// the keyfile is restored.
+#[tauri::command]
+#[specta::specta]
+pub async fn secrets_locked(storage: State<'_, Storage>) -> Result<bool> {
+    Ok(storage.secrets_locked().await)
+}
+
+/// Unlock the secrets with the master password, or read the keyfile again if no master
+/// password is set. Connections saved before secrets were encrypted are listed once
+/// unlocked.
+#[tauri::command]
+#[specta::specta]
+pub async fn unlock_secrets(
+    storage: State<'_, Storage>,
+    app: tauri::AppHandle,
+    password: String,
+) -> Result<()> {
+    storage.unlock_secrets(SecretString::new(password)).await?;
+    log::info!("Secrets unlocked");
+
+    ConnectionsChanged.emit(&app).unwrap();
+    log::debug!("Event emitted: {:?}", ConnectionsChanged);
+
+    Ok(())
+}
+
+/// Encrypt the secrets with a key derived from `password`, or with the local keyfile's if
+/// it's `None`.
+#[tauri::command]
+#[specta::specta]
+pub async fn set_master_password(
+    storage: State<'_, Storage>,
+    password: Option<String>,
+) -> Result<()> {
+    storage
+        .set_master_password(password.map(SecretString::new))
+        .await?;
+    log::info!("Master password updated");
+
+    Ok(())
+}
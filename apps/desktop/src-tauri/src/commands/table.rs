@@ -1,45 +1,498 @@
-use crate::AppState;
+use crate::{
+    AppState,
+    commands::connection::save_schema_cache,
+    state::{DataComparison, Storage},
+};
 use sea_query_binder::SqlxValues;
 use serde::{Deserialize, Serialize};
+use serde_json::{Map as JsonMap, Value as JsonValue};
 use specta::Type;
-use sqlparser::{
-    ast::Statement,
-    dialect::{Dialect, MySqlDialect, PostgreSqlDialect, SQLiteDialect},
-    parser::Parser,
+use sqlparser::ast::{AlterTableOperation, ObjectName, ObjectType, Statement};
+use tauri::{AppHandle, State};
+use tauri_specta::Event;
+use tx_handlers::{
+    DataDiff, DatabaseConnection, DecodedRow, ExecResult, Schema, SchemaChange, SchemaDiff,
+    TableDiscoveryFailure, TableInfo, TransferMode, TransferOptions, TransferSummary,
+    decode_raw_rows,
+};
+use tx_lib::{
+    Result, TxError,
+    events::{SchemaChanged, TableContentsChanged, TransferProgress},
+    safety,
 };
-use tx_handlers::{DatabaseConnection, DecodedRow, ExecResult, TableInfo, decode_raw_rows};
-use tx_lib::Result;
 
 #[tauri::command]
 #[specta::specta]
-pub async fn discover_db_schema(state: AppState<'_>) -> Result<Vec<TableInfo>> {
+pub async fn discover_db_schema(
+    storage: State<'_, Storage>,
+    state: AppState<'_>,
+) -> Result<Vec<TableInfo>> {
+    let mut state = state.lock().await;
+    let conn_id = state.conn_id;
+    let conn = state.conn.as_mut().unwrap();
+    conn.discover_remaining_tables().await?;
+    if let Some(conn_id) = conn_id {
+        save_schema_cache(&storage, conn_id, conn).await;
+    }
+
+    Ok(conn.get_schema().await.tables())
+}
+
+#[tauri::command]
+#[specta::specta]
+pub async fn get_tables_names(state: AppState<'_>) -> Result<Vec<String>> {
     let state = state.lock().await;
     let conn = state.conn.as_ref().unwrap();
-    let schema_discovery = conn.get_schema().await.tables;
 
-    Ok(schema_discovery)
+    Ok(conn.get_schema().await.tables_names)
+}
+
+/// Get the SQL statements `change` would execute, without executing them.
+#[tauri::command]
+#[specta::specta]
+pub async fn preview_schema_change(
+    state: AppState<'_>,
+    change: SchemaChange,
+) -> Result<Vec<String>> {
+    let mut state = state.lock().await;
+    let conn = state.conn.as_mut().unwrap();
+
+    conn.schema_change_statements(&change).await
+}
+
+/// Execute the statements of `change`. Altering or dropping a table can drop data, so it
+/// must be `confirmed` on connections confirming destructive statements.
+#[tauri::command]
+#[specta::specta]
+pub async fn apply_schema_change(
+    app: AppHandle,
+    state: AppState<'_>,
+    change: SchemaChange,
+    confirmed: bool,
+) -> Result<()> {
+    let mut state = state.lock().await;
+    match &change {
+        SchemaChange::Create(_) => state.safety_level.require_write("Create table")?,
+        SchemaChange::Alter { .. } => state
+            .safety_level
+            .confirm_destructive("Alter table", confirmed)?,
+        SchemaChange::Drop { .. } => state
+            .safety_level
+            .confirm_destructive("Drop table", confirmed)?,
+    }
+    let conn = state.conn.as_mut().unwrap();
+
+    conn.apply_schema_change(&change).await?;
+    log::debug!("Schema change applied.");
+
+    SchemaChanged.emit(&app).unwrap();
+    log::debug!("Event emitted: {:?}", SchemaChanged);
+
+    Ok(())
+}
+
+/// Compare the schemas of two saved connections using the same driver, the diff's
+/// script migrates the `from` database so its schema matches the `to` database's.
+#[tauri::command]
+#[specta::specta]
+pub async fn compare_schemas(
+    storage: State<'_, Storage>,
+    from_conn_id: i64,
+    to_conn_id: i64,
+) -> Result<SchemaDiff> {
+    let from = storage.get_connection_by_id(from_conn_id).await?;
+    let to = storage.get_connection_by_id(to_conn_id).await?;
+    if from.driver != to.driver {
+        return Err(TxError::DriverMismatch(from.driver, to.driver));
+    }
+
+    let mut from_conn =
+        DatabaseConnection::connect(&from.url()?, &from.driver, &from.connect_options()).await?;
+    let mut to_conn =
+        match DatabaseConnection::connect(&to.url()?, &to.driver, &to.connect_options()).await {
+            Ok(conn) => conn,
+            Err(e) => {
+                from_conn.close().await;
+                return Err(e);
+            }
+        };
+
+    let diff = from_conn.diff_schema(&mut to_conn).await;
+    from_conn.close().await;
+    to_conn.close().await;
+    log::debug!("Schemas of connections {from_conn_id} and {to_conn_id} compared.");
+
+    diff
+}
+
+/// Compare the rows of two tables with the same primary key, which can belong to the
+/// same connection. The diff's script reconciles the target table with the source's rows.
+///
+/// Rows are compared a chunk at a time, `after` is the `next_key` of the previous chunk.
+/// The connections are kept open from the first chunk until the last one is compared.
+#[tauri::command]
+#[specta::specta]
+pub async fn compare_table_data(
+    storage: State<'_, Storage>,
+    state: AppState<'_>,
+    source_conn_id: i64,
+    source_table: String,
+    target_conn_id: i64,
+    target_table: String,
+    after: Option<JsonMap<String, JsonValue>>,
+) -> Result<DataDiff> {
+    let previous = state.lock().await.data_comparison.take();
+    let mut comparison = match previous {
+        Some(comparison)
+            if after.is_some() && comparison.compares(source_conn_id, target_conn_id) =>
+        {
+            comparison
+        }
+        previous => {
+            if let Some(previous) = previous {
+                previous.close().await;
+            }
+            open_data_comparison(&storage, source_conn_id, target_conn_id).await?
+        }
+    };
+
+    let diff = comparison
+        .source
+        .diff_table_data(
+            &source_table,
+            &mut comparison.target,
+            &target_table,
+            after.as_ref(),
+        )
+        .await;
+    log::debug!("Data of tables {source_table} and {target_table} compared.");
+
+    match &diff {
+        Ok(DataDiff {
+            next_key: Some(_), ..
+        }) => {
+            let other = state.lock().await.data_comparison.replace(comparison);
+            if let Some(other) = other {
+                other.close().await;
+            }
+        }
+        _ => comparison.close().await,
+    }
+    diff
+}
+
+async fn open_data_comparison(
+    storage: &Storage,
+    source_conn_id: i64,
+    target_conn_id: i64,
+) -> Result<DataComparison> {
+    let source = storage.get_connection_by_id(source_conn_id).await?;
+    let target = storage.get_connection_by_id(target_conn_id).await?;
+    if source.driver != target.driver {
+        return Err(TxError::DriverMismatch(source.driver, target.driver));
+    }
+
+    // Only the compared tables are discovered.
+    let source_conn = DatabaseConnection::connect_with_schema(
+        &source.url()?,
+        &source.driver,
+        &source.connect_options(),
+        Schema::default(),
+    )
+    .await?;
+    let target_conn = match DatabaseConnection::connect_with_schema(
+        &target.url()?,
+        &target.driver,
+        &target.connect_options(),
+        Schema::default(),
+    )
+    .await
+    {
+        Ok(conn) => conn,
+        Err(e) => {
+            source_conn.close().await;
+            return Err(e);
+        }
+    };
+
+    Ok(DataComparison {
+        source_conn_id,
+        target_conn_id,
+        source: source_conn,
+        target: target_conn,
+    })
+}
+
+/// Copy a table's rows to another saved connection, which can use another driver.
+/// Truncating the target table must be `confirmed` if the target connection confirms
+/// destructive statements.
+#[tauri::command]
+#[specta::specta]
+pub async fn transfer_table(
+    app: AppHandle,
+    storage: State<'_, Storage>,
+    state: AppState<'_>,
+    source_conn_id: i64,
+    target_conn_id: i64,
+    options: TransferOptions,
+    confirmed: bool,
+) -> Result<TransferSummary> {
+    let source = storage.get_connection_by_id(source_conn_id).await?;
+    let target = storage.get_connection_by_id(target_conn_id).await?;
+    match options.mode {
+        TransferMode::Truncate => target
+            .safety_level
+            .confirm_destructive("Truncating the target table", confirmed)?,
+        TransferMode::Append | TransferMode::Upsert => {
+            target.safety_level.require_write("Transfer")?
+        }
+    }
+
+    let mut source_conn = DatabaseConnection::connect_with_schema(
+        &source.url()?,
+        &source.driver,
+        &source.connect_options(),
+        Schema::default(),
+    )
+    .await?;
+    let mut target_conn = match DatabaseConnection::connect_with_schema(
+        &target.url()?,
+        &target.driver,
+        &target.connect_options(),
+        Schema::default(),
+    )
+    .await
+    {
+        Ok(conn) => conn,
+        Err(e) => {
+            source_conn.close().await;
+            return Err(e);
+        }
+    };
+
+    let summary = tx_handlers::transfer_table(
+        &mut source_conn,
+        &mut target_conn,
+        &options,
+        |rows_transferred, total_rows| {
+            let progress = TransferProgress {
+                target_table: options.target_table.clone(),
+                rows_transferred,
+                total_rows,
+            };
+            progress.emit(&app).unwrap();
+            log::debug!("Event emitted: {:?}", progress);
+        },
+    )
+    .await;
+    source_conn.close().await;
+    target_conn.close().await;
+    let summary = summary?;
+    log::info!(
+        "Transferred {} rows from {} to {}",
+        summary.rows_transferred,
+        options.source_table,
+        options.target_table
+    );
+
+    // The transfer used its own connections, keep the open one up to date.
+    let mut state = state.lock().await;
+    if state.conn_id == Some(target_conn_id)
+        && let Some(conn) = state.conn.as_mut()
+    {
+        if summary.created_table {
+            conn.refresh_schema(Some(&options.target_table)).await?;
+            SchemaChanged.emit(&app).unwrap();
+            log::debug!("Event emitted: {:?}", SchemaChanged);
+        }
+        TableContentsChanged.emit(&app).unwrap();
+        log::debug!("Event emitted: {:?}", TableContentsChanged);
+    }
+
+    Ok(summary)
+}
+
+#[tauri::command]
+#[specta::specta]
+pub async fn get_table_info(state: AppState<'_>, table_name: String) -> Result<TableInfo> {
+    let mut state = state.lock().await;
+    let conn = state.conn.as_mut().unwrap();
+
+    conn.table_info(&table_name).await
+}
+
+#[tauri::command]
+#[specta::specta]
+pub async fn get_schema_discovery_failures(
+    state: AppState<'_>,
+) -> Result<Vec<TableDiscoveryFailure>> {
+    let state = state.lock().await;
+    let conn = state.conn.as_ref().unwrap();
+
+    Ok(conn.get_schema().await.failures())
+}
+
+#[tauri::command]
+#[specta::specta]
+pub async fn refresh_schema(
+    app: AppHandle,
+    state: AppState<'_>,
+    table_name: Option<String>,
+) -> Result<Vec<String>> {
+    let mut state = state.lock().await;
+    let conn = state.conn.as_mut().unwrap();
+
+    conn.refresh_schema(table_name.as_deref()).await?;
+    log::debug!("Schema refreshed.");
+
+    SchemaChanged.emit(&app).unwrap();
+    log::debug!("Event emitted: {:?}", SchemaChanged);
+
+    Ok(conn.get_schema().await.tables_names)
+}
+
+/// Describes which parts of the cached schema a set of statements invalidated.
+enum SchemaInvalidation {
+    None,
+    Tables(Vec<String>),
+    Full,
+}
+
+impl SchemaInvalidation {
+    fn merge(&mut self, other: SchemaInvalidation) {
+        match (&mut *self, other) {
+            (_, SchemaInvalidation::None) | (SchemaInvalidation::Full, _) => {}
+            (SchemaInvalidation::Tables(tables), SchemaInvalidation::Tables(other)) => {
+                tables.extend(other)
+            }
+            (_, other) => *self = other,
+        }
+    }
+
+    /// Re-discover the invalidated tables, returns whether anything was refreshed.
+    async fn refresh(self, conn: &mut DatabaseConnection) -> Result<bool> {
+        match self {
+            SchemaInvalidation::None => Ok(false),
+            SchemaInvalidation::Tables(tables) => {
+                for table in tables {
+                    conn.refresh_schema(Some(&table)).await?;
+                }
+                Ok(true)
+            }
+            SchemaInvalidation::Full => {
+                conn.refresh_schema(None).await?;
+                Ok(true)
+            }
+        }
+    }
+}
+
+impl SchemaInvalidation {
+    /// Parts of `conn`'s cached schema invalidated by executing `stmt`.
+    fn of(stmt: &Statement, conn: &DatabaseConnection) -> Self {
+        let table = |name: &ObjectName| table_name(name, conn);
+        match stmt {
+            Statement::CreateTable(create) => Self::Tables(vec![table(&create.name)]),
+            Statement::AlterTable {
+                name, operations, ..
+            } => {
+                // Renames touch two tables, easier to just re-discover everything.
+                if operations
+                    .iter()
+                    .any(|op| matches!(op, AlterTableOperation::RenameTable { .. }))
+                {
+                    Self::Full
+                } else {
+                    Self::Tables(vec![table(name)])
+                }
+            }
+            Statement::Drop {
+                object_type: ObjectType::Table,
+                names,
+                ..
+            } => Self::Tables(names.iter().map(table).collect()),
+            Statement::Drop { .. }
+            | Statement::CreateIndex(_)
+            | Statement::CreateView { .. }
+            | Statement::CreateType { .. } => Self::Full,
+            _ => Self::None,
+        }
+    }
+}
+
+/// Unquoted name `conn`'s cached schema knows the table `name` by, e.g `"main"."users"`
+/// -> `users`. Tables of attached SQLite databases stay qualified by their alias, e.g
+/// `"logs"."events"` -> `logs.events`.
+fn table_name(name: &ObjectName, conn: &DatabaseConnection) -> String {
+    let parts: Vec<String> = name
+        .0
+        .iter()
+        .map(|part| {
+            part.to_string()
+                .trim_matches(['"', '`', '[', ']'])
+                .to_string()
+        })
+        .collect();
+    let table = parts.last().cloned().unwrap_or_default();
+    let qualified = (parts.len() > 1).then(|| format!("{}.{table}", parts[parts.len() - 2]));
+    match qualified {
+        Some(qualified) if conn.attached_table(&qualified).is_some() => qualified,
+        _ => table,
+    }
 }
 
 #[derive(Serialize, Deserialize, Type)]
 pub enum RawQueryResult {
     Query(Vec<DecodedRow>),
     Exec(ExecResult),
+    /// Nothing was executed, these destructive statements must be confirmed first.
+    ConfirmationRequired(Vec<String>),
 }
 
+/// Execute the statements of `query`, returning the last one's result. Destructive
+/// statements on connections requiring confirmation are only executed if `confirmed`.
 #[tauri::command]
 #[specta::specta]
-pub async fn execute_raw_query(state: AppState<'_>, query: String) -> Result<RawQueryResult> {
-    let state = state.lock().await;
-    let conn = state.conn.as_ref().unwrap();
+pub async fn execute_raw_query(
+    app: AppHandle,
+    state: AppState<'_>,
+    query: String,
+    confirmed: bool,
+) -> Result<RawQueryResult> {
+    let mut state = state.lock().await;
+    let safety_level = state.safety_level;
+    let conn = state.conn.as_mut().unwrap();
 
-    let dialect: &dyn Dialect = match conn {
-        DatabaseConnection::Sqlite { .. } => &SQLiteDialect {},
-        DatabaseConnection::Postgres { .. } => &PostgreSqlDialect {},
-        DatabaseConnection::Mysql { .. } => &MySqlDialect {},
-    };
+    let mut ast = safety::parse(&query, &conn.driver())?;
+    let destructive = safety_level.check(&ast)?;
+    if !destructive.is_empty() && !confirmed {
+        return Ok(RawQueryResult::ConfirmationRequired(destructive));
+    }
+
+    let mut invalidation = SchemaInvalidation::None;
+    let result = execute_statements(conn, &mut ast, &mut invalidation).await;
+
+    // Statements executed before a failing one may have changed the schema as well.
+    let refreshed = invalidation.refresh(conn).await;
+    if let Ok(true) = refreshed {
+        SchemaChanged.emit(&app).unwrap();
+        log::debug!("Event emitted: {:?}", SchemaChanged);
+    }
+
+    let result = result?;
+    refreshed?;
+    Ok(result)
+}
 
-    let mut ast = Parser::parse_sql(dialect, query.as_str())?;
+/// Execute `ast` statement by statement, merging the schema changes of the ones that
+/// succeeded into `invalidation`.
+async fn execute_statements(
+    conn: &mut DatabaseConnection,
+    ast: &mut [Statement],
+    invalidation: &mut SchemaInvalidation,
+) -> Result<RawQueryResult> {
     let ast_len = ast.len();
+    let mut result = RawQueryResult::Query(vec![]);
     for (i, stmt) in ast.iter_mut().enumerate() {
         match stmt {
             Statement::Query(q) => {
@@ -78,18 +531,19 @@ pub async fn execute_raw_query(state: AppState<'_>, query: String) -> Result<Raw
                 let rows = conn
                     .fetch_all(&q.to_string(), SqlxValues(sea_query::Values(vec![])))
                     .await?;
-                return Ok(RawQueryResult::Query(decode_raw_rows(rows)?));
+                result = RawQueryResult::Query(decode_raw_rows(rows)?);
             }
             e => {
                 let res = conn.execute(&e.to_string()).await?;
+                invalidation.merge(SchemaInvalidation::of(e, conn));
                 if i != ast_len - 1 {
                     continue;
                 }
 
-                return Ok(RawQueryResult::Exec(res));
+                result = RawQueryResult::Exec(res);
             }
         }
     }
 
-    Ok(RawQueryResult::Query(vec![]))
+    Ok(result)
 }
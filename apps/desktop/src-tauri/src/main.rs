@@ -8,7 +8,10 @@ mod state;
 #[cfg(feature = "updater")]
 mod updater;
 
-use commands::{connection::*, fs::*, row::*, table::*};
+use commands::{
+    connection::*, dump::*, export::*, fs::*, import::*, maintenance::*, row::*, secret::*,
+    table::*,
+};
 #[cfg(debug_assertions)]
 use specta_typescript::{BigIntExportBehavior, Typescript};
 use state::SharedState;
@@ -85,12 +88,27 @@ fn main() {
             // Connection commands.
             test_connection,
             create_connection_record,
+            parse_connection_string,
+            update_connection,
+            duplicate_connection,
+            set_connection_labels,
+            set_connection_safety_level,
             delete_connection_record,
+            set_connection_pragmas,
+            set_attached_databases,
+            set_sqlite_extensions,
+            set_sqlite_key,
+            set_ssh_secret,
             establish_connection,
             drop_connection,
             connections_exist,
             get_connections,
+            filter_connections,
             get_connection_details,
+            // Secret commands.
+            secrets_locked,
+            unlock_secrets,
+            set_master_password,
             // Fs commands.
             open_in_external_editor,
             load_settings_file,
@@ -98,7 +116,35 @@ fn main() {
             write_into_keybindings_file,
             // Table commands.
             discover_db_schema,
+            get_tables_names,
+            get_table_info,
+            get_schema_discovery_failures,
+            refresh_schema,
+            preview_schema_change,
+            apply_schema_change,
+            compare_schemas,
+            compare_table_data,
+            transfer_table,
             execute_raw_query,
+            // Export commands.
+            export_table,
+            export_query,
+            // Import commands.
+            preview_import,
+            import_file,
+            open_data_file,
+            save_database_file,
+            // Dump commands.
+            dump_database,
+            restore_database,
+            // SQLite maintenance commands.
+            vacuum_database,
+            vacuum_database_into,
+            check_integrity,
+            analyze_database,
+            checkpoint_wal,
+            backup_database,
+            get_pragmas,
             // Row commands.
             get_paginated_rows,
             delete_rows,
@@ -106,7 +152,17 @@ fn main() {
             update_row,
             get_fk_relations
         ])
-        .events(collect_events![ConnectionsChanged, TableContentsChanged,])
+        .events(collect_events![
+            ConnectionsChanged,
+            TableContentsChanged,
+            SchemaChanged,
+            TransferProgress,
+            ExportProgress,
+            ImportProgress,
+            DumpProgress,
+            RestoreProgress,
+            BackupProgress,
+        ])
         .error_handling(tauri_specta::ErrorHandlingMode::Throw);
 
     #[cfg(debug_assertions)]
@@ -1,20 +1,36 @@
-use sea_query::{Asterisk, Expr, Func, Iden, Query, SqliteQueryBuilder};
+use crate::commands::row::ColumnOrdering;
+use sea_query::{
+    Asterisk, Expr, Func, Iden, NullOrdering, OnConflict, Order, Query, SqliteQueryBuilder,
+};
 use sea_query_binder::SqlxBinder;
 use serde::{Deserialize, Serialize};
 use specta::Type;
 use sqlx::{SqlitePool, prelude::FromRow, sqlite::SqliteConnectOptions};
-use tauri::{Manager, Runtime};
+use std::path::PathBuf;
+use tauri::{Manager, Runtime, async_runtime::RwLock};
 #[cfg(feature = "metax")]
 use tauri_plugin_shell::process::CommandChild;
-use tx_handlers::DatabaseConnection;
+use tx_handlers::{DatabaseConnection, Schema};
 use tx_lib::{
     TxError,
-    types::{ConnConfig, Drivers},
+    connection::ConnectionParams,
+    safety::SafetyLevel,
+    secret::SecretCipher,
+    types::{
+        AttachedDatabase, ConnConfig, ConnectionLabels, Drivers, Environment, SecretString,
+        SqliteExtension, SqlitePragmas,
+    },
 };
 
 #[derive(Default)]
 pub struct SharedState {
     pub conn: Option<DatabaseConnection>,
+    /// Id of the saved connection `conn` was established from.
+    pub conn_id: Option<i64>,
+    /// Safety level of the saved connection, checked before running statements on `conn`.
+    pub safety_level: SafetyLevel,
+    /// Connections of the table data comparison whose chunks are being read.
+    pub data_comparison: Option<DataComparison>,
     #[cfg(feature = "metax")]
     pub metax: MetaXState,
 }
@@ -28,6 +44,27 @@ impl SharedState {
     }
 }
 
+/// Connections to the saved connections of a table data comparison, kept open while
+/// its chunks are read.
+pub struct DataComparison {
+    pub source_conn_id: i64,
+    pub target_conn_id: i64,
+    pub source: DatabaseConnection,
+    pub target: DatabaseConnection,
+}
+
+impl DataComparison {
+    /// Whether the comparison is between the given saved connections.
+    pub fn compares(&self, source_conn_id: i64, target_conn_id: i64) -> bool {
+        self.source_conn_id == source_conn_id && self.target_conn_id == target_conn_id
+    }
+
+    pub async fn close(self) {
+        self.source.close().await;
+        self.target.close().await;
+    }
+}
+
 #[derive(Default, Clone, Type, Serialize, Deserialize, Debug)]
 #[serde(rename_all = "camelCase")]
 pub enum MetaXStatus {
@@ -65,10 +102,47 @@ impl MetaXState {
     }
 }
 
+/// Which connections [`Storage::filter_connections`] returns, every field must match.
+#[derive(Serialize, Deserialize, Type, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct ConnectionFilter {
+    pub group: Option<String>,
+    /// Only connections having all of these tags.
+    pub tags: Vec<String>,
+    pub color: Option<String>,
+    pub environment: Option<Environment>,
+}
+
+#[derive(Serialize, Deserialize, Type, Debug)]
+#[serde(rename_all = "camelCase")]
+pub enum ConnectionSortKey {
+    Name,
+    Group,
+    Color,
+    /// From development to production.
+    Environment,
+    CreatedAt,
+    UpdatedAt,
+}
+
+#[derive(Serialize, Deserialize, Type, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ConnectionSort {
+    pub by: ConnectionSortKey,
+    pub ordering: ColumnOrdering,
+}
+
 const STORAGE_FILE_NAME: &str = "data.db";
 
+/// Key encrypting the secrets when no master password is set, kept in the app data dir
+/// next to [`STORAGE_FILE_NAME`]. Secrets can't be decrypted once it's lost.
+const KEYFILE_NAME: &str = "secret.key";
+
 pub struct Storage {
     pool: SqlitePool,
+    /// `None` while a master password is set and hasn't been given yet.
+    cipher: RwLock<Option<SecretCipher>>,
+    keyfile: PathBuf,
 }
 
 #[derive(Iden)]
@@ -77,11 +151,52 @@ enum Connection {
     Table,
     Id,
     Name,
+    /// Emptied once migrated to `Params`.
     #[allow(clippy::enum_variant_names)]
     ConnectionString,
     Driver,
     CreatedAt,
     UpdatedAt,
+    Pragmas,
+    AttachedDatabases,
+    SqliteKeySecretId,
+    SqliteExtensions,
+    Params,
+    PasswordSecretId,
+    GroupName,
+    Tags,
+    Color,
+    Environment,
+    SafetyLevel,
+    SshSecretId,
+    MigrationError,
+    /// Secret holding the connection string that couldn't be migrated.
+    #[allow(clippy::enum_variant_names)]
+    ConnectionStringSecretId,
+}
+
+#[derive(Iden)]
+enum Secret {
+    Table,
+    Id,
+    Value,
+    Encrypted,
+}
+
+#[derive(Iden)]
+enum MasterPassword {
+    Table,
+    Id,
+    Salt,
+    Verifier,
+}
+
+#[derive(Iden)]
+enum SchemaCache {
+    Table,
+    ConnectionId,
+    Schema,
+    UpdatedAt,
 }
 
 impl Storage {
@@ -97,6 +212,7 @@ impl Storage {
                 data_dir.to_string_lossy()
             );
         }
+        let keyfile = data_dir.join(KEYFILE_NAME);
         data_dir.push(STORAGE_FILE_NAME);
 
         let pool = SqlitePool::connect_with(
@@ -110,26 +226,340 @@ impl Storage {
             .run(&pool)
             .await
             .expect("Failed to run migrations");
-        Self { pool }
+        let storage = Self {
+            pool,
+            cipher: RwLock::new(None),
+            keyfile,
+        };
+        if let Err(e) = storage.load_keyfile().await {
+            log::error!("Secrets stay locked: {e}");
+        }
+        storage
+    }
+
+    /// Unlock the secrets with the keyfile when no master password is set. The keyfile is
+    /// only created while no secret is encrypted yet, so a deleted one isn't replaced.
+    async fn load_keyfile(&self) -> Result<(), TxError> {
+        if self.master_password().await?.is_some() {
+            return Ok(());
+        }
+        let (query, values) = Query::select()
+            .expr(Expr::col(Secret::Id).count())
+            .from(Secret::Table)
+            .and_where(Expr::col(Secret::Encrypted).eq(true))
+            .build_sqlx(SqliteQueryBuilder);
+        let (encrypted,): (i64,) = sqlx::query_as_with(&query, values)
+            .fetch_one(&self.pool)
+            .await?;
+
+        let cipher = SecretCipher::from_keyfile(&self.keyfile, encrypted == 0)?;
+        *self.cipher.write().await = Some(cipher);
+        self.migrate_secrets().await
+    }
+
+    /// Encrypt the secrets saved in plaintext, and move the passwords of connection strings
+    /// to the secret store. Secrets must be unlocked.
+    async fn migrate_secrets(&self) -> Result<(), TxError> {
+        let (query, values) = Query::select()
+            .from(Secret::Table)
+            .columns([Secret::Id, Secret::Value])
+            .and_where(Expr::col(Secret::Encrypted).eq(false))
+            .build_sqlx(SqliteQueryBuilder);
+
+        let rows: Vec<(i64, String)> = sqlx::query_as_with(&query, values)
+            .fetch_all(&self.pool)
+            .await?;
+        for (secret_id, value) in rows {
+            self.update_secret(secret_id, &SecretString::new(value))
+                .await?;
+        }
+        self.migrate_connection_strings().await
+    }
+
+    /// Salt and verifier of the master password, `None` if the keyfile is used instead.
+    async fn master_password(&self) -> Result<Option<(String, String)>, TxError> {
+        let (query, values) = Query::select()
+            .from(MasterPassword::Table)
+            .columns([MasterPassword::Salt, MasterPassword::Verifier])
+            .build_sqlx(SqliteQueryBuilder);
+
+        let res = sqlx::query_as_with(&query, values)
+            .fetch_optional(&self.pool)
+            .await?;
+        Ok(res)
+    }
+
+    /// Whether secrets can't be read yet, because the master password wasn't given or the
+    /// keyfile couldn't be read.
+    pub async fn secrets_locked(&self) -> bool {
+        self.cipher.read().await.is_none()
+    }
+
+    /// Derive the key of the master password, checking it's the right one. Without a
+    /// master password, the keyfile is read again.
+    pub async fn unlock_secrets(&self, password: SecretString) -> Result<(), TxError> {
+        let Some((salt, verifier)) = self.master_password().await? else {
+            return self.load_keyfile().await;
+        };
+        let cipher = derive_cipher(password, salt).await?;
+        if !cipher.verify(&verifier) {
+            return Err(TxError::Secret("the master password is wrong".to_string()));
+        }
+        *self.cipher.write().await = Some(cipher);
+        self.migrate_secrets().await
+    }
+
+    /// Re-encrypt every secret with a key derived from `password`, or with the keyfile's
+    /// if it's `None`. Secrets must be unlocked.
+    pub async fn set_master_password(&self, password: Option<SecretString>) -> Result<(), TxError> {
+        let mut cipher = self.cipher.write().await;
+        let current = cipher.as_ref().ok_or(TxError::SecretsLocked)?;
+        let (new, master_password) = match password {
+            Some(password) => {
+                let salt = SecretCipher::new_salt()?;
+                let new = derive_cipher(password, salt.clone()).await?;
+                let verifier = new.verifier()?;
+                (new, Some((salt, verifier)))
+            }
+            None => (SecretCipher::from_keyfile(&self.keyfile, true)?, None),
+        };
+
+        let mut tx = self.pool.begin().await?;
+        let (query, values) = Query::select()
+            .from(Secret::Table)
+            .columns([Secret::Id, Secret::Value])
+            .and_where(Expr::col(Secret::Encrypted).eq(true))
+            .build_sqlx(SqliteQueryBuilder);
+        let rows: Vec<(i64, String)> = sqlx::query_as_with(&query, values)
+            .fetch_all(&mut *tx)
+            .await?;
+        for (secret_id, value) in rows {
+            let secret = current.decrypt(&value)?;
+            let (query, values) = Query::update()
+                .table(Secret::Table)
+                .value(Secret::Value, new.encrypt(&secret)?)
+                .and_where(Expr::col(Secret::Id).eq(secret_id))
+                .build_sqlx(SqliteQueryBuilder);
+            sqlx::query_with(&query, values).execute(&mut *tx).await?;
+        }
+
+        let (query, values) = Query::delete()
+            .from_table(MasterPassword::Table)
+            .build_sqlx(SqliteQueryBuilder);
+        sqlx::query_with(&query, values).execute(&mut *tx).await?;
+        if let Some((salt, verifier)) = master_password {
+            let (query, values) = Query::insert()
+                .into_table(MasterPassword::Table)
+                .columns([
+                    MasterPassword::Id,
+                    MasterPassword::Salt,
+                    MasterPassword::Verifier,
+                ])
+                .values_panic([1.into(), salt.into(), verifier.into()])
+                .build_sqlx(SqliteQueryBuilder);
+            sqlx::query_with(&query, values).execute(&mut *tx).await?;
+        }
+        tx.commit().await?;
+
+        *cipher = Some(new);
+        Ok(())
+    }
+
+    /// Parse the connection strings of connections saved before their settings were
+    /// structured, moving their passwords to the secret store.
+    ///
+    /// Connections whose string can't be parsed are kept with blank settings and the
+    /// reason, so they're listed as needing attention. Their whole string is moved to the
+    /// secret store, it may contain a password.
+    async fn migrate_connection_strings(&self) -> Result<(), TxError> {
+        let (query, values) = Query::select()
+            .from(Connection::Table)
+            .columns([
+                Connection::Id,
+                Connection::ConnectionString,
+                Connection::Driver,
+            ])
+            .and_where(Expr::col(Connection::Params).is_null())
+            .build_sqlx(SqliteQueryBuilder);
+
+        let rows: Vec<(i64, String, Drivers)> = sqlx::query_as_with(&query, values)
+            .fetch_all(&self.pool)
+            .await?;
+        for (conn_id, conn_string, driver) in rows {
+            let (params, password) = match ConnectionParams::from_url(&conn_string) {
+                Ok(parsed) => parsed,
+                Err(e) => {
+                    log::warn!(id = conn_id; "Failed to migrate connection string: {e}");
+                    let secret_id = self.insert_secret(&SecretString::new(conn_string)).await?;
+                    let (query, values) = Query::update()
+                        .table(Connection::Table)
+                        .value(
+                            Connection::Params,
+                            serde_json::to_string(&ConnectionParams::blank(driver))?,
+                        )
+                        .value(Connection::MigrationError, e.to_string())
+                        .value(Connection::ConnectionString, "")
+                        .value(Connection::ConnectionStringSecretId, secret_id)
+                        .and_where(Expr::col(Connection::Id).eq(conn_id))
+                        .build_sqlx(SqliteQueryBuilder);
+                    sqlx::query_with(&query, values).execute(&self.pool).await?;
+                    continue;
+                }
+            };
+            let password_secret_id = match password {
+                Some(password) => Some(self.insert_secret(&password).await?),
+                None => None,
+            };
+            let (query, values) = Query::update()
+                .table(Connection::Table)
+                .value(Connection::Params, serde_json::to_string(&params)?)
+                .value(Connection::PasswordSecretId, password_secret_id)
+                .value(Connection::ConnectionString, "")
+                .and_where(Expr::col(Connection::Id).eq(conn_id))
+                .build_sqlx(SqliteQueryBuilder);
+
+            sqlx::query_with(&query, values).execute(&self.pool).await?;
+            log::info!(id = conn_id; "Connection string migrated");
+        }
+        Ok(())
     }
 
     pub async fn insert_connection(
         &self,
-        conn_string: String,
         conn_name: String,
-        driver: Drivers,
+        params: &ConnectionParams,
+        password: Option<&SecretString>,
     ) -> Result<i64, TxError> {
+        params.validate()?;
+        let password_secret_id = match password {
+            Some(password) => Some(self.insert_secret(password).await?),
+            None => None,
+        };
         let (query, values) = Query::insert()
             .into_table(Connection::Table)
             .columns([
                 Connection::Name,
                 Connection::ConnectionString,
                 Connection::Driver,
+                Connection::Params,
+                Connection::PasswordSecretId,
             ])
             .values_panic([
                 conn_name.into(),
-                conn_string.into(),
-                driver.to_string().into(),
+                "".into(),
+                params.driver().to_string().into(),
+                serde_json::to_string(params)?.into(),
+                password_secret_id.into(),
+            ])
+            .build_sqlx(SqliteQueryBuilder);
+        let res = sqlx::query_with(&query, values).execute(&self.pool).await?;
+        Ok(res.last_insert_rowid())
+    }
+
+    /// Replace the name and settings of a saved connection. The password is kept when
+    /// `password` is `None`, and removed when it's empty.
+    pub async fn update_connection(
+        &self,
+        conn_id: i64,
+        conn_name: String,
+        params: &ConnectionParams,
+        password: Option<&SecretString>,
+    ) -> Result<(), TxError> {
+        params.validate()?;
+        let previous = self.get_connection_by_id(conn_id).await?;
+
+        let mut query = Query::update();
+        query
+            .table(Connection::Table)
+            .value(Connection::Name, conn_name)
+            .value(Connection::Driver, params.driver().to_string())
+            .value(Connection::Params, serde_json::to_string(params)?)
+            .value(Connection::ConnectionString, "")
+            .value(Connection::MigrationError, None::<String>)
+            .value(Connection::UpdatedAt, Expr::current_timestamp())
+            .and_where(Expr::col(Connection::Id).eq(conn_id));
+        if let Some(password) = password {
+            let secret_id = match password.expose().is_empty() {
+                true => None,
+                false => Some(self.insert_secret(password).await?),
+            };
+            query.value(Connection::PasswordSecretId, secret_id);
+        }
+        let (query, values) = query.build_sqlx(SqliteQueryBuilder);
+
+        sqlx::query_with(&query, values).execute(&self.pool).await?;
+        if let (Some(_), Some(secret_id)) = (password, previous.password_secret_id) {
+            self.delete_secret(secret_id).await?;
+        }
+        // The cached schema may belong to another database now.
+        if previous.params != *params {
+            self.delete_schema_cache(conn_id).await?;
+        }
+        Ok(())
+    }
+
+    /// Copy a saved connection, named `conn_name` or after the original. Its secrets are
+    /// copied too so each connection owns its own.
+    pub async fn duplicate_connection(
+        &self,
+        conn_id: i64,
+        conn_name: Option<String>,
+    ) -> Result<i64, TxError> {
+        let conn = self.get_connection_by_id(conn_id).await?;
+        let password_secret_id = match &conn.password {
+            Some(password) => Some(self.insert_secret(password).await?),
+            None => None,
+        };
+        let key_secret_id = match &conn.sqlite.key {
+            Some(key) => Some(self.insert_secret(key).await?),
+            None => None,
+        };
+        let ssh_secret_id = match &conn.ssh_secret {
+            Some(secret) => Some(self.insert_secret(secret).await?),
+            None => None,
+        };
+        let (query, values) = Query::insert()
+            .into_table(Connection::Table)
+            .columns([
+                Connection::Name,
+                Connection::ConnectionString,
+                Connection::Driver,
+                Connection::Params,
+                Connection::PasswordSecretId,
+                Connection::Pragmas,
+                Connection::AttachedDatabases,
+                Connection::SqliteExtensions,
+                Connection::SqliteKeySecretId,
+                Connection::GroupName,
+                Connection::Tags,
+                Connection::Color,
+                Connection::Environment,
+                Connection::SafetyLevel,
+                Connection::SshSecretId,
+                Connection::MigrationError,
+            ])
+            .values_panic([
+                conn_name
+                    .unwrap_or_else(|| format!("{} (copy)", conn.name))
+                    .into(),
+                "".into(),
+                conn.driver.to_string().into(),
+                serde_json::to_string(&conn.params)?.into(),
+                password_secret_id.into(),
+                serde_json::to_string(&conn.sqlite.pragmas)?.into(),
+                serde_json::to_string(&conn.sqlite.attached_databases)?.into(),
+                serde_json::to_string(&conn.sqlite.extensions)?.into(),
+                key_secret_id.into(),
+                conn.labels.group.into(),
+                serde_json::to_string(&conn.labels.tags)?.into(),
+                conn.labels.color.into(),
+                conn.labels
+                    .environment
+                    .map(|environment| environment.to_string())
+                    .into(),
+                conn.safety_level.to_string().into(),
+                ssh_secret_id.into(),
+                conn.needs_attention.into(),
             ])
             .build_sqlx(SqliteQueryBuilder);
         let res = sqlx::query_with(&query, values).execute(&self.pool).await?;
@@ -137,18 +567,350 @@ impl Storage {
     }
 
     pub async fn delete_connection(&self, conn_id: i64) -> Result<(), TxError> {
+        type SecretIds = (Option<i64>, Option<i64>, Option<i64>, Option<i64>);
+        let (query, values) = Query::select()
+            .from(Connection::Table)
+            .columns([
+                Connection::SqliteKeySecretId,
+                Connection::PasswordSecretId,
+                Connection::SshSecretId,
+                Connection::ConnectionStringSecretId,
+            ])
+            .and_where(Expr::col(Connection::Id).eq(conn_id))
+            .build_sqlx(SqliteQueryBuilder);
+        let secret_ids: Option<SecretIds> = sqlx::query_as_with(&query, values)
+            .fetch_optional(&self.pool)
+            .await?;
+
         let (query, values) = Query::delete()
             .from_table(Connection::Table)
             .and_where(Expr::col(Connection::Id).eq(conn_id))
             .build_sqlx(SqliteQueryBuilder);
 
         sqlx::query_with(&query, values).execute(&self.pool).await?;
+        if let Some((key_secret_id, password_secret_id, ssh_secret_id, conn_string_secret_id)) =
+            secret_ids
+        {
+            for secret_id in [
+                key_secret_id,
+                password_secret_id,
+                ssh_secret_id,
+                conn_string_secret_id,
+            ]
+            .into_iter()
+            .flatten()
+            {
+                self.delete_secret(secret_id).await?;
+            }
+        }
+        Ok(())
+    }
+
+    async fn sqlite_key_secret_id(&self, conn_id: i64) -> Result<Option<i64>, TxError> {
+        let (query, values) = Query::select()
+            .from(Connection::Table)
+            .column(Connection::SqliteKeySecretId)
+            .and_where(Expr::col(Connection::Id).eq(conn_id))
+            .build_sqlx(SqliteQueryBuilder);
+
+        let res: Option<Option<i64>> = sqlx::query_scalar_with(&query, values)
+            .fetch_optional(&self.pool)
+            .await?;
+        Ok(res.flatten())
+    }
+
+    /// Replace the SQLCipher key of a saved SQLite connection, `None` removes it.
+    pub async fn update_sqlite_key(
+        &self,
+        conn_id: i64,
+        key: Option<&SecretString>,
+    ) -> Result<(), TxError> {
+        let previous = self.sqlite_key_secret_id(conn_id).await?;
+        let secret_id = match key {
+            Some(key) => Some(self.insert_secret(key).await?),
+            None => None,
+        };
+        let (query, values) = Query::update()
+            .table(Connection::Table)
+            .value(Connection::SqliteKeySecretId, secret_id)
+            .value(Connection::UpdatedAt, Expr::current_timestamp())
+            .and_where(Expr::col(Connection::Id).eq(conn_id))
+            .build_sqlx(SqliteQueryBuilder);
+
+        sqlx::query_with(&query, values).execute(&self.pool).await?;
+        if let Some(previous) = previous {
+            self.delete_secret(previous).await?;
+        }
         Ok(())
     }
+
+    async fn ssh_secret_id(&self, conn_id: i64) -> Result<Option<i64>, TxError> {
+        let (query, values) = Query::select()
+            .from(Connection::Table)
+            .column(Connection::SshSecretId)
+            .and_where(Expr::col(Connection::Id).eq(conn_id))
+            .build_sqlx(SqliteQueryBuilder);
+
+        let res: Option<Option<i64>> = sqlx::query_scalar_with(&query, values)
+            .fetch_optional(&self.pool)
+            .await?;
+        Ok(res.flatten())
+    }
+
+    /// Replace the password or key passphrase of a saved connection's SSH tunnel, `None`
+    /// removes it.
+    pub async fn update_ssh_secret(
+        &self,
+        conn_id: i64,
+        secret: Option<&SecretString>,
+    ) -> Result<(), TxError> {
+        let previous = self.ssh_secret_id(conn_id).await?;
+        let secret_id = match secret {
+            Some(secret) => Some(self.insert_secret(secret).await?),
+            None => None,
+        };
+        let (query, values) = Query::update()
+            .table(Connection::Table)
+            .value(Connection::SshSecretId, secret_id)
+            .value(Connection::UpdatedAt, Expr::current_timestamp())
+            .and_where(Expr::col(Connection::Id).eq(conn_id))
+            .build_sqlx(SqliteQueryBuilder);
+
+        sqlx::query_with(&query, values).execute(&self.pool).await?;
+        if let Some(previous) = previous {
+            self.delete_secret(previous).await?;
+        }
+        Ok(())
+    }
+
+    /// Store a secret, e.g a password, returning the id connections reference it by.
+    pub async fn insert_secret(&self, secret: &SecretString) -> Result<i64, TxError> {
+        let value = self.encrypt(secret).await?;
+        let (query, values) = Query::insert()
+            .into_table(Secret::Table)
+            .columns([Secret::Value, Secret::Encrypted])
+            .values_panic([value.into(), true.into()])
+            .build_sqlx(SqliteQueryBuilder);
+        let res = sqlx::query_with(&query, values).execute(&self.pool).await?;
+        Ok(res.last_insert_rowid())
+    }
+
+    async fn update_secret(&self, secret_id: i64, secret: &SecretString) -> Result<(), TxError> {
+        let value = self.encrypt(secret).await?;
+        let (query, values) = Query::update()
+            .table(Secret::Table)
+            .value(Secret::Value, value)
+            .value(Secret::Encrypted, true)
+            .and_where(Expr::col(Secret::Id).eq(secret_id))
+            .build_sqlx(SqliteQueryBuilder);
+
+        sqlx::query_with(&query, values).execute(&self.pool).await?;
+        Ok(())
+    }
+
+    pub async fn get_secret(&self, secret_id: i64) -> Result<SecretString, TxError> {
+        let (query, values) = Query::select()
+            .from(Secret::Table)
+            .columns([Secret::Value, Secret::Encrypted])
+            .and_where(Expr::col(Secret::Id).eq(secret_id))
+            .build_sqlx(SqliteQueryBuilder);
+
+        let (value, encrypted): (String, bool) = sqlx::query_as_with(&query, values)
+            .fetch_one(&self.pool)
+            .await?;
+        if !encrypted {
+            return Ok(SecretString::new(value));
+        }
+        match self.cipher.read().await.as_ref() {
+            Some(cipher) => cipher.decrypt(&value),
+            None => Err(TxError::SecretsLocked),
+        }
+    }
+
+    async fn encrypt(&self, secret: &SecretString) -> Result<String, TxError> {
+        match self.cipher.read().await.as_ref() {
+            Some(cipher) => cipher.encrypt(secret),
+            None => Err(TxError::SecretsLocked),
+        }
+    }
+
+    pub async fn delete_secret(&self, secret_id: i64) -> Result<(), TxError> {
+        let (query, values) = Query::delete()
+            .from_table(Secret::Table)
+            .and_where(Expr::col(Secret::Id).eq(secret_id))
+            .build_sqlx(SqliteQueryBuilder);
+
+        sqlx::query_with(&query, values).execute(&self.pool).await?;
+        Ok(())
+    }
+
+    pub async fn update_safety_level(
+        &self,
+        conn_id: i64,
+        safety_level: SafetyLevel,
+    ) -> Result<(), TxError> {
+        let (query, values) = Query::update()
+            .table(Connection::Table)
+            .value(Connection::SafetyLevel, safety_level.to_string())
+            .value(Connection::UpdatedAt, Expr::current_timestamp())
+            .and_where(Expr::col(Connection::Id).eq(conn_id))
+            .build_sqlx(SqliteQueryBuilder);
+
+        sqlx::query_with(&query, values).execute(&self.pool).await?;
+        Ok(())
+    }
+
+    /// Replace the group, tags, color and environment of a saved connection.
+    pub async fn update_connection_labels(
+        &self,
+        conn_id: i64,
+        labels: ConnectionLabels,
+    ) -> Result<(), TxError> {
+        let labels = labels.normalize()?;
+        let (query, values) = Query::update()
+            .table(Connection::Table)
+            .value(Connection::GroupName, labels.group)
+            .value(Connection::Tags, serde_json::to_string(&labels.tags)?)
+            .value(Connection::Color, labels.color)
+            .value(
+                Connection::Environment,
+                labels
+                    .environment
+                    .map(|environment| environment.to_string()),
+            )
+            .value(Connection::UpdatedAt, Expr::current_timestamp())
+            .and_where(Expr::col(Connection::Id).eq(conn_id))
+            .build_sqlx(SqliteQueryBuilder);
+
+        sqlx::query_with(&query, values).execute(&self.pool).await?;
+        Ok(())
+    }
+
+    /// Saved connections matching `filter`, sorted by `sort` then by name. Connections
+    /// without the sorted label are listed last.
+    pub async fn filter_connections(
+        &self,
+        filter: &ConnectionFilter,
+        sort: &ConnectionSort,
+    ) -> Result<Vec<ConnConfig>, TxError> {
+        let mut query = Query::select();
+        query
+            .from(Connection::Table)
+            .column(Asterisk)
+            .and_where(Expr::col(Connection::Params).is_not_null());
+        if let Some(group) = &filter.group {
+            query.and_where(Expr::col(Connection::GroupName).eq(group.trim()));
+        }
+        for tag in &filter.tags {
+            query.and_where(Expr::cust_with_values(
+                r#"EXISTS (SELECT 1 FROM json_each("tags") WHERE value = ?)"#,
+                [tag.trim()],
+            ));
+        }
+        if let Some(color) = &filter.color {
+            query.and_where(Expr::col(Connection::Color).eq(color.to_lowercase()));
+        }
+        if let Some(environment) = filter.environment {
+            query.and_where(Expr::col(Connection::Environment).eq(environment.to_string()));
+        }
+
+        let order = match sort.ordering {
+            ColumnOrdering::Asc => Order::Asc,
+            ColumnOrdering::Desc => Order::Desc,
+        };
+        let key = match sort.by {
+            ConnectionSortKey::Name => Expr::col(Connection::Name).into(),
+            ConnectionSortKey::Group => Expr::col(Connection::GroupName).into(),
+            ConnectionSortKey::Color => Expr::col(Connection::Color).into(),
+            ConnectionSortKey::Environment => Expr::case(
+                Expr::col(Connection::Environment).eq(Environment::Development.to_string()),
+                0,
+            )
+            .case(
+                Expr::col(Connection::Environment).eq(Environment::Staging.to_string()),
+                1,
+            )
+            .case(
+                Expr::col(Connection::Environment).eq(Environment::Production.to_string()),
+                2,
+            )
+            .into(),
+            ConnectionSortKey::CreatedAt => Expr::col(Connection::CreatedAt).into(),
+            ConnectionSortKey::UpdatedAt => Expr::col(Connection::UpdatedAt).into(),
+        };
+        let (query, values) = query
+            .order_by_expr_with_nulls(key, order, NullOrdering::Last)
+            .order_by(Connection::Name, Order::Asc)
+            .build_sqlx(SqliteQueryBuilder);
+
+        let res = sqlx::query_as_with::<_, ConnConfig, _>(&query, values)
+            .fetch_all(&self.pool)
+            .await?;
+        Ok(res)
+    }
+
+    /// Replace the pragmas applied when connecting to a saved SQLite connection.
+    pub async fn update_connection_pragmas(
+        &self,
+        conn_id: i64,
+        pragmas: &SqlitePragmas,
+    ) -> Result<(), TxError> {
+        let (query, values) = Query::update()
+            .table(Connection::Table)
+            .value(Connection::Pragmas, serde_json::to_string(pragmas)?)
+            .value(Connection::UpdatedAt, Expr::current_timestamp())
+            .and_where(Expr::col(Connection::Id).eq(conn_id))
+            .build_sqlx(SqliteQueryBuilder);
+
+        sqlx::query_with(&query, values).execute(&self.pool).await?;
+        Ok(())
+    }
+
+    /// Replace the databases attached when connecting to a saved SQLite connection.
+    pub async fn update_attached_databases(
+        &self,
+        conn_id: i64,
+        databases: &[AttachedDatabase],
+    ) -> Result<(), TxError> {
+        let (query, values) = Query::update()
+            .table(Connection::Table)
+            .value(
+                Connection::AttachedDatabases,
+                serde_json::to_string(databases)?,
+            )
+            .value(Connection::UpdatedAt, Expr::current_timestamp())
+            .and_where(Expr::col(Connection::Id).eq(conn_id))
+            .build_sqlx(SqliteQueryBuilder);
+
+        sqlx::query_with(&query, values).execute(&self.pool).await?;
+        Ok(())
+    }
+
+    /// Replace the extensions loaded when connecting to a saved SQLite connection.
+    pub async fn update_sqlite_extensions(
+        &self,
+        conn_id: i64,
+        extensions: &[SqliteExtension],
+    ) -> Result<(), TxError> {
+        let (query, values) = Query::update()
+            .table(Connection::Table)
+            .value(
+                Connection::SqliteExtensions,
+                serde_json::to_string(extensions)?,
+            )
+            .value(Connection::UpdatedAt, Expr::current_timestamp())
+            .and_where(Expr::col(Connection::Id).eq(conn_id))
+            .build_sqlx(SqliteQueryBuilder);
+
+        sqlx::query_with(&query, values).execute(&self.pool).await?;
+        Ok(())
+    }
+
     pub async fn connections_count(&self) -> Result<i64, TxError> {
         let (query, values) = Query::select()
             .expr(Func::count(Expr::col(Connection::Id)))
             .from(Connection::Table)
+            .and_where(Expr::col(Connection::Params).is_not_null())
             .build_sqlx(SqliteQueryBuilder);
 
         #[derive(FromRow)]
@@ -160,10 +922,13 @@ impl Storage {
         Ok(res.0)
     }
 
+    /// All saved connections, except those whose connection string hasn't been migrated
+    /// yet because secrets are locked.
     pub async fn get_all_connections(&self) -> Result<Vec<ConnConfig>, TxError> {
         let (query, values) = Query::select()
             .from(Connection::Table)
             .column(Asterisk)
+            .and_where(Expr::col(Connection::Params).is_not_null())
             .build_sqlx(SqliteQueryBuilder);
 
         let res = sqlx::query_as_with::<_, ConnConfig, _>(&query, values)
@@ -180,9 +945,143 @@ impl Storage {
             .and_where(Expr::col(Connection::Id).eq(conn_id))
             .build_sqlx(SqliteQueryBuilder);
 
-        let res = sqlx::query_as_with::<_, ConnConfig, _>(&query, values)
+        let mut res = sqlx::query_as_with::<_, ConnConfig, _>(&query, values)
             .fetch_one(&self.pool)
             .await?;
+        if let Some(secret_id) = res.password_secret_id {
+            res.password = Some(self.get_secret(secret_id).await?);
+        }
+        if let Some(secret_id) = res.sqlite.key_secret_id {
+            res.sqlite.key = Some(self.get_secret(secret_id).await?);
+        }
+        if let Some(secret_id) = res.ssh_secret_id {
+            res.ssh_secret = Some(self.get_secret(secret_id).await?);
+        }
         Ok(res)
     }
+
+    /// Get the schema cached for a connection, `None` if there's none or it can't be read.
+    pub async fn get_schema_cache(&self, conn_id: i64) -> Result<Option<Schema>, TxError> {
+        let (query, values) = Query::select()
+            .from(SchemaCache::Table)
+            .column(SchemaCache::Schema)
+            .and_where(Expr::col(SchemaCache::ConnectionId).eq(conn_id))
+            .build_sqlx(SqliteQueryBuilder);
+
+        let res: Option<String> = sqlx::query_scalar_with(&query, values)
+            .fetch_optional(&self.pool)
+            .await?;
+
+        Ok(res.and_then(|schema| {
+            serde_json::from_str(&schema)
+                .inspect_err(|e| log::warn!(id = conn_id; "Ignoring unreadable schema cache: {e}"))
+                .ok()
+        }))
+    }
+
+    pub async fn delete_schema_cache(&self, conn_id: i64) -> Result<(), TxError> {
+        let (query, values) = Query::delete()
+            .from_table(SchemaCache::Table)
+            .and_where(Expr::col(SchemaCache::ConnectionId).eq(conn_id))
+            .build_sqlx(SqliteQueryBuilder);
+
+        sqlx::query_with(&query, values).execute(&self.pool).await?;
+        Ok(())
+    }
+
+    /// Cache a connection's schema, replacing the previous one.
+    ///
+    /// Schemas without a fingerprint are skipped since they can't be revalidated later.
+    pub async fn save_schema_cache(&self, conn_id: i64, schema: &Schema) -> Result<(), TxError> {
+        if schema.fingerprint.is_none() {
+            return Ok(());
+        }
+        let schema = serde_json::to_string(schema)?;
+        let (query, values) = Query::insert()
+            .into_table(SchemaCache::Table)
+            .columns([SchemaCache::ConnectionId, SchemaCache::Schema])
+            .values_panic([conn_id.into(), schema.into()])
+            .on_conflict(
+                OnConflict::column(SchemaCache::ConnectionId)
+                    .update_column(SchemaCache::Schema)
+                    .value(SchemaCache::UpdatedAt, Expr::current_timestamp())
+                    .to_owned(),
+            )
+            .build_sqlx(SqliteQueryBuilder);
+
+        sqlx::query_with(&query, values).execute(&self.pool).await?;
+        Ok(())
+    }
+}
+
+/// Derive a master password's key off the async runtime, PBKDF2 is slow on purpose.
+async fn derive_cipher(password: SecretString, salt: String) -> Result<SecretCipher, TxError> {
+    tauri::async_runtime::spawn_blocking(move || SecretCipher::from_password(&password, &salt))
+        .await?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn storage(name: &str) -> Storage {
+        let path = std::env::temp_dir().join(format!("tx-state-{}-{name}", std::process::id()));
+        let _ = std::fs::remove_file(path.with_extension("db"));
+        let pool = SqlitePool::connect_with(
+            SqliteConnectOptions::new()
+                .filename(path.with_extension("db"))
+                .create_if_missing(true),
+        )
+        .await
+        .unwrap();
+        sqlx::migrate!().run(&pool).await.unwrap();
+        let keyfile = path.with_extension("key");
+        let _ = std::fs::remove_file(&keyfile);
+        let cipher = SecretCipher::from_keyfile(&keyfile, true).unwrap();
+        Storage {
+            pool,
+            cipher: RwLock::new(Some(cipher)),
+            keyfile,
+        }
+    }
+
+    #[tokio::test]
+    async fn unparseable_connection_strings_are_moved_to_the_secret_store() {
+        let storage = storage("migration").await;
+        let conn_strings = [
+            "postgres://admin:hunter2@/app",
+            "admin:hunter2 at db.example.com",
+        ];
+        for conn_string in conn_strings {
+            sqlx::query(
+                "INSERT INTO connection (name, connection_string, driver) VALUES (?, ?, ?)",
+            )
+            .bind("legacy")
+            .bind(conn_string)
+            .bind(Drivers::PostgreSQL.to_string())
+            .execute(&storage.pool)
+            .await
+            .unwrap();
+        }
+        storage.migrate_connection_strings().await.unwrap();
+
+        let rows: Vec<(String, String, Option<String>, Option<i64>)> = sqlx::query_as(
+            "SELECT connection_string, params, migration_error, connection_string_secret_id
+             FROM connection ORDER BY id",
+        )
+        .fetch_all(&storage.pool)
+        .await
+        .unwrap();
+        for ((conn_string, params, migration_error, secret_id), original) in
+            rows.into_iter().zip(conn_strings)
+        {
+            assert!(migration_error.is_some());
+            for column in [conn_string, params, migration_error.unwrap()] {
+                assert!(!column.contains("hunter2"));
+            }
+            let secret = storage.get_secret(secret_id.unwrap()).await.unwrap();
+            assert_eq!(secret.expose(), original);
+        }
+        std::fs::remove_file(&storage.keyfile).unwrap();
+    }
 }
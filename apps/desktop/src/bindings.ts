@@ -17,15 +17,90 @@ async startMetax(connId: number) : Promise<null> {
 async getMetaxStatus() : Promise<MetaXStatus> {
     return await TAURI_INVOKE("get_metax_status");
 },
-async testConnection(connString: string, driver: Drivers) : Promise<null> {
-    return await TAURI_INVOKE("test_connection", { connString, driver });
+/**
+ * Ping a database before its connection is saved, `key` is the SQLCipher key of an
+ * encrypted SQLite database. With `ssh`, the database is reached through the tunnel,
+ * `ssh_secret` is its password or key passphrase.
+ */
+async testConnection(connString: string, driver: Drivers, key: string | null, ssh: SshTunnel | null, sshSecret: string | null) : Promise<null> {
+    return await TAURI_INVOKE("test_connection", { connString, driver, key, ssh, sshSecret });
 },
 async createConnectionRecord(connString: string, connName: string, driver: Drivers) : Promise<number> {
     return await TAURI_INVOKE("create_connection_record", { connString, connName, driver });
 },
+/**
+ * Replace the name and settings of a saved connection. The password is kept when
+ * `password` is `None`, and removed when it's empty. With `test`, the connection is
+ * pinged first and nothing is saved if it fails.
+ */
+async updateConnection(connId: number, connName: string, params: ConnectionParams, password: string | null, test: boolean) : Promise<null> {
+    return await TAURI_INVOKE("update_connection", { connId, connName, params, password, test });
+},
+/**
+ * Copy a saved connection with its options and secrets, `conn_name` defaults to the
+ * original's name followed by "(copy)".
+ */
+async duplicateConnection(connId: number, connName: string | null) : Promise<number> {
+    return await TAURI_INVOKE("duplicate_connection", { connId, connName });
+},
+/**
+ * Set the group, tags, color and environment of a saved connection.
+ */
+async setConnectionLabels(connId: number, labels: ConnectionLabels) : Promise<null> {
+    return await TAURI_INVOKE("set_connection_labels", { connId, labels });
+},
+/**
+ * Set which statements are allowed on a saved connection. If it's the open connection
+ * and it becomes read-only or stops being so, it's re-established so the database
+ * session's read-only flag matches.
+ */
+async setConnectionSafetyLevel(connId: number, safetyLevel: SafetyLevel) : Promise<null> {
+    return await TAURI_INVOKE("set_connection_safety_level", { connId, safetyLevel });
+},
+/**
+ * Split a connection string into its settings, its password is left out.
+ */
+async parseConnectionString(connString: string) : Promise<ConnectionParams> {
+    return await TAURI_INVOKE("parse_connection_string", { connString });
+},
 async deleteConnectionRecord(connId: number) : Promise<string> {
     return await TAURI_INVOKE("delete_connection_record", { connId });
 },
+/**
+ * Replace the pragmas of a saved SQLite connection, they're applied the next time it's
+ * established.
+ */
+async setConnectionPragmas(connId: number, pragmas: SqlitePragmas) : Promise<null> {
+    return await TAURI_INVOKE("set_connection_pragmas", { connId, pragmas });
+},
+/**
+ * Replace the databases attached to a saved SQLite connection, they're attached the next
+ * time it's established. The cached schema is dropped since it lists their tables.
+ */
+async setAttachedDatabases(connId: number, databases: AttachedDatabase[]) : Promise<null> {
+    return await TAURI_INVOKE("set_attached_databases", { connId, databases });
+},
+/**
+ * Replace the extensions loaded by a saved SQLite connection, they're loaded the next time
+ * it's established.
+ */
+async setSqliteExtensions(connId: number, extensions: SqliteExtension[]) : Promise<null> {
+    return await TAURI_INVOKE("set_sqlite_extensions", { connId, extensions });
+},
+/**
+ * Replace the SQLCipher key of a saved SQLite connection, `None` removes it. The key is
+ * kept in the secret store rather than in the connection.
+ */
+async setSqliteKey(connId: number, key: string | null) : Promise<null> {
+    return await TAURI_INVOKE("set_sqlite_key", { connId, key });
+},
+/**
+ * Replace the password or key passphrase of a saved connection's SSH tunnel, `None`
+ * removes it.
+ */
+async setSshSecret(connId: number, secret: string | null) : Promise<null> {
+    return await TAURI_INVOKE("set_ssh_secret", { connId, secret });
+},
 async establishConnection(connId: number) : Promise<null> {
     return await TAURI_INVOKE("establish_connection", { connId });
 },
@@ -38,9 +113,37 @@ async connectionsExist() : Promise<boolean> {
 async getConnections() : Promise<ConnConfig[]> {
     return await TAURI_INVOKE("get_connections");
 },
+/**
+ * Saved connections matching `filter`, sorted by `sort` then by name.
+ */
+async filterConnections(filter: ConnectionFilter, sort: ConnectionSort) : Promise<ConnConfig[]> {
+    return await TAURI_INVOKE("filter_connections", { filter, sort });
+},
 async getConnectionDetails(connId: number) : Promise<ConnConfig> {
     return await TAURI_INVOKE("get_connection_details", { connId });
 },
+/**
+ * Whether secrets must be unlocked before connecting, with the master password or once
+ * the keyfile is restored.
+ */
+async secretsLocked() : Promise<boolean> {
+    return await TAURI_INVOKE("secrets_locked");
+},
+/**
+ * Unlock the secrets with the master password, or read the keyfile again if no master
+ * password is set. Connections saved before secrets were encrypted are listed once
+ * unlocked.
+ */
+async unlockSecrets(password: string) : Promise<null> {
+    return await TAURI_INVOKE("unlock_secrets", { password });
+},
+/**
+ * Encrypt the secrets with a key derived from `password`, or with the local keyfile's if
+ * it's `None`.
+ */
+async setMasterPassword(password: string | null) : Promise<null> {
+    return await TAURI_INVOKE("set_master_password", { password });
+},
 async openInExternalEditor(file: ConfigFile) : Promise<null> {
     return await TAURI_INVOKE("open_in_external_editor", { file });
 },
@@ -56,8 +159,156 @@ async writeIntoKeybindingsFile(keybindings: Keybinding[]) : Promise<null> {
 async discoverDbSchema() : Promise<TableInfo[]> {
     return await TAURI_INVOKE("discover_db_schema");
 },
-async executeRawQuery(query: string) : Promise<RawQueryResult> {
-    return await TAURI_INVOKE("execute_raw_query", { query });
+async getTablesNames() : Promise<string[]> {
+    return await TAURI_INVOKE("get_tables_names");
+},
+async getTableInfo(tableName: string) : Promise<TableInfo> {
+    return await TAURI_INVOKE("get_table_info", { tableName });
+},
+async getSchemaDiscoveryFailures() : Promise<TableDiscoveryFailure[]> {
+    return await TAURI_INVOKE("get_schema_discovery_failures");
+},
+async refreshSchema(tableName: string | null) : Promise<string[]> {
+    return await TAURI_INVOKE("refresh_schema", { tableName });
+},
+/**
+ * Get the SQL statements `change` would execute, without executing them.
+ */
+async previewSchemaChange(change: SchemaChange) : Promise<string[]> {
+    return await TAURI_INVOKE("preview_schema_change", { change });
+},
+/**
+ * Execute the statements of `change`. Altering or dropping a table can drop data, so it
+ * must be `confirmed` on connections confirming destructive statements.
+ */
+async applySchemaChange(change: SchemaChange, confirmed: boolean) : Promise<null> {
+    return await TAURI_INVOKE("apply_schema_change", { change, confirmed });
+},
+/**
+ * Compare the schemas of two saved connections using the same driver, the diff's
+ * script migrates the `from` database so its schema matches the `to` database's.
+ */
+async compareSchemas(fromConnId: number, toConnId: number) : Promise<SchemaDiff> {
+    return await TAURI_INVOKE("compare_schemas", { fromConnId, toConnId });
+},
+/**
+ * Compare the rows of two tables with the same primary key, which can belong to the
+ * same connection. The diff's script reconciles the target table with the source's rows.
+ * 
+ * Rows are compared a chunk at a time, `after` is the `next_key` of the previous chunk.
+ * The connections are kept open from the first chunk until the last one is compared.
+ */
+async compareTableData(sourceConnId: number, sourceTable: string, targetConnId: number, targetTable: string, after: { [key in string]: JsonValue } | null) : Promise<DataDiff> {
+    return await TAURI_INVOKE("compare_table_data", { sourceConnId, sourceTable, targetConnId, targetTable, after });
+},
+/**
+ * Copy a table's rows to another saved connection, which can use another driver.
+ * Truncating the target table must be `confirmed` if the target connection confirms
+ * destructive statements.
+ */
+async transferTable(sourceConnId: number, targetConnId: number, options: TransferOptions, confirmed: boolean) : Promise<TransferSummary> {
+    return await TAURI_INVOKE("transfer_table", { sourceConnId, targetConnId, options, confirmed });
+},
+/**
+ * Execute the statements of `query`, returning the last one's result. Destructive
+ * statements on connections requiring confirmation are only executed if `confirmed`.
+ */
+async executeRawQuery(query: string, confirmed: boolean) : Promise<RawQueryResult> {
+    return await TAURI_INVOKE("execute_raw_query", { query, confirmed });
+},
+/**
+ * Export the rows of a table matching `filtering`, ordered by `sorting`.
+ */
+async exportTable(tableName: string, sorting: SortingData[], filtering: FilteringData[], options: ExportOptions) : Promise<ExportSummary> {
+    return await TAURI_INVOKE("export_table", { tableName, sorting, filtering, options });
+},
+/**
+ * Export the rows returned by a raw query, on a clone of the connection like
+ * [`export_table`]. Statements other than queries are rejected, they'd run unconfirmed.
+ */
+async exportQuery(query: string, options: ExportOptions) : Promise<ExportSummary> {
+    return await TAURI_INVOKE("export_query", { query, options });
+},
+/**
+ * Read the first `limit` rows of a file along with the types inferred for its columns.
+ */
+async previewImport(path: string, format: ImportFormat, limit: number) : Promise<ImportPreview> {
+    return await TAURI_INVOKE("preview_import", { path, format, limit });
+},
+/**
+ * Insert the rows of a file into a table of the open connection.
+ */
+async importFile(options: ImportOptions) : Promise<ImportSummary> {
+    return await TAURI_INVOKE("import_file", { options });
+},
+/**
+ * Load a CSV, JSON or Parquet file into an in-memory SQLite database and make it the
+ * open connection.
+ */
+async openDataFile(path: string, format: ImportFormat) : Promise<DataFile> {
+    return await TAURI_INVOKE("open_data_file", { path, format });
+},
+/**
+ * Save the open SQLite database to a file, e.g to keep an opened data file as a `.db`
+ * file. The file is overwritten if it exists.
+ */
+async saveDatabaseFile(path: string) : Promise<null> {
+    return await TAURI_INVOKE("save_database_file", { path });
+},
+/**
+ * Write a SQL script recreating the open connection's database.
+ */
+async dumpDatabase(options: DumpOptions) : Promise<DumpSummary> {
+    return await TAURI_INVOKE("dump_database", { options });
+},
+/**
+ * Execute the statements of a SQL script on the open connection. The script can drop
+ * tables, so it must be `confirmed` on connections confirming destructive statements.
+ */
+async restoreDatabase(options: RestoreOptions, confirmed: boolean) : Promise<RestoreSummary> {
+    return await TAURI_INVOKE("restore_database", { options, confirmed });
+},
+/**
+ * Rebuild the open SQLite database, releasing the space left by deleted rows.
+ */
+async vacuumDatabase() : Promise<VacuumSummary> {
+    return await TAURI_INVOKE("vacuum_database");
+},
+/**
+ * Write a vacuumed copy of the open SQLite database to `path`, which must not exist.
+ */
+async vacuumDatabaseInto(path: string) : Promise<VacuumSummary> {
+    return await TAURI_INVOKE("vacuum_database_into", { path });
+},
+/**
+ * Look for corruption in the open SQLite database.
+ */
+async checkIntegrity(quick: boolean) : Promise<IntegrityCheck> {
+    return await TAURI_INVOKE("check_integrity", { quick });
+},
+/**
+ * Gather statistics about the open SQLite database for the query planner.
+ */
+async analyzeDatabase() : Promise<null> {
+    return await TAURI_INVOKE("analyze_database");
+},
+/**
+ * Copy the frames of the open SQLite database's WAL file back into the database.
+ */
+async checkpointWal(mode: CheckpointMode) : Promise<WalCheckpoint> {
+    return await TAURI_INVOKE("checkpoint_wal", { mode });
+},
+/**
+ * Copy the open SQLite database to `path` while it's in use, with the online backup API.
+ */
+async backupDatabase(path: string) : Promise<BackupSummary> {
+    return await TAURI_INVOKE("backup_database", { path });
+},
+/**
+ * Current values of the open SQLite connection's pragmas.
+ */
+async getPragmas() : Promise<Pragma[]> {
+    return await TAURI_INVOKE("get_pragmas");
 },
 async getPaginatedRows(payload: GetRowsPayload) : Promise<PaginatedRows> {
     return await TAURI_INVOKE("get_paginated_rows", { payload });
@@ -81,10 +332,24 @@ async getFkRelations(tableName: string, columnName: string, cellValue: JsonValue
 
 export const events = __makeEvents__<{
 connectionsChanged: ConnectionsChanged,
-tableContentsChanged: TableContentsChanged
+tableContentsChanged: TableContentsChanged,
+schemaChanged: SchemaChanged,
+transferProgress: TransferProgress,
+exportProgress: ExportProgress,
+importProgress: ImportProgress,
+dumpProgress: DumpProgress,
+restoreProgress: RestoreProgress,
+backupProgress: BackupProgress
 }>({
 connectionsChanged: "connections-changed",
-tableContentsChanged: "table-contents-changed"
+tableContentsChanged: "table-contents-changed",
+schemaChanged: "schema-changed",
+transferProgress: "transfer-progress",
+exportProgress: "export-progress",
+importProgress: "import-progress",
+dumpProgress: "dump-progress",
+restoreProgress: "restore-progress",
+backupProgress: "backup-progress"
 })
 
 /** user-defined constants **/
@@ -94,11 +359,173 @@ export const KEYBINDINGS_FILE_NAME = "dev/keybindings.json" as const;
 
 /** user-defined types **/
 
-export type ColumnInfo = { autoGenerated: boolean; name: string; nullable: boolean; pk: boolean; type: CustomColumnType }
+export type AttachedDatabase = { 
+/**
+ * Schema name the database is attached as.
+ */
+alias: string; path: string }
+/**
+ * Progress of a SQLite backup, emitted after each copied step of pages.
+ */
+export type BackupProgress = { path: string; pagesCopied: number; totalPages: number }
+export type BackupSummary = { pagesCopied: number; bytesCopied: number }
+export type CheckpointMode = 
+/**
+ * Checkpoint as many frames as possible without waiting for readers or writers.
+ */
+"passive" | 
+/**
+ * Wait for writers, then checkpoint every frame.
+ */
+"full" | 
+/**
+ * Like `Full`, then wait for readers so the next writer restarts the WAL file.
+ */
+"restart" | 
+/**
+ * Like `Restart`, then truncate the WAL file.
+ */
+"truncate"
+export type ColumnInfo = { autoGenerated: boolean; name: string; nullable: boolean; pk: boolean; type: CustomColumnType; 
+/**
+ * The default value expression as reported by the database, e.g `CURRENT_TIMESTAMP`.
+ */
+defaultValue: string | null; 
+/**
+ * Maximum length for character and binary columns.
+ */
+maxLength: number | null; 
+/**
+ * Total number of digits for numeric columns.
+ */
+numericPrecision: number | null; 
+/**
+ * Number of digits after the decimal point for numeric columns.
+ */
+numericScale: number | null; collation: string | null; comment: string | null }
+export type ColumnDefinition = { name: string; 
+/**
+ * Name of the column in the current table when it's being renamed.
+ */
+previousName?: string | null; type: CustomColumnType; nullable: boolean; autoIncrement: boolean; 
+/**
+ * SQL expression used as is, e.g `'draft'` or `CURRENT_TIMESTAMP`.
+ */
+defaultValue: string | null; maxLength: number | null; numericPrecision: number | null; numericScale: number | null }
+export type ColumnMapping = { 
+/**
+ * Column of the file.
+ */
+source: string; 
+/**
+ * Column of the table the values are inserted into.
+ */
+target: string; 
+/**
+ * Type of the column when the table is created, ignored otherwise.
+ */
+type: CustomColumnType }
 export type ColumnOrdering = "asc" | "desc"
+export type ColumnValueDiff = { column: string; 
+/**
+ * `None` if the row doesn't exist in the source table.
+ */
+source: JsonValue | null; 
+/**
+ * `None` if the row doesn't exist in the target table.
+ */
+target: JsonValue | null }
 export type ConfigFile = "settings" | "keybindings" | "logs"
-export type ConnConfig = { id: number; driver: Drivers; name: string; connectionString: string }
+export type ConnConfig = { id: number; driver: Drivers; name: string; 
+/**
+ * Settings the connection's URL is rendered from.
+ */
+params: ConnectionParams; 
+/**
+ * Secret holding the password of PostgreSQL and MySQL connections.
+ */
+passwordSecretId: number | null; 
+/**
+ * Secret holding the password or key passphrase of the SSH tunnel.
+ */
+sshSecretId: number | null; 
+/**
+ * Which statements are allowed, and which must be confirmed before running.
+ */
+safetyLevel: SafetyLevel; 
+/**
+ * Group, tags, color and environment the connection is organized by.
+ */
+labels: ConnectionLabels; 
+/**
+ * Options applied to SQLite connections, ignored by other drivers.
+ */
+sqlite: SqliteOptions; 
+/**
+ * Why the connection string the connection was saved with couldn't be migrated to
+ * `params`, which are blank until the connection is edited.
+ */
+needsAttention: string | null }
+/**
+ * Which connections [`Storage::filter_connections`] returns, every field must match.
+ */
+export type ConnectionFilter = { group: string | null; 
+/**
+ * Only connections having all of these tags.
+ */
+tags: string[]; color: string | null; environment: Environment | null }
+export type ConnectionLabels = { 
+/**
+ * Folder the connection is listed in, `None` lists it outside of any.
+ */
+group: string | null; tags: string[]; 
+/**
+ * Hex color, e.g `#ff0000`.
+ */
+color: string | null; environment: Environment | null }
+/**
+ * Settings of a connection, per driver.
+ */
+export type ConnectionParams = ({ driver: "sqlite" } & SqliteParams) | ({ driver: "postgresql" } & ServerParams) | ({ driver: "mysql" } & ServerParams)
+export type ConnectionSort = { by: ConnectionSortKey; ordering: ColumnOrdering }
+export type ConnectionSortKey = "name" | "group" | "color" | 
+/**
+ * From development to production.
+ */
+"environment" | "createdAt" | "updatedAt"
 export type ConnectionsChanged = null
+export type CsvImportOptions = { 
+/**
+ * Must be an ASCII character.
+ */
+delimiter: string; 
+/**
+ * Read the column names from the first record, otherwise they're named `column1`,
+ * `column2`...
+ */
+header: boolean }
+export type CsvOptions = { 
+/**
+ * Must be an ASCII character.
+ */
+delimiter: string; quoting: CsvQuoting; 
+/**
+ * Write the column names as the first record.
+ */
+header: boolean }
+export type CsvQuoting = 
+/**
+ * Only quote fields containing the delimiter, quotes or line breaks.
+ */
+"necessary" | "always" | 
+/**
+ * Quote every field that isn't a number.
+ */
+"nonNumeric" | 
+/**
+ * Never quote fields, even if it makes the file ambiguous.
+ */
+"never"
 /**
  * Acts as a unified interface for all databases' datatypes.
  * Each database implements the conversion of it's datatypes to the
@@ -106,19 +533,205 @@ export type ConnectionsChanged = null
  */
 export type CustomColumnType = "string" | "text" | "uuid" | "float" | "positiveInteger" | "boolean" | "integer" | "date" | "dateTime" | "time" | "year" | "json" | { enum: CustomEnumDef } | "binary" | "custom" | "unSupported"
 export type CustomEnumDef = { name: string; variants: string[] }
+export type DataDiff = { 
+/**
+ * Columns that were compared, starting with the primary key columns.
+ */
+columns: string[]; 
+/**
+ * Columns that only exist in one of the tables, or whose values can't be decoded to be
+ * compared, e.g binary columns, so they weren't compared.
+ */
+skippedColumns: string[]; 
+/**
+ * Differing rows of the chunk, in primary key order.
+ */
+rows: RowDiff[]; 
+/**
+ * Number of rows of the chunk that are the same in both tables.
+ */
+unchanged: number; 
+/**
+ * Statements reconciling the chunk's rows of the target table with the source table's.
+ */
+script: string[]; 
+/**
+ * Primary key of the chunk's last row, pass it back to compare the next chunk.
+ * `None` once both tables have been compared entirely.
+ */
+nextKey: { [key in string]: JsonValue } | null }
 /**
  * Represents the transformation result of a series of database `ValueRef`
  * into a `JsonMap` that can be understood and serialized by the frontend.
  * 
  * Result is a map whose key is the column name and value is the column value in the row.
  */
+export type DataFile = { path: string; 
+/**
+ * Table the rows were loaded into, named after the file.
+ */
+tableName: string; 
+/**
+ * Values that don't fit the types inferred from the first rows are kept as text.
+ */
+import: ImportSummary }
 export type DecodedRow = { [key in string]: JsonValue }
+export type DiffKind = 
+/**
+ * Only exists in the `to` schema.
+ */
+"added" | 
+/**
+ * Only exists in the `from` schema.
+ */
+"removed" | "changed"
 export type Drivers = "sqlite" | "postgresql" | "mysql"
+export type DumpOptions = { 
+/**
+ * File the script is written to, it's overwritten if it exists.
+ */
+path: string; 
+/**
+ * Drop the dumped tables, and Postgres' types and sequences, before creating them so
+ * the script can restore a database that still has them.
+ */
+dropExisting: boolean; 
+/**
+ * Dump the tables' rows along with their schema.
+ */
+data: boolean }
+/**
+ * Progress of a database dump, emitted periodically while rows are written.
+ */
+export type DumpProgress = { path: string; rowsDumped: number }
+export type DumpSummary = { tables: string[]; rowsDumped: number; 
+/**
+ * Tables whose details couldn't be discovered, they're left out of the dump.
+ */
+skippedTables: TableDiscoveryFailure[] }
+/**
+ * Environment a connection's database belongs to.
+ */
+export type Environment = "development" | "staging" | "production"
 export type ExecResult = { rows_affected: number }
+export type ExportFormat = { csv: CsvOptions } | 
+/**
+ * A single array of objects.
+ */
+"json" | 
+/**
+ * One object per line.
+ */
+"ndjson" | 
+/**
+ * `INSERT` statements in the connection's dialect, one per row.
+ */
+{ sql: { 
+/**
+ * Table the rows are inserted into, defaults to the exported table.
+ */
+tableName: string | null } } | 
+/**
+ * Columns are written with the Parquet type matching theirs.
+ */
+"parquet" | 
+/**
+ * A single sheet with a header row and typed cells.
+ */
+"xlsx"
+export type ExportOptions = { 
+/**
+ * File the rows are written to, it's overwritten if it exists.
+ */
+path: string; format: ExportFormat }
+/**
+ * Progress of an export, emitted periodically while rows are written.
+ */
+export type ExportProgress = { path: string; rowsExported: number }
+export type ExportSummary = { 
+/**
+ * Columns of the exported rows, in the order they were written.
+ */
+columns: string[]; rowsExported: number }
 export type FKRows = { tableName: string; rows: { [key in string]: JsonValue }[] }
+/**
+ * A column of the imported file.
+ */
+export type FileColumn = { name: string; 
+/**
+ * Type inferred from the first rows of the file.
+ */
+type: CustomColumnType; 
+/**
+ * Whether some of the first rows have no value for the column.
+ */
+nullable: boolean }
 export type FilteringData = { column: string; filters: Filters }
 export type Filters = { gt: JsonValue } | { gte: JsonValue } | { lt: JsonValue } | { lte: JsonValue } | { eq: JsonValue } | { ne: JsonValue } | { between: [JsonValue, JsonValue] } | { like: string } | { notLike: string } | "isEmpty" | "isNotEmpty" | { inArray: JsonValue[] } | { notInArray: JsonValue[] }
+export type ForeignKeyAction = "noAction" | "restrict" | "cascade" | "setNull" | "setDefault"
+export type ForeignKeyInfo = { 
+/**
+ * Constraint name, SQLite foreign keys are unnamed.
+ */
+name: string | null; columns: string[]; referencedTable: string; referencedColumns: string[]; onUpdate: ForeignKeyAction; onDelete: ForeignKeyAction }
 export type GetRowsPayload = { tableName: string; pagination: PaginationData; sorting: SortingData[]; filtering: FilteringData[] }
+export type ImportFormat = 
+/**
+ * Empty fields are imported as `NULL`s.
+ */
+{ csv: CsvImportOptions } | 
+/**
+ * An array of objects, or one object per line.
+ */
+"json" | "parquet"
+export type ImportOptions = { path: string; format: ImportFormat; tableName: string; 
+/**
+ * Create the table from the mapped columns if it doesn't exist.
+ */
+createTable: boolean; 
+/**
+ * File columns that aren't mapped are left out.
+ */
+columns: ColumnMapping[]; 
+/**
+ * Number of rows inserted per statement, each batch in its own transaction.
+ */
+batchSize: number }
+export type ImportPreview = { 
+/**
+ * Columns of CSV and Parquet files in the order they're stored in, JSON ones in the
+ * order their keys first appear.
+ */
+columns: FileColumn[]; 
+/**
+ * First rows of the file.
+ */
+rows: DecodedRow[] }
+/**
+ * Progress of an import, emitted after each inserted batch.
+ */
+export type ImportProgress = { path: string; rowsImported: number }
+export type ImportRowError = { 
+/**
+ * Line of the file the row starts at, or its position in Parquet files.
+ */
+line: number; message: string }
+export type ImportSummary = { createdTable: boolean; rowsImported: number; rowsFailed: number; 
+/**
+ * Errors of the first failed rows.
+ */
+errors: ImportRowError[] }
+export type IndexInfo = { name: string; columns: string[]; unique: boolean }
+export type IntegrityCheck = { ok: boolean; 
+/**
+ * Problems found in the database, empty when it's ok.
+ */
+messages: string[] }
+/**
+ * A difference of a single item, `from` and `to` are its state in each schema.
+ */
+export type ItemDiff<T> = { kind: DiffKind; from: T | null; to: T | null }
+export type JournalMode = "delete" | "truncate" | "persist" | "memory" | "wal" | "off"
 export type JsonValue = null | boolean | number | string | JsonValue[] | { [key in string]: JsonValue }
 /**
  * Represents a keybinding record in the keybindings json file.
@@ -131,7 +744,51 @@ export type KeybindingCommand = Sidebar | Table
 export type MetaXStatus = "active" | "exited" | "paused"
 export type PaginatedRows = { data: DecodedRow[]; pageCount: number }
 export type PaginationData = { pageIndex: number; pageSize: number }
-export type RawQueryResult = { Query: DecodedRow[] } | { Exec: ExecResult }
+export type Pragma = { name: string; 
+/**
+ * Value as returned by SQLite, e.g `1` for enabled flags.
+ */
+value: JsonValue }
+export type RawQueryResult = { Query: DecodedRow[] } | { Exec: ExecResult } | 
+/**
+ * Nothing was executed, these destructive statements must be confirmed first.
+ */
+{ ConfirmationRequired: string[] }
+export type RestoreOptions = { path: string; 
+/**
+ * Execute the script in a single transaction, rolled back on the first failing
+ * statement. Otherwise failing statements are reported and the next ones executed.
+ * 
+ * MySQL commits schema changes immediately, even in a transaction.
+ */
+atomic: boolean }
+/**
+ * Progress of a script restore, emitted periodically while statements are executed.
+ */
+export type RestoreProgress = { path: string; statementsExecuted: number; 
+/**
+ * Bytes of the script read so far.
+ */
+bytesRead: number; totalBytes: number }
+export type RestoreSummary = { statementsExecuted: number; errors: StatementError[] }
+export type RowDiff = { kind: RowDiffKind; 
+/**
+ * Primary key values identifying the row.
+ */
+key: { [key in string]: JsonValue }; 
+/**
+ * The differing columns, every column for inserted and deleted rows.
+ */
+columns: ColumnValueDiff[] }
+export type RowDiffKind = 
+/**
+ * Only exists in the source table.
+ */
+"inserted" | 
+/**
+ * Only exists in the target table.
+ */
+"deleted" | "changed"
 /**
  * Represents a cell info in a row. Used primarily for when performing
  * operations on rows (`Insert`, `Update`, `Delete`)
@@ -149,6 +806,63 @@ fontSize: number;
  * Enable vim keybindings in the editor
  */
 vimMode: boolean }
+/**
+ * A structural change to a table, previewed as SQL before being applied.
+ */
+export type SafetyLevel = "normal" | 
+/**
+ * Destructive statements, e.g `DROP` or `DELETE` without `WHERE`, only run once
+ * confirmed.
+ */
+"confirmDestructive" | 
+/**
+ * Only queries are allowed, the database session is read-only too where the driver
+ * supports it.
+ */
+"readOnly"
+export type SchemaChange = { create: TableDefinition } | 
+/**
+ * Alter `table_name` so it matches `definition`, which may rename it.
+ */
+{ alter: { tableName: string; definition: TableDefinition } } | { drop: { tableName: string } }
+export type SchemaChanged = null
+export type SchemaDiff = { 
+/**
+ * Tables that differ between the two schemas.
+ */
+tables: TableDiff[]; 
+/**
+ * Tables that failed to be discovered in either schema, so they weren't compared.
+ */
+skipped: string[]; 
+/**
+ * Statements migrating the `from` database so its schema matches the `to` one.
+ */
+script: string[] }
+/**
+ * Settings of PostgreSQL and MySQL connections, the password is kept in the secret store.
+ */
+export type ServerParams = { host: string; port: number; 
+/**
+ * Empty to use the driver's default user.
+ */
+user: string; 
+/**
+ * Empty to use the server's default database.
+ */
+database: string; 
+/**
+ * `None` uses the driver's default.
+ */
+sslMode: SslMode | null; 
+/**
+ * Query parameters of the URL other than the SSL mode.
+ */
+options: { [key in string]: string }; 
+/**
+ * SSH server the database is reached through, `None` connects directly.
+ */
+ssh?: SshTunnel | null }
 /**
  * The configuration object for TableX's settings.
  */
@@ -171,9 +885,146 @@ checkForUpdates: boolean;
 sqlEditor: SQLEditorSettings }
 export type Sidebar = "focusSearch"
 export type SortingData = { column: string; ordering: ColumnOrdering }
+export type SqliteExtension = { 
+/**
+ * Path of the extension's library, SQLite tries the platform's suffix if it's missing.
+ */
+path: string; 
+/**
+ * Name of the extension's init function, `None` lets SQLite derive it from the path.
+ */
+entryPoint: string | null }
+export type SqliteOptions = { pragmas: SqlitePragmas; 
+/**
+ * Databases attached to each connection, their tables are listed as `alias.table`.
+ */
+attachedDatabases: AttachedDatabase[]; 
+/**
+ * Extensions loaded by each connection, in order.
+ */
+extensions: SqliteExtension[]; 
+/**
+ * Secret holding the SQLCipher key of an encrypted database.
+ */
+keySecretId: number | null }
+/**
+ * Pragmas set on each connection opened to a SQLite database, `None` keeps the value
+ * given by the connection string or SQLite's default.
+ */
+export type SqliteParams = { filePath: string; 
+/**
+ * Query parameters of the URL, e.g `mode=ro`.
+ */
+options: { [key in string]: string } }
+export type SqlitePragmas = { foreignKeys: boolean | null; journalMode: JournalMode | null; 
+/**
+ * Milliseconds to wait for a locked database before failing.
+ */
+busyTimeout: number | null; synchronous: Synchronous | null; 
+/**
+ * Number of pages cached in memory, or KiB if negative.
+ */
+cacheSize: number | null }
+/**
+ * How to authenticate with the SSH server, the password or the key's passphrase is kept
+ * in the secret store.
+ */
+export type SshAuth = { method: "password" } | { method: "keyFile"; path: string }
+/**
+ * SSH server forwarding a local port to the database, the host and port of the
+ * connection are then resolved by the SSH server.
+ */
+export type SshTunnel = { host: string; port: number; user: string; auth: SshAuth; 
+/**
+ * File the server's key is verified with, `None` uses `~/.ssh/known_hosts`.
+ */
+knownHosts: string | null; 
+/**
+ * Trust the key of a server missing from `known_hosts` on first use and add it,
+ * like OpenSSH's `StrictHostKeyChecking=accept-new`. A changed key is still rejected.
+ */
+acceptNewHostKey?: boolean }
+export type SslMode = "disable" | "prefer" | "require" | 
+/**
+ * Require SSL and verify the server's certificate.
+ */
+"verifyCa" | 
+/**
+ * Like `VerifyCa`, and check the certificate matches the host.
+ */
+"verifyFull"
+export type StatementError = { 
+/**
+ * Line of the script the statement starts at.
+ */
+line: number; 
+/**
+ * Start of the statement.
+ */
+statement: string; message: string }
+export type Synchronous = "off" | "normal" | "full" | "extra"
 export type Table = "deleteRow" | "copyRow" | "selectAll"
 export type TableContentsChanged = null
-export type TableInfo = { name: string; columns: ColumnInfo[]; create_statement: string }
+/**
+ * Structured definition of a table, used to generate the DDL creating or altering it.
+ */
+export type TableDefinition = { name: string; columns: ColumnDefinition[]; 
+/**
+ * Names of the columns making up the primary key, in order.
+ */
+primaryKey: string[]; indexes: IndexInfo[]; foreignKeys: ForeignKeyInfo[] }
+/**
+ * The differences of a table, added and removed tables list all of their items.
+ */
+export type TableDiff = { tableName: string; kind: DiffKind; columns: ItemDiff<ColumnDefinition>[]; 
+/**
+ * Names of the primary key columns, if they differ.
+ */
+primaryKey: ItemDiff<string[]> | null; indexes: ItemDiff<IndexInfo>[]; foreignKeys: ItemDiff<ForeignKeyInfo>[] }
+/**
+ * A table whose details couldn't be discovered.
+ */
+export type TableDiscoveryFailure = { tableName: string; reason: string }
+export type TableInfo = { name: string; columns: ColumnInfo[]; create_statement: string; 
+/**
+ * Secondary indexes, the primary key isn't included.
+ */
+indexes: IndexInfo[]; foreign_keys: ForeignKeyInfo[] }
+export type TransferMode = 
+/**
+ * Delete the target table's rows before copying.
+ */
+"truncate" | "append" | 
+/**
+ * Update the target rows that have the same primary key instead of inserting them.
+ */
+"upsert"
+export type TransferOptions = { sourceTable: string; targetTable: string; 
+/**
+ * Create the target table from the source table's definition if it doesn't exist.
+ */
+createTable: boolean; mode: TransferMode; 
+/**
+ * Number of rows inserted per statement.
+ */
+batchSize: number }
+/**
+ * Progress of a table transfer, emitted after each inserted batch.
+ */
+export type TransferProgress = { targetTable: string; rowsTransferred: number; 
+/**
+ * Number of rows in the source table when the transfer started.
+ */
+totalRows: number }
+export type TransferSummary = { createdTable: boolean; 
+/**
+ * Columns that were copied.
+ */
+columns: string[]; 
+/**
+ * Source columns that don't exist in the target table, or that it generates.
+ */
+skippedColumns: string[]; rowsTransferred: number }
 /**
  * Global error object returned by all commands
  */
@@ -186,6 +1037,24 @@ message: string;
  * Detailed error message throwing by the low level api
  */
 details: string }
+export type VacuumSummary = { sizeBefore: number; 
+/**
+ * Size of the vacuumed database, or of its copy for `VACUUM INTO`.
+ */
+sizeAfter: number; bytesReclaimed: number }
+export type WalCheckpoint = { 
+/**
+ * The checkpoint couldn't complete because of another connection.
+ */
+busy: boolean; 
+/**
+ * Frames in the WAL file, `None` if the database isn't in WAL mode.
+ */
+walFrames: number | null; 
+/**
+ * Frames copied back into the database, `None` if it isn't in WAL mode.
+ */
+checkpointedFrames: number | null }
 
 /** tauri-specta globals **/
 
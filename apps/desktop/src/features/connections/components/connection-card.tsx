@@ -37,6 +37,11 @@ export const ConnectionCard = ({ connection }: { connection: ConnConfig }) => {
         <CardDescription className="text-xs">
           {connection.driver}
         </CardDescription>
+        {connection.needsAttention && (
+          <CardDescription className="text-xs text-destructive">
+            Needs attention: {connection.needsAttention}
+          </CardDescription>
+        )}
       </CardHeader>
     </Card>
   )
@@ -63,7 +63,13 @@ export const NewConnectionBtn = () => {
       ...data.connectionOpts
     })
     return toast.promise(
-      commands.testConnection(connString, data.connectionOpts.driver),
+      commands.testConnection(
+        connString,
+        data.connectionOpts.driver,
+        null,
+        null,
+        null
+      ),
       {
         id: "test_connection",
         loading: "Testing connection...",
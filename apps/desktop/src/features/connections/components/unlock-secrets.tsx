@@ -0,0 +1,73 @@
+import { commands, TxError } from "@/bindings"
+import {
+  Empty,
+  EmptyContent,
+  EmptyDescription,
+  EmptyHeader,
+  EmptyMedia,
+  EmptyTitle
+} from "@tablex/ui/components/empty"
+import { Input } from "@tablex/ui/components/input"
+import { LoadingButton } from "@tablex/ui/components/loading-button"
+import { useRouter } from "@tanstack/react-router"
+import { Lock } from "lucide-react"
+import { useState } from "react"
+import { toast } from "sonner"
+
+export function UnlockSecrets() {
+  const router = useRouter()
+  const [password, setPassword] = useState("")
+  const [isLoading, setIsLoading] = useState(false)
+
+  const onUnlock = async () => {
+    setIsLoading(true)
+    try {
+      await commands.unlockSecrets(password)
+    } catch (error) {
+      return toast.error("Couldn't unlock the saved passwords.", {
+        description: (error as TxError).details
+      })
+    } finally {
+      setIsLoading(false)
+    }
+    setPassword("")
+    await router.invalidate()
+  }
+
+  return (
+    <Empty>
+      <EmptyHeader>
+        <EmptyMedia variant={"icon"}>
+          <Lock />
+        </EmptyMedia>
+      </EmptyHeader>
+      <div className="max-w-lg space-y-3">
+        <EmptyTitle>Secrets Locked</EmptyTitle>
+        <EmptyDescription>
+          Enter the master password to decrypt the saved passwords. Without a
+          master password, restore the keyfile next to the app's data and
+          unlock again.
+        </EmptyDescription>
+      </div>
+      <EmptyContent>
+        <form
+          className="flex w-full gap-2"
+          onSubmit={(e) => {
+            e.preventDefault()
+            onUnlock()
+          }}
+        >
+          <Input
+            type="password"
+            placeholder="Master password"
+            value={password}
+            onChange={(e) => setPassword(e.target.value)}
+          />
+          <LoadingButton type="submit" loading={isLoading}>
+            Unlock
+          </LoadingButton>
+        </form>
+      </EmptyContent>
+    </Empty>
+  )
+}
@@ -56,12 +56,17 @@ export const SQLEditor = () => {
   const {
     mutate: runQuery,
     data: result,
+    variables,
     isError,
     error,
     isPending
-  } = useMutation<RawQueryResult, TxError, { editorState: EditorState }>({
+  } = useMutation<
+    RawQueryResult,
+    TxError,
+    { editorState: EditorState; confirmed?: boolean }
+  >({
     mutationKey: ["run_query"],
-    mutationFn: async ({ editorState }) => {
+    mutationFn: async ({ editorState, confirmed = false }) => {
       let query: string
 
       if (editorState.selection.main.empty) {
@@ -72,7 +77,7 @@ export const SQLEditor = () => {
           editorState.selection.main.to
         )
       }
-      return await commands.executeRawQuery(query)
+      return await commands.executeRawQuery(query, confirmed)
     }
   })
 
@@ -123,6 +128,15 @@ export const SQLEditor = () => {
     }
     if ("Exec" in result)
       return <code>{`Rows Affected: ${result.Exec.rows_affected}`} </code>
+    if ("ConfirmationRequired" in result)
+      return (
+        <ConfirmDestructive
+          statements={result.ConfirmationRequired}
+          onConfirm={() =>
+            runQuery({ editorState: variables!.editorState, confirmed: true })
+          }
+        />
+      )
   }
 
   return (
@@ -181,6 +195,28 @@ const EditorTools = ({ disabled, onRun, onFormat }: EditorToolsProps) => {
   )
 }
 
+const ConfirmDestructive = ({
+  statements,
+  onConfirm
+}: {
+  statements: string[]
+  onConfirm: () => void
+}) => {
+  return (
+    <div className="flex h-full w-full flex-col items-center justify-center gap-y-4 p-4">
+      <span className="text-xl">
+        This connection requires confirming destructive statements
+      </span>
+      <pre className="text-muted-foreground max-w-full overflow-auto">
+        {statements.join(";\n")}
+      </pre>
+      <Button variant={"destructive"} onClick={onConfirm}>
+        Run anyway
+      </Button>
+    </div>
+  )
+}
+
 const QueryLoading = () => {
   return (
     <div className="flex h-full w-full flex-col items-center justify-center gap-y-4">
@@ -1,19 +1,21 @@
-import { useRouteContext, useSearch } from "@tanstack/react-router"
+import { useSuspenseQuery } from "@tanstack/react-query"
+import { useSearch } from "@tanstack/react-router"
 import { getZodSchemaFromCols } from "./columns"
+import { getTableSchemaOptions } from "./queries"
 
 export const useTableSchema = () => {
-  const { schema } = useRouteContext({ from: "/connection/$connId/editor" })
   const { table } = useSearch({
     from: "/connection/$connId/editor",
     select: (s) => ({ table: s.table })
   })
-  const tableSchema = schema.find((s) => s.name === table)
 
-  if (!schema || !tableSchema)
+  if (!table)
     throw new Error(
       "useTableSchema must be used within the table editor route context"
     )
 
+  const { data: tableSchema } = useSuspenseQuery(getTableSchemaOptions(table))
+
   return {
     tableSchema,
     pkCols: tableSchema.columns.filter((c) => c.pk),
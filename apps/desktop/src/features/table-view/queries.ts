@@ -10,3 +10,10 @@ export const getPaginatedRowsOptions = (data: GetRowsPayload) => {
     placeholderData: keepPreviousData
   })
 }
+
+export const getTableSchemaOptions = (tableName: string) => {
+  return queryOptions({
+    queryKey: [QUERY_KEYS.DB_SCHEMA, tableName],
+    queryFn: async () => await commands.getTableInfo(tableName)
+  })
+}
@@ -52,7 +52,7 @@ import {
 } from "@tablex/ui/components/tabs"
 import { cn } from "@tablex/ui/utils"
 import { useQuery } from "@tanstack/react-query"
-import { createFileRoute, Link } from "@tanstack/react-router"
+import { createFileRoute, Link, useRouter } from "@tanstack/react-router"
 import { Table } from "@tanstack/react-table"
 import { tokyoNight } from "@uiw/codemirror-theme-tokyo-night"
 import { RefreshCw } from "lucide-react"
@@ -62,8 +62,9 @@ import z from "zod"
 
 export const Route = createFileRoute("/connection/$connId/editor")({
   beforeLoad: async () => {
-    const schema = await commands.discoverDbSchema()
-    return { schema }
+    // Tables details are discovered on demand, see `useTableSchema`.
+    const tablesNames = await commands.getTablesNames()
+    return { tablesNames }
   },
   validateSearch: z.object({
     schema: z.string().optional(),
@@ -77,8 +78,19 @@ export const Route = createFileRoute("/connection/$connId/editor")({
 })
 
 function RouteComponent() {
-  const { schema } = Route.useRouteContext()
+  const { tablesNames, queryClient } = Route.useRouteContext()
   const table = Route.useSearch({ select: (s) => s.table })
+  const router = useRouter()
+
+  // Re-runs `beforeLoad` so the sidebar and table definitions pick up DDL changes.
+  useTauriEventListener(
+    "schemaChanged",
+    () => {
+      router.invalidate()
+      queryClient.invalidateQueries({ queryKey: [QUERY_KEYS.DB_SCHEMA] })
+    },
+    [router, queryClient]
+  )
 
   return (
     <SidebarProvider className="min-w-0">
@@ -97,14 +109,14 @@ function RouteComponent() {
             <SidebarGroup>
               <SidebarGroupContent>
                 <SidebarMenu className="space-y-1">
-                  {schema.map((t) => (
+                  {tablesNames.map((name) => (
                     <SidebarMenuItem>
                       <SidebarMenuButton asChild>
                         <Link
                           to="."
-                          search={(prev) => ({ ...prev, table: t.name })}
+                          search={(prev) => ({ ...prev, table: name })}
                         >
-                          {t.name}
+                          {name}
                         </Link>
                       </SidebarMenuButton>
                     </SidebarMenuItem>
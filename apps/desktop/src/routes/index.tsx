@@ -1,6 +1,7 @@
 import { commands, TxError } from "@/bindings"
 import { ConnectionCard } from "@/features/connections/components/connection-card"
 import { NewConnectionBtn } from "@/features/connections/components/new-connection-btn"
+import { UnlockSecrets } from "@/features/connections/components/unlock-secrets"
 import { useSettings } from "@/features/settings/context"
 import { LOCAL_STORAGE } from "@/lib/constants"
 import {
@@ -16,13 +17,19 @@ import { Database } from "lucide-react"
 import { toast } from "sonner"
 
 export const Route = createFileRoute("/")({
-  loader: commands.getConnections,
+  loader: async () => {
+    const [connections, secretsLocked] = await Promise.all([
+      commands.getConnections(),
+      commands.secretsLocked()
+    ])
+    return { connections, secretsLocked }
+  },
   component: Index
 })
 
 function Index() {
   const navigate = Route.useNavigate()
-  const connections = Route.useLoaderData()
+  const { connections, secretsLocked } = Route.useLoaderData()
   const settings = useSettings()
 
   const onClickConnect = async (connId: number) => {
@@ -48,6 +55,14 @@ function Index() {
     })
   }
 
+  if (secretsLocked) {
+    return (
+      <main className="flex h-full flex-col items-center justify-center">
+        <UnlockSecrets />
+      </main>
+    )
+  }
+
   if (connections.length === 0) {
     return (
       <main className="flex h-full flex-col items-center justify-center">
@@ -0,0 +1,596 @@
+//! Comparison of the rows of two tables matched by primary key, and the statements
+//! reconciling one with the other.
+
+use crate::{
+    DatabaseConnection,
+    ddl::{inline, quoted, schema_builder},
+    decode_raw_rows,
+    schema::{ColumnInfo, CustomColumnType, TableInfo},
+};
+use sea_query::{Alias, Cond, Expr, Order, Query, SelectStatement, SimpleExpr, Value};
+use sea_query_binder::SqlxBinder;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map as JsonMap, Value as JsonValue};
+use specta::Type;
+use std::{cmp::Ordering, collections::VecDeque};
+use tx_lib::{Result, TxError, types::Drivers};
+
+/// Number of rows read from each table at once.
+const BATCH_SIZE: u64 = 1000;
+/// Number of differing rows compared at once, see [`DataDiff::next_key`].
+const CHUNK_SIZE: usize = 1000;
+
+type Row = JsonMap<String, JsonValue>;
+
+#[derive(Serialize, Deserialize, Type, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum RowDiffKind {
+    /// Only exists in the source table.
+    Inserted,
+    /// Only exists in the target table.
+    Deleted,
+    Changed,
+}
+
+#[derive(Serialize, Deserialize, Type, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ColumnValueDiff {
+    pub column: String,
+    /// `None` if the row doesn't exist in the source table.
+    pub source: Option<JsonValue>,
+    /// `None` if the row doesn't exist in the target table.
+    pub target: Option<JsonValue>,
+}
+
+#[derive(Serialize, Deserialize, Type, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct RowDiff {
+    pub kind: RowDiffKind,
+    /// Primary key values identifying the row.
+    pub key: JsonMap<String, JsonValue>,
+    /// The differing columns, every column for inserted and deleted rows.
+    pub columns: Vec<ColumnValueDiff>,
+}
+
+#[derive(Serialize, Deserialize, Type, Clone, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct DataDiff {
+    /// Columns that were compared, starting with the primary key columns.
+    pub columns: Vec<String>,
+    /// Columns that only exist in one of the tables, or whose values can't be decoded to be
+    /// compared, e.g binary columns, so they weren't compared.
+    pub skipped_columns: Vec<String>,
+    /// Differing rows of the chunk, in primary key order.
+    pub rows: Vec<RowDiff>,
+    /// Number of rows of the chunk that are the same in both tables.
+    pub unchanged: u64,
+    /// Statements reconciling the chunk's rows of the target table with the source table's.
+    pub script: Vec<String>,
+    /// Primary key of the chunk's last row, pass it back to compare the next chunk.
+    /// `None` once both tables have been compared entirely.
+    pub next_key: Option<JsonMap<String, JsonValue>>,
+}
+
+/// Compare the rows of `source_table` and `target_table` whose primary key comes after
+/// `after`, matched by their primary key.
+///
+/// Both tables are read in primary key order, a batch at a time, and merged as they're read.
+/// The comparison stops after [`CHUNK_SIZE`] differing rows, so the diff of large tables is
+/// read chunk by chunk starting from [`DataDiff::next_key`].
+pub async fn diff_table_data(
+    source: &DatabaseConnection,
+    source_table: &TableInfo,
+    target: &DatabaseConnection,
+    target_table: &TableInfo,
+    after: Option<&JsonMap<String, JsonValue>>,
+) -> Result<DataDiff> {
+    let driver = source.driver();
+    if driver != target.driver() {
+        return Err(TxError::DriverMismatch(driver, target.driver()));
+    }
+
+    let primary_key: Vec<&ColumnInfo> = source_table.columns.iter().filter(|c| c.pk).collect();
+    if primary_key.is_empty() {
+        return Err(comparison(format!(
+            "table `{}` has no primary key",
+            source_table.name
+        )));
+    }
+    if !primary_key.iter().map(|c| &c.name).eq(target_table
+        .columns
+        .iter()
+        .filter(|c| c.pk)
+        .map(|c| &c.name))
+    {
+        return Err(comparison(format!(
+            "tables `{}` and `{}` have different primary keys",
+            source_table.name, target_table.name
+        )));
+    }
+    if let Some(column) = primary_key.iter().find(|c| {
+        !matches!(
+            c.r#type,
+            CustomColumnType::Integer
+                | CustomColumnType::PositiveInteger
+                | CustomColumnType::String
+                | CustomColumnType::Text
+                | CustomColumnType::Uuid
+        )
+    }) {
+        return Err(comparison(format!(
+            "the type of primary key column `{}` can't be ordered reliably",
+            column.name
+        )));
+    }
+
+    let key_columns: Vec<String> = primary_key.iter().map(|c| c.name.clone()).collect();
+    let mut columns = key_columns.clone();
+    columns.extend(
+        source_table
+            .columns
+            .iter()
+            .filter(|c| {
+                !c.pk
+                    && target_table
+                        .columns
+                        .iter()
+                        .any(|t| t.name == c.name && comparable(c) && comparable(t))
+            })
+            .map(|c| c.name.clone()),
+    );
+    let skipped_columns = source_table
+        .columns
+        .iter()
+        .chain(&target_table.columns)
+        .map(|c| c.name.clone())
+        .filter(|name| !columns.contains(name))
+        .fold(vec![], |mut skipped, name| {
+            if !skipped.contains(&name) {
+                skipped.push(name);
+            }
+            skipped
+        });
+
+    let after: Option<Vec<Value>> = after.map(|key| {
+        key_columns
+            .iter()
+            .map(|c| literal(key.get(c).unwrap_or(&JsonValue::Null)))
+            .collect()
+    });
+    let mut source_rows = OrderedRows::new(
+        source,
+        &source_table.name,
+        &primary_key,
+        &columns,
+        after.clone(),
+    );
+    let mut target_rows =
+        OrderedRows::new(target, &target_table.name, &primary_key, &columns, after);
+    let mut diff = DataDiff {
+        columns,
+        skipped_columns,
+        ..Default::default()
+    };
+    let mut script = Script::new(driver, &target_table.name, &key_columns);
+
+    loop {
+        source_rows.fill().await?;
+        target_rows.fill().await?;
+        let ordering = match (source_rows.peek(), target_rows.peek()) {
+            (None, None) => break,
+            (Some(_), None) => Ordering::Less,
+            (None, Some(_)) => Ordering::Greater,
+            (Some(source), Some(target)) => compare_keys(&key_columns, source, target),
+        };
+        let (source_row, target_row) = match ordering {
+            Ordering::Less => (source_rows.pop(), None),
+            Ordering::Greater => (None, target_rows.pop()),
+            Ordering::Equal => (source_rows.pop(), target_rows.pop()),
+        };
+
+        let (kind, columns) = match (&source_row, &target_row) {
+            (Some(source), None) => (
+                RowDiffKind::Inserted,
+                column_diffs(&diff.columns, source, None),
+            ),
+            // Deleted rows only have values in the target table.
+            (None, Some(target)) => (
+                RowDiffKind::Deleted,
+                column_diffs(&diff.columns, target, None)
+                    .into_iter()
+                    .map(|c| ColumnValueDiff {
+                        column: c.column,
+                        source: None,
+                        target: c.source,
+                    })
+                    .collect(),
+            ),
+            (Some(source), Some(target)) => {
+                let columns = column_diffs(&diff.columns, source, Some(target));
+                if columns.is_empty() {
+                    diff.unchanged += 1;
+                    continue;
+                }
+                (RowDiffKind::Changed, columns)
+            }
+            (None, None) => break,
+        };
+        let Some(row) = source_row.as_ref().or(target_row.as_ref()) else {
+            break;
+        };
+        let key: JsonMap<String, JsonValue> = key_columns
+            .iter()
+            .map(|c| (c.clone(), row.get(c).cloned().unwrap_or_default()))
+            .collect();
+        script.push(kind, &key, &columns);
+        diff.rows.push(RowDiff {
+            kind,
+            key: key.clone(),
+            columns,
+        });
+        if diff.rows.len() == CHUNK_SIZE {
+            diff.next_key = Some(key);
+            break;
+        }
+    }
+
+    diff.script = script.statements();
+    Ok(diff)
+}
+
+/// Whether the values of `column` are decoded faithfully enough to be compared, binary
+/// values and those of types without a decoder would all read as `NULL`.
+fn comparable(column: &ColumnInfo) -> bool {
+    !matches!(
+        column.r#type,
+        CustomColumnType::Binary | CustomColumnType::Custom | CustomColumnType::UnSupported
+    )
+}
+
+/// Differing values between `row` and `other`, or all of `row`'s values if there's no `other`.
+fn column_diffs(columns: &[String], row: &Row, other: Option<&Row>) -> Vec<ColumnValueDiff> {
+    columns
+        .iter()
+        .filter_map(|column| {
+            let value = row.get(column).cloned().unwrap_or_default();
+            let other_value = other.map(|other| other.get(column).cloned().unwrap_or_default());
+            (other_value.as_ref() != Some(&value)).then(|| ColumnValueDiff {
+                column: column.clone(),
+                source: Some(value),
+                target: other_value,
+            })
+        })
+        .collect()
+}
+
+/// Reads a table's rows in primary key order, a batch at a time.
+struct OrderedRows<'a> {
+    conn: &'a DatabaseConnection,
+    query: SelectStatement,
+    /// Expressions the rows are ordered by, one per primary key column.
+    key_exprs: Vec<SimpleExpr>,
+    key_columns: Vec<String>,
+    last_key: Option<Vec<Value>>,
+    rows: VecDeque<Row>,
+    exhausted: bool,
+}
+
+impl<'a> OrderedRows<'a> {
+    fn new(
+        conn: &'a DatabaseConnection,
+        table_name: &str,
+        primary_key: &[&ColumnInfo],
+        columns: &[String],
+        after: Option<Vec<Value>>,
+    ) -> Self {
+        let driver = conn.driver();
+        let key_exprs: Vec<SimpleExpr> = primary_key.iter().map(|c| key_expr(&driver, c)).collect();
+        let mut query = Query::select();
+        query
+            .columns(columns.iter().map(Alias::new))
+            .from(Alias::new(table_name))
+            .limit(BATCH_SIZE);
+        for expr in &key_exprs {
+            query.order_by_expr(expr.clone(), Order::Asc);
+        }
+        Self {
+            conn,
+            query,
+            key_exprs,
+            key_columns: primary_key.iter().map(|c| c.name.clone()).collect(),
+            last_key: after,
+            rows: VecDeque::new(),
+            exhausted: false,
+        }
+    }
+
+    /// Read the next batch once the current one is consumed.
+    async fn fill(&mut self) -> Result<()> {
+        if !self.rows.is_empty() || self.exhausted {
+            return Ok(());
+        }
+
+        let mut query = self.query.clone();
+        if let Some(last_key) = &self.last_key {
+            query.and_where(
+                Expr::tuple(self.key_exprs.clone())
+                    .gt(Expr::tuple(last_key.iter().cloned().map(SimpleExpr::Value))),
+            );
+        }
+        let (stmt, values) = query.build_any_sqlx(self.conn.into_builder().as_ref());
+        let rows = decode_raw_rows(self.conn.fetch_all(&stmt, values).await?)?;
+
+        self.exhausted = (rows.len() as u64) < BATCH_SIZE;
+        if let Some(last) = rows.last() {
+            self.last_key = Some(
+                self.key_columns
+                    .iter()
+                    .map(|c| literal(last.0.get(c).unwrap_or(&JsonValue::Null)))
+                    .collect(),
+            );
+        }
+        self.rows.extend(rows.into_iter().map(|row| row.0));
+        Ok(())
+    }
+
+    fn peek(&self) -> Option<&Row> {
+        self.rows.front()
+    }
+
+    fn pop(&mut self) -> Option<Row> {
+        self.rows.pop_front()
+    }
+}
+
+/// Expression ordering rows by `column` the same way [`compare_values`] does, text
+/// is compared byte by byte regardless of the column's collation.
+fn key_expr(driver: &Drivers, column: &ColumnInfo) -> SimpleExpr {
+    if matches!(
+        column.r#type,
+        CustomColumnType::Integer | CustomColumnType::PositiveInteger
+    ) {
+        return Expr::col(Alias::new(&column.name)).into();
+    }
+    let name = quoted(schema_builder(driver).as_ref(), &column.name);
+    Expr::cust(match driver {
+        Drivers::SQLite => format!("{name} COLLATE BINARY"),
+        Drivers::PostgreSQL => format!("CAST({name} AS TEXT) COLLATE \"C\""),
+        Drivers::MySQL => format!("BINARY {name}"),
+    })
+}
+
+fn compare_keys(key_columns: &[String], a: &Row, b: &Row) -> Ordering {
+    key_columns
+        .iter()
+        .map(|c| {
+            compare_values(
+                a.get(c).unwrap_or(&JsonValue::Null),
+                b.get(c).unwrap_or(&JsonValue::Null),
+            )
+        })
+        .find(|ordering| ordering.is_ne())
+        .unwrap_or(Ordering::Equal)
+}
+
+/// Orders values like SQLite does: nulls first, then numbers, then text.
+fn compare_values(a: &JsonValue, b: &JsonValue) -> Ordering {
+    match (a, b) {
+        (JsonValue::Number(a), JsonValue::Number(b)) => match (a.as_i64(), b.as_i64()) {
+            (Some(a), Some(b)) => a.cmp(&b),
+            _ => a
+                .as_f64()
+                .partial_cmp(&b.as_f64())
+                .unwrap_or(Ordering::Equal),
+        },
+        (JsonValue::String(a), JsonValue::String(b)) => a.as_bytes().cmp(b.as_bytes()),
+        _ => {
+            let rank = |value: &JsonValue| match value {
+                JsonValue::Null => 0,
+                JsonValue::Bool(_) | JsonValue::Number(_) => 1,
+                _ => 2,
+            };
+            rank(a).cmp(&rank(b))
+        }
+    }
+}
+
+/// Value of a decoded cell, strings are left for the database to cast to the column's type.
+pub(crate) fn literal(value: &JsonValue) -> Value {
+    match value {
+        JsonValue::Null => Value::String(None),
+        JsonValue::Bool(b) => Value::Bool(Some(*b)),
+        JsonValue::Number(n) => {
+            if let Some(i) = n.as_i64() {
+                Value::BigInt(Some(i))
+            } else if let Some(u) = n.as_u64() {
+                Value::BigUnsigned(Some(u))
+            } else {
+                Value::Double(n.as_f64())
+            }
+        }
+        JsonValue::String(s) => Value::String(Some(Box::new(s.clone()))),
+        JsonValue::Array(_) | JsonValue::Object(_) => {
+            Value::String(Some(Box::new(value.to_string())))
+        }
+    }
+}
+
+/// Statements reconciling a chunk of the target table, deletions run first so they
+/// can't conflict with the chunk's inserted rows.
+struct Script<'a> {
+    driver: Drivers,
+    table_name: &'a str,
+    key_columns: &'a [String],
+    deletes: Vec<String>,
+    updates: Vec<String>,
+    inserts: Vec<String>,
+}
+
+impl<'a> Script<'a> {
+    fn new(driver: Drivers, table_name: &'a str, key_columns: &'a [String]) -> Self {
+        Self {
+            driver,
+            table_name,
+            key_columns,
+            deletes: vec![],
+            updates: vec![],
+            inserts: vec![],
+        }
+    }
+
+    fn push(&mut self, kind: RowDiffKind, key: &Row, columns: &[ColumnValueDiff]) {
+        let table = Alias::new(self.table_name);
+        let key_cond = self.key_columns.iter().fold(Cond::all(), |cond, c| {
+            let value = literal(key.get(c).unwrap_or(&JsonValue::Null));
+            cond.add(Expr::col(Alias::new(c)).eq(value))
+        });
+        let source_values = columns.iter().filter_map(|c| {
+            c.source
+                .as_ref()
+                .map(|value| (Alias::new(&c.column), SimpleExpr::Value(literal(value))))
+        });
+
+        match kind {
+            RowDiffKind::Inserted => {
+                let (names, values): (Vec<_>, Vec<_>) = source_values.unzip();
+                let stmt = Query::insert()
+                    .into_table(table)
+                    .columns(names)
+                    .values_panic(values)
+                    .to_owned();
+                self.inserts.push(inline(&self.driver, &stmt));
+            }
+            RowDiffKind::Deleted => {
+                let stmt = Query::delete()
+                    .from_table(table)
+                    .cond_where(key_cond)
+                    .to_owned();
+                self.deletes.push(inline(&self.driver, &stmt));
+            }
+            RowDiffKind::Changed => {
+                let stmt = Query::update()
+                    .table(table)
+                    .values(source_values)
+                    .cond_where(key_cond)
+                    .to_owned();
+                self.updates.push(inline(&self.driver, &stmt));
+            }
+        }
+    }
+
+    fn statements(self) -> Vec<String> {
+        [self.deletes, self.updates, self.inserts].concat()
+    }
+}
+
+fn comparison(reason: String) -> TxError {
+    TxError::DataComparison(reason)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn tables(
+        source_rows: &str,
+        target_rows: &str,
+    ) -> (DatabaseConnection, DatabaseConnection) {
+        let source = DatabaseConnection::in_memory().await.unwrap();
+        let target = DatabaseConnection::in_memory().await.unwrap();
+        for (conn, rows) in [(&source, source_rows), (&target, target_rows)] {
+            conn.execute("CREATE TABLE t (id INTEGER PRIMARY KEY, name TEXT, data BLOB)")
+                .await
+                .unwrap();
+            conn.execute(rows).await.unwrap();
+        }
+        (source, target)
+    }
+
+    #[tokio::test]
+    async fn script_reconciles_the_target_table() {
+        let (mut source, mut target) = tables(
+            "INSERT INTO t VALUES (1, 'a', NULL), (2, 'b', NULL), (4, 'd', NULL)",
+            "INSERT INTO t VALUES (1, 'a', NULL), (2, 'x', NULL), (3, 'c', NULL)",
+        )
+        .await;
+
+        let diff = source
+            .diff_table_data("t", &mut target, "t", None)
+            .await
+            .unwrap();
+        let kinds: Vec<_> = diff
+            .rows
+            .iter()
+            .map(|r| (r.key["id"].clone(), r.kind))
+            .collect();
+        assert_eq!(
+            kinds,
+            vec![
+                (2.into(), RowDiffKind::Changed),
+                (3.into(), RowDiffKind::Deleted),
+                (4.into(), RowDiffKind::Inserted),
+            ]
+        );
+        assert_eq!(diff.unchanged, 1);
+        assert_eq!(diff.next_key, None);
+
+        for stmt in &diff.script {
+            target.execute(stmt).await.unwrap();
+        }
+        let diff = source
+            .diff_table_data("t", &mut target, "t", None)
+            .await
+            .unwrap();
+        assert!(diff.rows.is_empty());
+        assert_eq!(diff.unchanged, 3);
+    }
+
+    #[tokio::test]
+    async fn differing_rows_are_compared_in_chunks() {
+        let (mut source, mut target) = tables(
+            "WITH RECURSIVE n(i) AS (SELECT 1 UNION ALL SELECT i + 1 FROM n WHERE i < 2500)
+             INSERT INTO t SELECT i, 'row ' || i, NULL FROM n",
+            "INSERT INTO t VALUES (1, 'row 1', NULL)",
+        )
+        .await;
+
+        let mut after = None;
+        let mut chunks = vec![];
+        loop {
+            let diff = source
+                .diff_table_data("t", &mut target, "t", after.as_ref())
+                .await
+                .unwrap();
+            chunks.push((diff.rows.len(), diff.script.len(), diff.unchanged));
+            assert!(diff.rows.iter().all(|r| r.kind == RowDiffKind::Inserted));
+            if let (Some(first), Some(previous)) = (diff.rows.first(), &after) {
+                assert!(compare_keys(&["id".to_string()], &first.key, previous).is_gt());
+            }
+            after = diff.next_key;
+            if after.is_none() {
+                break;
+            }
+        }
+        assert_eq!(
+            chunks,
+            vec![(1000, 1000, 1), (1000, 1000, 0), (499, 499, 0)]
+        );
+    }
+
+    #[tokio::test]
+    async fn binary_columns_are_skipped() {
+        let (mut source, mut target) = tables(
+            "INSERT INTO t VALUES (1, 'a', X'00')",
+            "INSERT INTO t VALUES (1, 'a', X'FF')",
+        )
+        .await;
+
+        let diff = source
+            .diff_table_data("t", &mut target, "t", None)
+            .await
+            .unwrap();
+        assert_eq!(diff.columns, vec!["id", "name"]);
+        assert_eq!(diff.skipped_columns, vec!["data"]);
+        assert_eq!(diff.unchanged, 1);
+    }
+}
@@ -0,0 +1,109 @@
+//! Data files loaded into a table of an in-memory SQLite database, so they can be browsed
+//! and queried like any other table.
+
+use crate::{
+    DatabaseConnection,
+    import::{
+        ColumnMapping, ImportFormat, ImportOptions, ImportSummary, import_rows, preview_import,
+    },
+};
+use serde::{Deserialize, Serialize};
+use specta::Type;
+use std::path::Path;
+use tx_lib::{Result, TxError};
+
+/// Number of rows inserted per statement while loading a file.
+const BATCH_SIZE: u32 = 1_000;
+
+#[derive(Serialize, Deserialize, Type, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct DataFile {
+    pub path: String,
+    /// Table the rows were loaded into, named after the file.
+    pub table_name: String,
+    /// Values that don't fit the types inferred from the first rows are kept as text.
+    pub import: ImportSummary,
+}
+
+/// Load a file into a new in-memory SQLite database, creating a table whose columns have
+/// the types inferred from the file's first rows.
+///
+/// `on_progress` is called after each inserted batch with the number of rows loaded so
+/// far.
+pub async fn open_data_file(
+    path: &str,
+    format: &ImportFormat,
+    on_progress: impl FnMut(u64),
+) -> Result<(DatabaseConnection, DataFile)> {
+    let preview = preview_import(path, format, 0)?;
+    if preview.columns.is_empty() {
+        return Err(TxError::Import("the file has no columns".to_string()));
+    }
+    let table_name = Path::new(path)
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .filter(|stem| !stem.is_empty())
+        .unwrap_or("data")
+        .to_string();
+    let options = ImportOptions {
+        path: path.to_string(),
+        format: format.clone(),
+        table_name: table_name.clone(),
+        create_table: true,
+        columns: preview
+            .columns
+            .into_iter()
+            .map(|column| ColumnMapping {
+                source: column.name.clone(),
+                target: column.name,
+                r#type: column.r#type,
+            })
+            .collect(),
+        batch_size: BATCH_SIZE,
+    };
+
+    let mut conn = DatabaseConnection::in_memory().await?;
+    let import = match import_rows(&mut conn, &options, true, on_progress).await {
+        Ok(summary) => summary,
+        Err(e) => {
+            conn.close().await;
+            return Err(e);
+        }
+    };
+
+    Ok((
+        conn,
+        DataFile {
+            path: path.to_string(),
+            table_name,
+            import,
+        },
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::import::CsvImportOptions;
+
+    #[tokio::test]
+    async fn mismatched_values_are_kept_as_text() {
+        let path = std::env::temp_dir().join(format!("tx-data-{}.csv", std::process::id()));
+        let rows: String = (0..1_000).map(|i| format!("{i}\n")).collect();
+        std::fs::write(&path, format!("n\n{rows}not a number\n")).unwrap();
+        let path = path.to_string_lossy().into_owned();
+
+        let format = ImportFormat::Csv(CsvImportOptions::default());
+        let (conn, file) = open_data_file(&path, &format, |_| {}).await.unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(file.import.rows_imported, 1_001);
+        assert_eq!(file.import.rows_failed, 0);
+
+        let table = format!(
+            "SELECT typeof(n) AS type FROM \"{}\" ORDER BY rowid DESC",
+            file.table_name
+        );
+        let last = crate::DecodedRow::from(conn.fetch_one(&table).await.unwrap());
+        assert_eq!(last.0["type"], "text");
+    }
+}
@@ -1,58 +1,176 @@
 use crate::{
-    query::{ExecResult, QueryResult},
-    schema::Schema,
+    MySQLHandler, PostgresHandler, SQLiteHandler,
+    data_diff::{self, DataDiff},
+    ddl::{self, SchemaChange, TableDefinition},
+    diff::{self, SchemaDiff},
+    query::{DecodedRow, ExecResult, QueryResult},
+    schema::{Schema, TableDiscovery, TableInfo},
+    ssh::{LOCALHOST, Tunnel, open_tunnel},
 };
+use futures_util::{StreamExt, TryStreamExt, stream::BoxStream};
 use sea_query_binder::SqlxValues;
-use sea_schema::{
-    mysql::discovery::SchemaDiscovery as MySQLSchemaDiscovery,
-    postgres::discovery::SchemaDiscovery as PostgresSchemaDiscovery,
-    sea_query::{MysqlQueryBuilder, PostgresQueryBuilder, QueryBuilder, SqliteQueryBuilder},
-    sqlite::discovery::SchemaDiscovery as SqliteSchemaDiscovery,
+use sea_schema::sea_query::{
+    MysqlQueryBuilder, PostgresQueryBuilder, QueryBuilder, SqliteQueryBuilder,
 };
+use serde_json::{Map as JsonMap, Value as JsonValue};
 use sqlx::{
-    Connection,
-    mysql::{MySqlConnectOptions, MySqlPool},
+    Connection, Executor, MySql, Postgres, Sqlite, Transaction,
+    mysql::{MySqlConnectOptions, MySqlPool, MySqlPoolOptions},
     postgres::{PgConnectOptions, PgPool},
-    sqlite::{SqliteConnectOptions, SqlitePool},
+    sqlite::{SqliteConnectOptions, SqlitePool, SqlitePoolOptions},
+};
+use std::sync::Arc;
+use tx_lib::{
+    Result, TxError,
+    connection::ConnectOptions,
+    types::{Drivers, SqliteOptions},
 };
-use tx_lib::{Result, TxError, types::Drivers};
 
+/// Clones share the pool and SSH tunnel, so closing any of them closes all of them.
+#[derive(Clone)]
 pub enum DatabaseConnection {
-    Sqlite { pool: SqlitePool, schema: Schema },
-    Postgres { pool: PgPool, schema: Schema },
-    Mysql { pool: MySqlPool, schema: Schema },
+    Sqlite {
+        pool: SqlitePool,
+        schema: Schema,
+        /// Options the pool was opened with, the tables of attached databases are
+        /// discovered with the same key and extensions.
+        options: Arc<SqliteOptions>,
+    },
+    Postgres {
+        pool: PgPool,
+        schema: Schema,
+        /// SSH tunnel the pool connects through, closed with the connection.
+        tunnel: Option<Arc<Tunnel>>,
+    },
+    Mysql {
+        pool: MySqlPool,
+        schema: Schema,
+        /// SSH tunnel the pool connects through, closed with the connection.
+        tunnel: Option<Arc<Tunnel>>,
+    },
+}
+
+/// Statements performing a schema change.
+enum ChangeStatements {
+    Transaction(Vec<String>),
+    /// Statements rebuilding a SQLite table, which must run with foreign keys disabled.
+    Rebuild {
+        table_name: String,
+        stmts: Vec<String>,
+    },
+}
+
+/// An open transaction on any of the supported databases, rolled back if it's
+/// dropped without being committed.
+pub enum DatabaseTransaction {
+    Sqlite(Transaction<'static, Sqlite>),
+    Postgres(Transaction<'static, Postgres>),
+    Mysql(Transaction<'static, MySql>),
+}
+
+impl DatabaseTransaction {
+    pub async fn execute_with(&mut self, stmt: &str, values: SqlxValues) -> Result<ExecResult> {
+        let res: ExecResult = match self {
+            DatabaseTransaction::Sqlite(tx) => sqlx::query_with(stmt, values)
+                .execute(&mut **tx)
+                .await?
+                .into(),
+            DatabaseTransaction::Postgres(tx) => sqlx::query_with(stmt, values)
+                .execute(&mut **tx)
+                .await?
+                .into(),
+            DatabaseTransaction::Mysql(tx) => sqlx::query_with(stmt, values)
+                .execute(&mut **tx)
+                .await?
+                .into(),
+        };
+
+        Ok(res)
+    }
+    pub async fn commit(self) -> Result<()> {
+        match self {
+            DatabaseTransaction::Sqlite(tx) => tx.commit().await?,
+            DatabaseTransaction::Postgres(tx) => tx.commit().await?,
+            DatabaseTransaction::Mysql(tx) => tx.commit().await?,
+        }
+        Ok(())
+    }
 }
 
 impl DatabaseConnection {
-    pub async fn connect(url: &str, driver: &Drivers) -> Result<Self> {
+    /// Connect to the database at `url` with `connect_options`, e.g through an SSH tunnel.
+    pub async fn connect(
+        url: &str,
+        driver: &Drivers,
+        connect_options: &ConnectOptions,
+    ) -> Result<Self> {
+        let mut con =
+            Self::connect_with_schema(url, driver, connect_options, Schema::default()).await?;
+        con.refresh_schema(None).await?;
+        Ok(con)
+    }
+
+    /// Connect without listing the tables, using an already known (e.g cached) `schema` instead.
+    pub async fn connect_with_schema(
+        url: &str,
+        driver: &Drivers,
+        connect_options: &ConnectOptions,
+        schema: Schema,
+    ) -> Result<Self> {
+        let ConnectOptions {
+            sqlite,
+            read_only,
+            ssh,
+        } = connect_options;
         // TODO: use match statement once if_let_guard feature is stable.
         let con = match driver {
             Drivers::SQLite => {
-                let pool = SqlitePool::connect_with(url.parse::<SqliteConnectOptions>()?).await?;
-                let schema = SqliteSchemaDiscovery::new(pool.clone())
-                    .discover()
-                    .await
-                    .unwrap()
-                    .into();
-                DatabaseConnection::Sqlite { pool, schema }
+                let pool = SQLiteHandler::connect(url, sqlite, *read_only).await?;
+                DatabaseConnection::Sqlite {
+                    pool,
+                    schema,
+                    options: Arc::new(sqlite.clone()),
+                }
             }
             Drivers::PostgreSQL => {
-                let pool = PgPool::connect_with(url.parse::<PgConnectOptions>()?).await?;
-                let schema = PostgresSchemaDiscovery::new(pool.clone(), "public")
-                    .discover()
-                    .await
-                    .unwrap()
-                    .into();
-                DatabaseConnection::Postgres { pool, schema }
+                let mut options = url.parse::<PgConnectOptions>()?;
+                if *read_only {
+                    options = options.options([("default_transaction_read_only", "on")]);
+                }
+                let tunnel =
+                    open_tunnel(ssh.as_ref(), options.get_host(), options.get_port()).await?;
+                if let Some(tunnel) = &tunnel {
+                    options = options.host(LOCALHOST).port(tunnel.local_port());
+                }
+                let pool = PgPool::connect_with(options).await?;
+                DatabaseConnection::Postgres {
+                    pool,
+                    schema,
+                    tunnel: tunnel.map(Arc::new),
+                }
             }
             Drivers::MySQL => {
-                let pool = MySqlPool::connect_with(url.parse::<MySqlConnectOptions>()?).await?;
-                let schema = MySQLSchemaDiscovery::new(pool.clone(), "public")
-                    .discover()
-                    .await
-                    .unwrap()
-                    .into();
-                DatabaseConnection::Mysql { pool, schema }
+                let mut pool_options = MySqlPoolOptions::new();
+                if *read_only {
+                    pool_options = pool_options.after_connect(|conn, _| {
+                        Box::pin(async move {
+                            conn.execute("SET SESSION TRANSACTION READ ONLY").await?;
+                            Ok(())
+                        })
+                    });
+                }
+                let mut options = url.parse::<MySqlConnectOptions>()?;
+                let tunnel =
+                    open_tunnel(ssh.as_ref(), options.get_host(), options.get_port()).await?;
+                if let Some(tunnel) = &tunnel {
+                    options = options.host(LOCALHOST).port(tunnel.local_port());
+                }
+                let pool = pool_options.connect_with(options).await?;
+                DatabaseConnection::Mysql {
+                    pool,
+                    schema,
+                    tunnel: tunnel.map(Arc::new),
+                }
             }
         };
         Ok(con)
@@ -77,6 +195,47 @@ impl DatabaseConnection {
         //     return Err(TxError::UnsupportedDriver(String::default()));
         // }
     }
+
+    /// Connect to a new, empty in-memory SQLite database.
+    pub async fn in_memory() -> Result<Self> {
+        // The database is gone once its connection is closed, so a single one is kept
+        // open for as long as the pool. It isn't opened as a `mode=memory` URI, the files
+        // written by `VACUUM INTO` would be in memory too.
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .idle_timeout(None)
+            .max_lifetime(None)
+            .connect_with(SqliteConnectOptions::new().filename(":memory:"))
+            .await?;
+        Ok(DatabaseConnection::Sqlite {
+            pool,
+            schema: Schema::default(),
+            options: Arc::default(),
+        })
+    }
+
+    /// Alias of the attached SQLite database `table_name` is qualified by, and the table's
+    /// name in that database. `None` for tables of the main database, even if their name
+    /// contains a dot.
+    pub fn attached_table<'a>(&self, table_name: &'a str) -> Option<(&'a str, &'a str)> {
+        let DatabaseConnection::Sqlite { options, .. } = self else {
+            return None;
+        };
+        table_name.split_once('.').filter(|(alias, _)| {
+            options
+                .attached_databases
+                .iter()
+                .any(|database| database.alias.eq_ignore_ascii_case(alias))
+        })
+    }
+
+    pub fn driver(&self) -> Drivers {
+        match self {
+            DatabaseConnection::Sqlite { .. } => Drivers::SQLite,
+            DatabaseConnection::Postgres { .. } => Drivers::PostgreSQL,
+            DatabaseConnection::Mysql { .. } => Drivers::MySQL,
+        }
+    }
     pub fn into_builder(&self) -> Box<dyn QueryBuilder> {
         match self {
             DatabaseConnection::Sqlite { .. } => Box::new(SqliteQueryBuilder),
@@ -84,10 +243,13 @@ impl DatabaseConnection {
             DatabaseConnection::Mysql { .. } => Box::new(MysqlQueryBuilder),
         }
     }
-    pub async fn ping(url: &str, driver: &Drivers) -> Result<()> {
+    /// Check the database at `url` can be connected to with `connect_options`, whose
+    /// `read_only` is ignored.
+    pub async fn ping(url: &str, driver: &Drivers, connect_options: &ConnectOptions) -> Result<()> {
+        let ssh = connect_options.ssh.as_ref();
         match driver {
             Drivers::SQLite => {
-                let pool = SqlitePool::connect_with(url.parse::<SqliteConnectOptions>()?).await?;
+                let pool = SQLiteHandler::connect(url, &connect_options.sqlite, false).await?;
 
                 let ping_result = pool
                     .acquire()
@@ -101,7 +263,12 @@ impl DatabaseConnection {
                 ping_result
             }
             Drivers::PostgreSQL => {
-                let pool = PgPool::connect_with(url.parse::<PgConnectOptions>()?).await?;
+                let mut options = url.parse::<PgConnectOptions>()?;
+                let tunnel = open_tunnel(ssh, options.get_host(), options.get_port()).await?;
+                if let Some(tunnel) = &tunnel {
+                    options = options.host(LOCALHOST).port(tunnel.local_port());
+                }
+                let pool = PgPool::connect_with(options).await?;
 
                 let ping_result = pool
                     .acquire()
@@ -111,11 +278,19 @@ impl DatabaseConnection {
                     .map_err(|_| TxError::PingError);
 
                 pool.close().await;
+                if let Some(tunnel) = tunnel {
+                    tunnel.close().await;
+                }
 
                 ping_result
             }
             Drivers::MySQL => {
-                let pool = MySqlPool::connect_with(url.parse::<MySqlConnectOptions>()?).await?;
+                let mut options = url.parse::<MySqlConnectOptions>()?;
+                let tunnel = open_tunnel(ssh, options.get_host(), options.get_port()).await?;
+                if let Some(tunnel) = &tunnel {
+                    options = options.host(LOCALHOST).port(tunnel.local_port());
+                }
+                let pool = MySqlPool::connect_with(options).await?;
 
                 let ping_result = pool
                     .acquire()
@@ -125,6 +300,9 @@ impl DatabaseConnection {
                     .map_err(|_| TxError::PingError);
 
                 pool.close().await;
+                if let Some(tunnel) = tunnel {
+                    tunnel.close().await;
+                }
 
                 ping_result
             }
@@ -153,6 +331,51 @@ impl DatabaseConnection {
         };
         Ok(res)
     }
+    /// Stream the rows returned by `stmt`, decoding them as they're read instead of
+    /// loading them all in memory.
+    pub fn fetch_stream<'a>(&'a self, stmt: &'a str) -> BoxStream<'a, Result<DecodedRow>> {
+        match self {
+            DatabaseConnection::Sqlite { pool, .. } => sqlx::query(stmt)
+                .fetch(pool)
+                .map_ok(DecodedRow::from)
+                .map_err(TxError::from)
+                .boxed(),
+            DatabaseConnection::Postgres { pool, .. } => sqlx::query(stmt)
+                .fetch(pool)
+                .map_ok(DecodedRow::from)
+                .map_err(TxError::from)
+                .boxed(),
+            DatabaseConnection::Mysql { pool, .. } => sqlx::query(stmt)
+                .fetch(pool)
+                .map_ok(DecodedRow::from)
+                .map_err(TxError::from)
+                .boxed(),
+        }
+    }
+    /// Stream the undecoded rows returned by `stmt`, e.g to read their columns' order.
+    pub fn fetch_raw_stream<'a>(
+        &'a self,
+        stmt: &'a str,
+        values: SqlxValues,
+    ) -> BoxStream<'a, Result<QueryResult>> {
+        match self {
+            DatabaseConnection::Sqlite { pool, .. } => sqlx::query_with(stmt, values)
+                .fetch(pool)
+                .map_ok(QueryResult::from)
+                .map_err(TxError::from)
+                .boxed(),
+            DatabaseConnection::Postgres { pool, .. } => sqlx::query_with(stmt, values)
+                .fetch(pool)
+                .map_ok(QueryResult::from)
+                .map_err(TxError::from)
+                .boxed(),
+            DatabaseConnection::Mysql { pool, .. } => sqlx::query_with(stmt, values)
+                .fetch(pool)
+                .map_ok(QueryResult::from)
+                .map_err(TxError::from)
+                .boxed(),
+        }
+    }
     pub async fn fetch_one(&self, stmt: &str) -> Result<QueryResult> {
         let res: QueryResult = match self {
             DatabaseConnection::Sqlite { pool, .. } => {
@@ -196,6 +419,49 @@ impl DatabaseConnection {
 
         Ok(res)
     }
+    /// Execute `stmts` in order inside a single transaction.
+    ///
+    /// **Note** that MySQL implicitly commits DDL statements, so they can't be rolled back.
+    pub async fn execute_in_transaction(&self, stmts: &[String]) -> Result<()> {
+        match self {
+            DatabaseConnection::Sqlite { pool, .. } => {
+                let mut tx = pool.begin().await?;
+                for stmt in stmts {
+                    sqlx::query(stmt).execute(&mut *tx).await?;
+                }
+                tx.commit().await?;
+            }
+            DatabaseConnection::Postgres { pool, .. } => {
+                let mut tx = pool.begin().await?;
+                for stmt in stmts {
+                    sqlx::query(stmt).execute(&mut *tx).await?;
+                }
+                tx.commit().await?;
+            }
+            DatabaseConnection::Mysql { pool, .. } => {
+                let mut tx = pool.begin().await?;
+                for stmt in stmts {
+                    sqlx::query(stmt).execute(&mut *tx).await?;
+                }
+                tx.commit().await?;
+            }
+        }
+        Ok(())
+    }
+    pub async fn begin(&self) -> Result<DatabaseTransaction> {
+        let tx = match self {
+            DatabaseConnection::Sqlite { pool, .. } => {
+                DatabaseTransaction::Sqlite(pool.begin().await?)
+            }
+            DatabaseConnection::Postgres { pool, .. } => {
+                DatabaseTransaction::Postgres(pool.begin().await?)
+            }
+            DatabaseConnection::Mysql { pool, .. } => {
+                DatabaseTransaction::Mysql(pool.begin().await?)
+            }
+        };
+        Ok(tx)
+    }
     pub async fn get_schema(&self) -> Schema {
         match self {
             DatabaseConnection::Sqlite { schema, .. } => schema.clone(),
@@ -204,11 +470,436 @@ impl DatabaseConnection {
         }
     }
 
+    /// Get a table's details, discovering and caching them if they weren't already.
+    pub async fn table_info(&mut self, table_name: &str) -> Result<TableInfo> {
+        if let Some(table) = self.schema_mut().table(table_name) {
+            return Ok(table.clone());
+        }
+
+        self.discover_tables(&[table_name.to_string()]).await?;
+
+        let schema = self.schema_mut();
+        if let Some(table) = schema.table(table_name) {
+            return Ok(table.clone());
+        }
+        let reason = schema
+            .failures()
+            .into_iter()
+            .find(|f| f.table_name == table_name)
+            .map(|f| f.reason)
+            .unwrap_or_else(|| format!("table `{table_name}` doesn't exist"));
+        Err(TxError::SchemaDiscovery(reason))
+    }
+
+    /// Discover the details of every table that wasn't discovered yet.
+    ///
+    /// Tables that fail to be discovered are recorded in the schema's failures
+    /// instead of failing the whole discovery.
+    pub async fn discover_remaining_tables(&mut self) -> Result<()> {
+        let tables_names = self.schema_mut().undiscovered();
+        if tables_names.is_empty() {
+            return Ok(());
+        }
+        self.discover_tables(&tables_names).await
+    }
+
+    /// Re-discover the schema without reconnecting.
+    ///
+    /// If `table_name` is provided only that table is re-discovered, otherwise the tables
+    /// are re-listed and their details will be discovered again on demand.
+    pub async fn refresh_schema(&mut self, table_name: Option<&str>) -> Result<()> {
+        match table_name {
+            Some(table_name) => {
+                self.discover_tables(&[table_name.to_string()]).await?;
+                // Only part of the schema was refreshed, so it no longer matches any version.
+                self.schema_mut().fingerprint = None;
+                Ok(())
+            }
+            None => {
+                let fingerprint = self
+                    .schema_fingerprint()
+                    .await
+                    .inspect_err(|e| log::warn!("Failed to fingerprint schema: {e}"))
+                    .ok();
+                let tables_names = self.tables_names().await?;
+                let schema = self.schema_mut();
+                *schema = Schema::new(tables_names);
+                schema.fingerprint = fingerprint;
+                Ok(())
+            }
+        }
+    }
+
+    /// Whether `table_name` currently exists, regardless of the cached schema.
+    pub async fn table_exists(&self, table_name: &str) -> Result<bool> {
+        Ok(self
+            .tables_names()
+            .await?
+            .iter()
+            .any(|name| name == table_name))
+    }
+
+    async fn tables_names(&self) -> Result<Vec<String>> {
+        let tables_names = match self {
+            DatabaseConnection::Sqlite { pool, .. } => SQLiteHandler::tables_names(pool).await?,
+            DatabaseConnection::Postgres { pool, .. } => {
+                PostgresHandler::tables_names(pool).await?
+            }
+            DatabaseConnection::Mysql { pool, .. } => MySQLHandler::tables_names(pool).await?,
+        };
+        Ok(tables_names.0)
+    }
+
+    /// Generate the statements performing `change`, without executing them.
+    ///
+    /// SQLite tables are rebuilt when `ALTER TABLE` can't perform the change.
+    pub async fn schema_change_statements(&mut self, change: &SchemaChange) -> Result<Vec<String>> {
+        match self.change_statements(change).await? {
+            ChangeStatements::Transaction(stmts) | ChangeStatements::Rebuild { stmts, .. } => {
+                Ok(stmts)
+            }
+        }
+    }
+
+    /// Perform `change` and re-discover the affected tables.
+    pub async fn apply_schema_change(&mut self, change: &SchemaChange) -> Result<()> {
+        match (self.change_statements(change).await?, &*self) {
+            (
+                ChangeStatements::Rebuild { table_name, stmts },
+                DatabaseConnection::Sqlite { pool, .. },
+            ) => SQLiteHandler::execute_rebuild(pool, &table_name, &stmts).await?,
+            (ChangeStatements::Transaction(stmts) | ChangeStatements::Rebuild { stmts, .. }, _) => {
+                self.execute_in_transaction(&stmts).await?
+            }
+        }
+
+        match change {
+            SchemaChange::Create(definition) => self.refresh_schema(Some(&definition.name)).await,
+            SchemaChange::Alter {
+                table_name,
+                definition,
+            } if *table_name != definition.name => self.refresh_schema(None).await,
+            SchemaChange::Alter { table_name, .. } | SchemaChange::Drop { table_name } => {
+                self.refresh_schema(Some(table_name)).await
+            }
+        }
+    }
+
+    async fn change_statements(&mut self, change: &SchemaChange) -> Result<ChangeStatements> {
+        let driver = self.driver();
+        let stmts = match change {
+            SchemaChange::Create(definition) => ddl::create_table(&driver, definition)?,
+            SchemaChange::Alter {
+                table_name,
+                definition,
+            } => {
+                let current = TableDefinition::from(&self.table_info(table_name).await?);
+                match (ddl::alter_table(&driver, &current, definition), &*self) {
+                    (
+                        Err(TxError::UnsupportedSchemaChange(_)),
+                        DatabaseConnection::Sqlite { pool, .. },
+                    ) => {
+                        return Ok(ChangeStatements::Rebuild {
+                            table_name: definition.name.clone(),
+                            stmts: SQLiteHandler::rebuild_table_statements(
+                                pool, &current, definition,
+                            )
+                            .await?,
+                        });
+                    }
+                    (stmts, _) => stmts?,
+                }
+            }
+            SchemaChange::Drop { table_name } => vec![ddl::drop_table(&driver, table_name)],
+        };
+        Ok(ChangeStatements::Transaction(stmts))
+    }
+
+    /// Rebuild a SQLite table so it matches `definition`, even if `ALTER TABLE` could
+    /// perform the change.
+    pub async fn rebuild_table(
+        &mut self,
+        table_name: &str,
+        definition: &TableDefinition,
+    ) -> Result<()> {
+        let current = TableDefinition::from(&self.table_info(table_name).await?);
+        let DatabaseConnection::Sqlite { pool, .. } = self else {
+            return Err(TxError::UnsupportedSchemaChange(
+                "only SQLite tables can be rebuilt".to_string(),
+            ));
+        };
+        SQLiteHandler::rebuild_table(pool, &current, definition).await?;
+
+        if table_name != definition.name {
+            self.refresh_schema(None).await
+        } else {
+            self.refresh_schema(Some(table_name)).await
+        }
+    }
+
+    /// Compare this database's schema with `other`'s, along with the script migrating
+    /// this database so its schema matches `other`'s.
+    pub async fn diff_schema(&mut self, other: &mut DatabaseConnection) -> Result<SchemaDiff> {
+        if self.driver() != other.driver() {
+            return Err(TxError::DriverMismatch(self.driver(), other.driver()));
+        }
+        self.discover_remaining_tables().await?;
+        other.discover_remaining_tables().await?;
+
+        let (from, to) = (self.get_schema().await, other.get_schema().await);
+        let mut diff = diff::diff_schemas(&from, &to);
+        for change in diff::migration_changes(&from, &to) {
+            diff.script
+                .extend(self.schema_change_statements(&change).await?);
+        }
+        // Rebuilt SQLite tables are dropped, which must not cascade to the rows referencing them.
+        if self.driver() == Drivers::SQLite && !diff.script.is_empty() {
+            diff.script
+                .insert(0, "PRAGMA foreign_keys = OFF".to_string());
+            diff.script.push("PRAGMA foreign_keys = ON".to_string());
+        }
+        Ok(diff)
+    }
+
+    /// Compare the rows of `table_name` with the rows of `target`'s `target_table` whose
+    /// primary key comes after `after`, along with the script reconciling the target table.
+    pub async fn diff_table_data(
+        &mut self,
+        table_name: &str,
+        target: &mut DatabaseConnection,
+        target_table: &str,
+        after: Option<&JsonMap<String, JsonValue>>,
+    ) -> Result<DataDiff> {
+        let source_info = self.table_info(table_name).await?;
+        let target_info = target.table_info(target_table).await?;
+        data_diff::diff_table_data(self, &source_info, target, &target_info, after).await
+    }
+
+    /// Get a value that changes whenever the database schema changes, used to tell
+    /// whether a cached schema is still up to date.
+    pub async fn schema_fingerprint(&self) -> Result<String> {
+        match self {
+            DatabaseConnection::Sqlite { pool, .. } => {
+                SQLiteHandler::schema_fingerprint(pool).await
+            }
+            DatabaseConnection::Postgres { pool, .. } => {
+                PostgresHandler::schema_fingerprint(pool).await
+            }
+            DatabaseConnection::Mysql { pool, .. } => MySQLHandler::schema_fingerprint(pool).await,
+        }
+    }
+
+    async fn discover_tables(&mut self, tables_names: &[String]) -> Result<()> {
+        let discovered = match self {
+            DatabaseConnection::Sqlite { pool, options, .. } => {
+                SQLiteHandler::discover_tables(pool, options, tables_names).await?
+            }
+            DatabaseConnection::Postgres { pool, .. } => {
+                PostgresHandler::discover_tables(pool, tables_names).await?
+            }
+            DatabaseConnection::Mysql { pool, .. } => {
+                MySQLHandler::discover_tables(pool, tables_names).await?
+            }
+        };
+
+        let schema = self.schema_mut();
+        for (table_name, discovery) in tables_names.iter().zip(discovered) {
+            if let TableDiscovery::Failed(reason) = &discovery {
+                log::warn!("Failed to discover table {table_name}: {reason}");
+            }
+            schema.record(table_name, discovery);
+        }
+        Ok(())
+    }
+
+    /// Replace the cached schema, e.g with one discovered through a clone of this connection.
+    pub fn set_schema(&mut self, schema: Schema) {
+        *self.schema_mut() = schema;
+    }
+
+    fn schema_mut(&mut self) -> &mut Schema {
+        match self {
+            DatabaseConnection::Sqlite { schema, .. } => schema,
+            DatabaseConnection::Postgres { schema, .. } => schema,
+            DatabaseConnection::Mysql { schema, .. } => schema,
+        }
+    }
+
+    /// Write a copy of a SQLite database to `path`, which must not exist.
+    ///
+    /// Only the copy is written, so it's allowed on read-only connections too.
+    pub async fn vacuum_into(&self, path: &str) -> Result<()> {
+        match self {
+            DatabaseConnection::Sqlite { pool, .. } => {
+                let mut conn = pool.acquire().await?;
+                let query_only: bool = sqlx::query_scalar("PRAGMA query_only")
+                    .fetch_one(&mut *conn)
+                    .await?;
+                if query_only {
+                    sqlx::query("PRAGMA query_only = OFF")
+                        .execute(&mut *conn)
+                        .await?;
+                }
+                let vacuumed = sqlx::query("VACUUM INTO ?")
+                    .bind(path)
+                    .execute(&mut *conn)
+                    .await;
+                if query_only
+                    && let Err(e) = sqlx::query("PRAGMA query_only = ON")
+                        .execute(&mut *conn)
+                        .await
+                {
+                    // It mustn't go back to the pool writable.
+                    conn.close_on_drop();
+                    return Err(e.into());
+                }
+                vacuumed?;
+                Ok(())
+            }
+            conn => Err(TxError::UnsupportedDriver(conn.driver().to_string())),
+        }
+    }
+
     pub async fn close(&self) {
         match self {
             DatabaseConnection::Sqlite { pool, .. } => pool.close().await,
-            DatabaseConnection::Postgres { pool, .. } => pool.close().await,
-            DatabaseConnection::Mysql { pool, .. } => pool.close().await,
+            DatabaseConnection::Postgres { pool, tunnel, .. } => {
+                pool.close().await;
+                if let Some(tunnel) = tunnel {
+                    tunnel.close().await;
+                }
+            }
+            DatabaseConnection::Mysql { pool, tunnel, .. } => {
+                pool.close().await;
+                if let Some(tunnel) = tunnel {
+                    tunnel.close().await;
+                }
+            }
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schema::ForeignKeyInfo;
+
+    #[tokio::test]
+    async fn tables_are_discovered_on_demand() {
+        let mut conn = DatabaseConnection::in_memory().await.unwrap();
+        conn.execute("CREATE TABLE a (id INTEGER PRIMARY KEY)")
+            .await
+            .unwrap();
+        conn.execute("CREATE TABLE b (id INTEGER PRIMARY KEY)")
+            .await
+            .unwrap();
+        conn.refresh_schema(None).await.unwrap();
+        assert_eq!(conn.get_schema().await.undiscovered(), vec!["a", "b"]);
+
+        assert_eq!(conn.table_info("a").await.unwrap().columns.len(), 1);
+        assert_eq!(conn.get_schema().await.undiscovered(), vec!["b"]);
+        assert!(matches!(
+            conn.table_info("missing").await,
+            Err(TxError::SchemaDiscovery(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn fingerprint_only_changes_with_the_schema() {
+        let conn = DatabaseConnection::in_memory().await.unwrap();
+        let empty = conn.schema_fingerprint().await.unwrap();
+        conn.execute("CREATE TABLE t (id INTEGER PRIMARY KEY)")
+            .await
+            .unwrap();
+        let created = conn.schema_fingerprint().await.unwrap();
+        assert_ne!(empty, created);
+
+        conn.execute("INSERT INTO t VALUES (1)").await.unwrap();
+        assert_eq!(created, conn.schema_fingerprint().await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn clones_share_the_pool_but_not_the_schema() {
+        let mut conn = DatabaseConnection::in_memory().await.unwrap();
+        let mut clone = conn.clone();
+        clone
+            .execute("CREATE TABLE t (id INTEGER PRIMARY KEY)")
+            .await
+            .unwrap();
+        clone.refresh_schema(None).await.unwrap();
+        assert!(conn.get_schema().await.tables_names.is_empty());
+
+        conn.set_schema(clone.get_schema().await);
+        assert_eq!(conn.get_schema().await.tables_names, vec!["t"]);
+    }
+
+    async fn count(conn: &DatabaseConnection, query: &str) -> i64 {
+        let row = DecodedRow::from(conn.fetch_one(query).await.unwrap());
+        row.0.values().next().unwrap().as_i64().unwrap()
+    }
+
+    #[tokio::test]
+    async fn rebuild_recreates_renamed_triggers() {
+        let mut conn = DatabaseConnection::in_memory().await.unwrap();
+        for stmt in [
+            "CREATE TABLE t (id INTEGER PRIMARY KEY, name TEXT, note TEXT)",
+            "CREATE TABLE log (name TEXT)",
+            "CREATE TRIGGER t_insert AFTER INSERT ON t BEGIN INSERT INTO log VALUES (NEW.name); END",
+            "CREATE TRIGGER t_note AFTER UPDATE ON t BEGIN SELECT NEW.note; END",
+        ] {
+            conn.execute(stmt).await.unwrap();
+        }
+        conn.refresh_schema(None).await.unwrap();
+
+        let mut definition = TableDefinition::from(&conn.table_info("t").await.unwrap());
+        definition.columns[1].previous_name = Some("name".to_string());
+        definition.columns[1].name = "title".to_string();
+        definition.columns.remove(2);
+        conn.rebuild_table("t", &definition).await.unwrap();
+
+        conn.execute("INSERT INTO t (title) VALUES ('x')")
+            .await
+            .unwrap();
+        assert_eq!(
+            count(&conn, "SELECT COUNT(*) FROM log WHERE name = 'x'").await,
+            1
+        );
+        assert_eq!(
+            count(
+                &conn,
+                "SELECT COUNT(*) FROM sqlite_master WHERE type = 'trigger'"
+            )
+            .await,
+            1
+        );
+    }
+
+    #[tokio::test]
+    async fn rebuild_fails_on_foreign_key_violations() {
+        let mut conn = DatabaseConnection::in_memory().await.unwrap();
+        for stmt in [
+            "CREATE TABLE parent (id INTEGER PRIMARY KEY)",
+            "CREATE TABLE child (id INTEGER PRIMARY KEY, parent_id INTEGER)",
+            "INSERT INTO child VALUES (1, 42)",
+        ] {
+            conn.execute(stmt).await.unwrap();
+        }
+        conn.refresh_schema(None).await.unwrap();
+
+        let mut definition = TableDefinition::from(&conn.table_info("child").await.unwrap());
+        definition.foreign_keys.push(ForeignKeyInfo {
+            name: None,
+            columns: vec!["parent_id".to_string()],
+            referenced_table: "parent".to_string(),
+            referenced_columns: vec!["id".to_string()],
+            on_update: Default::default(),
+            on_delete: Default::default(),
+        });
+        let result = conn.rebuild_table("child", &definition).await;
+        assert!(
+            matches!(result, Err(TxError::TableRebuild(_))),
+            "{result:?}"
+        );
+        assert_eq!(count(&conn, "SELECT COUNT(*) FROM child").await, 1);
+    }
+}
@@ -0,0 +1,878 @@
+//! Generation of the DDL statements creating, altering and dropping tables
+//! from structured table definitions.
+
+use crate::schema::{ColumnInfo, CustomColumnType, ForeignKeyInfo, IndexInfo, TableInfo};
+use sea_query::{
+    Alias, ColumnDef, ColumnType, DynIden, Expr, ForeignKey, ForeignKeyCreateStatement, Iden,
+    Index, IndexCreateStatement, MysqlQueryBuilder, PostgresQueryBuilder, Query,
+    QueryStatementWriter, SchemaBuilder, SeaRc, SqliteQueryBuilder, StringLen, Table,
+};
+use serde::{Deserialize, Serialize};
+use specta::Type;
+use sqlparser::{
+    dialect::SQLiteDialect,
+    keywords::Keyword,
+    tokenizer::{Token, Tokenizer},
+};
+use std::collections::{HashMap, HashSet};
+use tx_lib::{Result, TxError, types::Drivers};
+
+#[derive(Serialize, Deserialize, Type, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+/// Structured definition of a table, used to generate the DDL creating or altering it.
+pub struct TableDefinition {
+    pub name: String,
+    pub columns: Vec<ColumnDefinition>,
+    /// Names of the columns making up the primary key, in order.
+    pub primary_key: Vec<String>,
+    pub indexes: Vec<IndexInfo>,
+    pub foreign_keys: Vec<ForeignKeyInfo>,
+}
+
+#[derive(Serialize, Deserialize, Type, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ColumnDefinition {
+    pub name: String,
+    /// Name of the column in the current table when it's being renamed.
+    #[serde(default)]
+    pub previous_name: Option<String>,
+    pub r#type: CustomColumnType,
+    pub nullable: bool,
+    pub auto_increment: bool,
+    /// SQL expression used as is, e.g `'draft'` or `CURRENT_TIMESTAMP`.
+    pub default_value: Option<String>,
+    pub max_length: Option<u32>,
+    pub numeric_precision: Option<u32>,
+    pub numeric_scale: Option<u32>,
+}
+
+impl ColumnDefinition {
+    /// Whether both columns have the same type, constraints and default.
+    pub(crate) fn same_spec(&self, other: &ColumnDefinition) -> bool {
+        self.r#type == other.r#type
+            && self.nullable == other.nullable
+            && self.auto_increment == other.auto_increment
+            && self.default_value == other.default_value
+            && self.max_length == other.max_length
+            && self.numeric_precision == other.numeric_precision
+            && self.numeric_scale == other.numeric_scale
+    }
+}
+
+impl From<&ColumnInfo> for ColumnDefinition {
+    fn from(value: &ColumnInfo) -> Self {
+        let auto_increment = value.auto_generated && value.pk;
+        Self {
+            name: value.name.clone(),
+            previous_name: None,
+            r#type: value.r#type.clone(),
+            // SQLite reports its primary key columns as nullable.
+            nullable: value.nullable && !value.pk,
+            auto_increment,
+            // Auto increment defaults (e.g postgres' `nextval(...)`) are implied.
+            default_value: if auto_increment {
+                None
+            } else {
+                value.default_value.clone()
+            },
+            max_length: value.max_length,
+            numeric_precision: value.numeric_precision,
+            numeric_scale: value.numeric_scale,
+        }
+    }
+}
+
+impl From<&TableInfo> for TableDefinition {
+    fn from(value: &TableInfo) -> Self {
+        Self {
+            name: value.name.clone(),
+            columns: value.columns.iter().map(ColumnDefinition::from).collect(),
+            primary_key: value
+                .columns
+                .iter()
+                .filter(|c| c.pk)
+                .map(|c| c.name.clone())
+                .collect(),
+            indexes: value.indexes.clone(),
+            foreign_keys: value.foreign_keys.clone(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Type, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+/// A structural change to a table, previewed as SQL before being applied.
+pub enum SchemaChange {
+    Create(TableDefinition),
+    /// Alter `table_name` so it matches `definition`, which may rename it.
+    #[serde(rename_all = "camelCase")]
+    Alter {
+        table_name: String,
+        definition: TableDefinition,
+    },
+    #[serde(rename_all = "camelCase")]
+    Drop {
+        table_name: String,
+    },
+}
+
+pub(crate) fn schema_builder(driver: &Drivers) -> Box<dyn SchemaBuilder> {
+    match driver {
+        Drivers::SQLite => Box::new(SqliteQueryBuilder),
+        Drivers::PostgreSQL => Box::new(PostgresQueryBuilder),
+        Drivers::MySQL => Box::new(MysqlQueryBuilder),
+    }
+}
+
+/// Statements creating the table and its indexes.
+pub fn create_table(driver: &Drivers, definition: &TableDefinition) -> Result<Vec<String>> {
+    validate(driver, definition)?;
+    let builder = schema_builder(driver);
+
+    // Auto increment columns must be declared as the primary key inline.
+    let inline_pk = match definition.primary_key.as_slice() {
+        [pk] => definition
+            .columns
+            .iter()
+            .find(|c| &c.name == pk && c.auto_increment)
+            .map(|c| c.name.as_str()),
+        _ => None,
+    };
+
+    let mut table = Table::create();
+    table.table(Alias::new(&definition.name));
+    for column in &definition.columns {
+        let mut column_def = column_def(driver, column)?;
+        if inline_pk == Some(column.name.as_str()) {
+            column_def.primary_key();
+        }
+        table.col(&mut column_def);
+    }
+    if inline_pk.is_none() && !definition.primary_key.is_empty() {
+        let mut pk = Index::create();
+        for column in &definition.primary_key {
+            pk.col(Alias::new(column));
+        }
+        table.primary_key(&mut pk);
+    }
+    for fk in &definition.foreign_keys {
+        table.foreign_key(&mut foreign_key(&definition.name, fk));
+    }
+
+    let mut statements = vec![table.build_any(builder.as_ref())];
+    statements.extend(
+        definition
+            .indexes
+            .iter()
+            .map(|index| create_index(&definition.name, index).build_any(builder.as_ref())),
+    );
+    Ok(statements)
+}
+
+pub fn drop_table(driver: &Drivers, table_name: &str) -> String {
+    Table::drop()
+        .table(Alias::new(table_name))
+        .build_any(schema_builder(driver).as_ref())
+}
+
+/// Statements altering the `current` table so it matches `target`.
+///
+/// Columns are matched by name, or by `previous_name` when they're renamed.
+pub fn alter_table(
+    driver: &Drivers,
+    current: &TableDefinition,
+    target: &TableDefinition,
+) -> Result<Vec<String>> {
+    validate(driver, target)?;
+    let builder = schema_builder(driver);
+    let table = Alias::new(&target.name);
+    let mut statements = vec![];
+
+    if current.name != target.name {
+        statements.push(
+            Table::rename()
+                .table(Alias::new(&current.name), table.clone())
+                .build_any(builder.as_ref()),
+        );
+    }
+
+    for fk in &current.foreign_keys {
+        if target.foreign_keys.iter().any(|t| same_foreign_key(fk, t)) {
+            continue;
+        }
+        let Some(name) = fk
+            .name
+            .as_ref()
+            .filter(|_| !matches!(driver, Drivers::SQLite))
+        else {
+            return Err(unsupported(format!(
+                "dropping the foreign key on ({}) requires rebuilding the table",
+                fk.columns.join(", ")
+            )));
+        };
+        statements.push(
+            ForeignKey::drop()
+                .name(name)
+                .table(table.clone())
+                .build_any(builder.as_ref()),
+        );
+    }
+
+    for index in &current.indexes {
+        if target.indexes.contains(index) {
+            continue;
+        }
+        if index.name.starts_with("sqlite_autoindex_") {
+            return Err(unsupported(format!(
+                "dropping the unique constraint on ({}) requires rebuilding the table",
+                index.columns.join(", ")
+            )));
+        }
+        statements.push(
+            Index::drop()
+                .name(&index.name)
+                .table(table.clone())
+                .build_any(builder.as_ref()),
+        );
+    }
+
+    let kept: HashSet<&str> = target
+        .columns
+        .iter()
+        .map(|c| c.previous_name.as_deref().unwrap_or(&c.name))
+        .collect();
+    for column in &current.columns {
+        if !kept.contains(column.name.as_str()) {
+            statements.push(
+                Table::alter()
+                    .table(table.clone())
+                    .drop_column(Alias::new(&column.name))
+                    .build_any(builder.as_ref()),
+            );
+        }
+    }
+
+    for column in &target.columns {
+        let current_name = column.previous_name.as_deref().unwrap_or(&column.name);
+        let Some(current_column) = current.columns.iter().find(|c| c.name == current_name) else {
+            statements.push(
+                Table::alter()
+                    .table(table.clone())
+                    .add_column(&mut column_def(driver, column)?)
+                    .build_any(builder.as_ref()),
+            );
+            continue;
+        };
+
+        if current_name != column.name {
+            statements.push(
+                Table::alter()
+                    .table(table.clone())
+                    .rename_column(Alias::new(current_name), Alias::new(&column.name))
+                    .build_any(builder.as_ref()),
+            );
+        }
+        if !current_column.same_spec(column) {
+            statements.extend(modify_column(driver, &target.name, current_column, column)?);
+        }
+    }
+
+    let renamed_pk: Vec<&str> = current
+        .primary_key
+        .iter()
+        .map(|pk| {
+            target
+                .columns
+                .iter()
+                .find(|c| c.previous_name.as_ref() == Some(pk))
+                .map_or(pk.as_str(), |c| c.name.as_str())
+        })
+        .collect();
+    if renamed_pk != target.primary_key {
+        statements.extend(alter_primary_key(driver, current, target)?);
+    }
+
+    for fk in &target.foreign_keys {
+        if current.foreign_keys.iter().any(|c| same_foreign_key(c, fk)) {
+            continue;
+        }
+        if matches!(driver, Drivers::SQLite) {
+            return Err(unsupported(format!(
+                "adding a foreign key on ({}) requires rebuilding the table",
+                fk.columns.join(", ")
+            )));
+        }
+        statements.push(foreign_key(&target.name, fk).build_any(builder.as_ref()));
+    }
+
+    for index in &target.indexes {
+        if !current.indexes.contains(index) {
+            statements.push(create_index(&target.name, index).build_any(builder.as_ref()));
+        }
+    }
+
+    Ok(statements)
+}
+
+/// Statements rebuilding the SQLite `current` table so it matches `target`, for the
+/// changes `ALTER TABLE` can't perform: create the new table, copy the data over,
+/// replace the old table and recreate the indexes.
+///
+/// They must run in a transaction with foreign keys disabled, and the table's triggers
+/// recreated afterwards, see [`rebuild_trigger`]. `CHECK` constraints aren't part of
+/// table definitions, so they aren't carried over.
+pub fn rebuild_table(current: &TableDefinition, target: &TableDefinition) -> Result<Vec<String>> {
+    let new_table = TableDefinition {
+        name: format!("_rebuild_{}", target.name),
+        indexes: vec![],
+        ..target.clone()
+    };
+    let mut stmts = create_table(&Drivers::SQLite, &new_table)?;
+
+    let (columns, current_columns): (Vec<_>, Vec<_>) = target
+        .columns
+        .iter()
+        .filter_map(|c| {
+            let current_name = c.previous_name.as_deref().unwrap_or(&c.name);
+            current
+                .columns
+                .iter()
+                .any(|c| c.name == current_name)
+                .then(|| (Alias::new(&c.name), Alias::new(current_name)))
+        })
+        .unzip();
+    if !columns.is_empty() {
+        let copy = Query::insert()
+            .into_table(Alias::new(&new_table.name))
+            .columns(columns)
+            .select_from(
+                Query::select()
+                    .columns(current_columns)
+                    .from(Alias::new(&current.name))
+                    .to_owned(),
+            )
+            .map_err(|e| invalid(e.to_string()))?
+            .to_string(SqliteQueryBuilder);
+        stmts.push(copy);
+    }
+
+    stmts.push(drop_table(&Drivers::SQLite, &current.name));
+    stmts.push(
+        Table::rename()
+            .table(Alias::new(&new_table.name), Alias::new(&target.name))
+            .to_string(SqliteQueryBuilder),
+    );
+    stmts.extend(
+        target
+            .indexes
+            .iter()
+            .map(|index| create_index(&target.name, index).to_string(SqliteQueryBuilder)),
+    );
+    Ok(stmts)
+}
+
+/// Rewrite the `CREATE TRIGGER` statement of one of `current`'s triggers so it can be
+/// recreated on the rebuilt `target` table, with the table and columns renamed.
+///
+/// Returns `None` if the trigger refers to a dropped column, it can't be recreated then.
+/// Identifiers are matched by name only, so another table's column named like a renamed
+/// or dropped column is renamed, or drops the trigger, as well.
+pub(crate) fn rebuild_trigger(
+    sql: &str,
+    current: &TableDefinition,
+    target: &TableDefinition,
+) -> Result<Option<String>> {
+    let mut renames: HashMap<String, &str> = target
+        .columns
+        .iter()
+        .filter_map(|c| {
+            let previous_name = c.previous_name.as_ref()?;
+            (*previous_name != c.name).then(|| (previous_name.to_lowercase(), c.name.as_str()))
+        })
+        .collect();
+    if current.name != target.name {
+        renames.insert(current.name.to_lowercase(), &target.name);
+    }
+    let dropped: HashSet<String> = current
+        .columns
+        .iter()
+        .filter(|current| {
+            !target
+                .columns
+                .iter()
+                .any(|c| c.previous_name.as_deref().unwrap_or(&c.name) == current.name)
+        })
+        .map(|c| c.name.to_lowercase())
+        .collect();
+
+    let tokens = Tokenizer::new(&SQLiteDialect {}, sql)
+        .tokenize()
+        .map_err(|e| unsupported(format!("failed to parse trigger: {e}")))?;
+    let mut rebuilt = String::with_capacity(sql.len());
+    let mut qualified = false;
+    for token in tokens {
+        let Token::Word(word) = &token else {
+            if !matches!(token, Token::Whitespace(_)) {
+                qualified = token == Token::Period;
+            }
+            rebuilt.push_str(&token.to_string());
+            continue;
+        };
+        // e.g `AFTER UPDATE ON`, while `NEW.update` or `"update"` refer to a column.
+        let is_syntax =
+            word.quote_style.is_none() && !qualified && is_trigger_keyword(word.keyword);
+        qualified = false;
+        if is_syntax {
+            rebuilt.push_str(&token.to_string());
+            continue;
+        }
+        let name = word.value.to_lowercase();
+        if dropped.contains(&name) {
+            return Ok(None);
+        }
+        match renames.get(&name) {
+            Some(new_name) => rebuilt.push_str(&quoted(&SqliteQueryBuilder, new_name)),
+            None => rebuilt.push_str(&token.to_string()),
+        }
+    }
+    Ok(Some(rebuilt))
+}
+
+/// Keywords making up the syntax of triggers, SQLite accepts most other keywords as
+/// unquoted identifiers.
+fn is_trigger_keyword(keyword: Keyword) -> bool {
+    matches!(
+        keyword,
+        Keyword::CREATE
+            | Keyword::TEMP
+            | Keyword::TEMPORARY
+            | Keyword::TRIGGER
+            | Keyword::IF
+            | Keyword::NOT
+            | Keyword::EXISTS
+            | Keyword::BEFORE
+            | Keyword::AFTER
+            | Keyword::INSTEAD
+            | Keyword::OF
+            | Keyword::INSERT
+            | Keyword::UPDATE
+            | Keyword::DELETE
+            | Keyword::ON
+            | Keyword::FOR
+            | Keyword::EACH
+            | Keyword::ROW
+            | Keyword::WHEN
+            | Keyword::BEGIN
+            | Keyword::END
+            | Keyword::SELECT
+            | Keyword::FROM
+            | Keyword::WHERE
+            | Keyword::INTO
+            | Keyword::VALUES
+            | Keyword::SET
+            | Keyword::AND
+            | Keyword::OR
+            | Keyword::IS
+            | Keyword::IN
+            | Keyword::NULL
+            | Keyword::NEW
+            | Keyword::OLD
+    )
+}
+
+fn modify_column(
+    driver: &Drivers,
+    table_name: &str,
+    current: &ColumnDefinition,
+    target: &ColumnDefinition,
+) -> Result<Vec<String>> {
+    let builder = schema_builder(driver);
+    let table = Alias::new(table_name);
+    match driver {
+        Drivers::SQLite => Err(unsupported(format!(
+            "changing column `{}` requires rebuilding the table",
+            target.name
+        ))),
+        // MySQL redefines the whole column.
+        Drivers::MySQL => Ok(vec![
+            Table::alter()
+                .table(table)
+                .modify_column(&mut column_def(driver, target)?)
+                .build_any(builder.as_ref()),
+        ]),
+        Drivers::PostgreSQL => {
+            if target.auto_increment != current.auto_increment {
+                return Err(unsupported(format!(
+                    "changing auto increment of column `{}` isn't supported",
+                    target.name
+                )));
+            }
+            let type_changed = target.r#type != current.r#type
+                || target.max_length != current.max_length
+                || target.numeric_precision != current.numeric_precision
+                || target.numeric_scale != current.numeric_scale;
+            let mut column = if type_changed {
+                ColumnDef::new_with_type(Alias::new(&target.name), column_type(driver, target)?)
+            } else {
+                ColumnDef::new(Alias::new(&target.name))
+            };
+            if target.nullable != current.nullable {
+                if target.nullable {
+                    column.null();
+                } else {
+                    column.not_null();
+                }
+            }
+            if let Some(default) = target
+                .default_value
+                .as_ref()
+                .filter(|_| target.default_value != current.default_value)
+            {
+                column.default(Expr::cust(default));
+            }
+
+            let mut statements = vec![];
+            if type_changed || !column.get_column_spec().is_empty() {
+                statements.push(
+                    Table::alter()
+                        .table(table)
+                        .modify_column(&mut column)
+                        .build_any(builder.as_ref()),
+                );
+            }
+            // sea-query has no way to express dropping a default.
+            if target.default_value.is_none() && current.default_value.is_some() {
+                statements.push(format!(
+                    "ALTER TABLE {} ALTER COLUMN {} DROP DEFAULT",
+                    quoted(builder.as_ref(), table_name),
+                    quoted(builder.as_ref(), &target.name),
+                ));
+            }
+            Ok(statements)
+        }
+    }
+}
+
+fn alter_primary_key(
+    driver: &Drivers,
+    current: &TableDefinition,
+    target: &TableDefinition,
+) -> Result<Vec<String>> {
+    let builder = schema_builder(driver);
+    let table = quoted(builder.as_ref(), &target.name);
+    let mut statements = vec![];
+
+    if !current.primary_key.is_empty() {
+        match driver {
+            Drivers::MySQL => statements.push(format!("ALTER TABLE {table} DROP PRIMARY KEY")),
+            _ => {
+                return Err(unsupported(
+                    "changing an existing primary key requires rebuilding the table".to_string(),
+                ));
+            }
+        }
+    }
+    if !target.primary_key.is_empty() {
+        if matches!(driver, Drivers::SQLite) {
+            return Err(unsupported(
+                "adding a primary key requires rebuilding the table".to_string(),
+            ));
+        }
+        let columns = target
+            .primary_key
+            .iter()
+            .map(|c| quoted(builder.as_ref(), c))
+            .collect::<Vec<_>>()
+            .join(", ");
+        statements.push(format!("ALTER TABLE {table} ADD PRIMARY KEY ({columns})"));
+    }
+    Ok(statements)
+}
+
+fn validate(driver: &Drivers, definition: &TableDefinition) -> Result<()> {
+    if definition.name.trim().is_empty() {
+        return Err(invalid("the table name is empty".to_string()));
+    }
+    if definition.columns.is_empty() {
+        return Err(invalid(format!(
+            "table `{}` has no columns",
+            definition.name
+        )));
+    }
+
+    let mut names = HashSet::new();
+    for column in &definition.columns {
+        if column.name.trim().is_empty() {
+            return Err(invalid("a column name is empty".to_string()));
+        }
+        if !names.insert(column.name.as_str()) {
+            return Err(invalid(format!("column `{}` is duplicated", column.name)));
+        }
+        if column.auto_increment && definition.primary_key != [column.name.as_str()] {
+            return Err(invalid(format!(
+                "auto increment column `{}` must be the only primary key column",
+                column.name
+            )));
+        }
+    }
+
+    let check_columns = |columns: &[String], what: &str| {
+        if columns.is_empty() {
+            return Err(invalid(format!("{what} has no columns")));
+        }
+        match columns.iter().find(|c| !names.contains(c.as_str())) {
+            Some(column) => Err(invalid(format!("{what} uses unknown column `{column}`"))),
+            None => Ok(()),
+        }
+    };
+    if !definition.primary_key.is_empty() {
+        check_columns(&definition.primary_key, "the primary key")?;
+    }
+    for index in &definition.indexes {
+        if index.name.trim().is_empty() {
+            return Err(invalid("an index name is empty".to_string()));
+        }
+        check_columns(&index.columns, &format!("index `{}`", index.name))?;
+    }
+    for fk in &definition.foreign_keys {
+        check_columns(&fk.columns, "a foreign key")?;
+        if fk.columns.len() != fk.referenced_columns.len() {
+            return Err(invalid(format!(
+                "the foreign key on ({}) references {} columns",
+                fk.columns.join(", "),
+                fk.referenced_columns.len()
+            )));
+        }
+    }
+
+    for column in &definition.columns {
+        column_type(driver, column)?;
+    }
+    Ok(())
+}
+
+fn column_def(driver: &Drivers, column: &ColumnDefinition) -> Result<ColumnDef> {
+    let mut def = ColumnDef::new_with_type(Alias::new(&column.name), column_type(driver, column)?);
+    if column.nullable {
+        def.null();
+    } else {
+        def.not_null();
+    }
+    if column.auto_increment {
+        def.auto_increment();
+    }
+    if let Some(default) = &column.default_value {
+        // SQLite reports expressions without the parentheses it requires around them.
+        match driver {
+            Drivers::SQLite => def.default(Expr::cust(format!("({default})"))),
+            _ => def.default(Expr::cust(default)),
+        };
+    }
+    Ok(def)
+}
+
+fn column_type(driver: &Drivers, column: &ColumnDefinition) -> Result<ColumnType> {
+    let col_type = match &column.r#type {
+        CustomColumnType::String => {
+            ColumnType::String(column.max_length.map_or(StringLen::None, StringLen::N))
+        }
+        CustomColumnType::Text => ColumnType::Text,
+        CustomColumnType::Uuid => ColumnType::Uuid,
+        CustomColumnType::Float => match column.numeric_precision {
+            Some(precision) => {
+                ColumnType::Decimal(Some((precision, column.numeric_scale.unwrap_or(0))))
+            }
+            None => ColumnType::Double,
+        },
+        CustomColumnType::PositiveInteger => ColumnType::BigUnsigned,
+        // Only `integer` primary keys are aliases of SQLite's rowid.
+        CustomColumnType::Integer => match driver {
+            Drivers::SQLite => ColumnType::Integer,
+            _ => ColumnType::BigInteger,
+        },
+        CustomColumnType::Boolean => ColumnType::Boolean,
+        CustomColumnType::Date => ColumnType::Date,
+        CustomColumnType::DateTime => ColumnType::DateTime,
+        CustomColumnType::Time => ColumnType::Time,
+        CustomColumnType::Year => match driver {
+            Drivers::MySQL => ColumnType::Year,
+            _ => ColumnType::SmallInteger,
+        },
+        CustomColumnType::Json => ColumnType::Json,
+        CustomColumnType::Binary => ColumnType::Blob,
+        // Postgres enum types must already exist.
+        CustomColumnType::Enum(def) => match driver {
+            Drivers::SQLite => ColumnType::Text,
+            _ => ColumnType::Enum {
+                name: SeaRc::new(Alias::new(&def.name)),
+                variants: def
+                    .variants
+                    .iter()
+                    .map(|v| SeaRc::new(Alias::new(v)) as DynIden)
+                    .collect(),
+            },
+        },
+        CustomColumnType::Custom | CustomColumnType::UnSupported => {
+            return Err(invalid(format!(
+                "column `{}` has an unsupported type",
+                column.name
+            )));
+        }
+    };
+    if column.auto_increment
+        && !matches!(
+            col_type,
+            ColumnType::BigInteger | ColumnType::BigUnsigned | ColumnType::Integer
+        )
+    {
+        return Err(invalid(format!(
+            "auto increment column `{}` must be an integer",
+            column.name
+        )));
+    }
+    Ok(col_type)
+}
+
+fn foreign_key(table_name: &str, fk: &ForeignKeyInfo) -> ForeignKeyCreateStatement {
+    let mut stmt = ForeignKey::create();
+    if let Some(name) = &fk.name {
+        stmt.name(name);
+    }
+    stmt.from_tbl(Alias::new(table_name))
+        .to_tbl(Alias::new(&fk.referenced_table));
+    for column in &fk.columns {
+        stmt.from_col(Alias::new(column));
+    }
+    for column in &fk.referenced_columns {
+        stmt.to_col(Alias::new(column));
+    }
+    stmt.on_update(fk.on_update.into())
+        .on_delete(fk.on_delete.into())
+        .to_owned()
+}
+
+pub(crate) fn create_index(table_name: &str, index: &IndexInfo) -> IndexCreateStatement {
+    let mut stmt = Index::create();
+    // SQLite's names for the indexes of UNIQUE constraints are reserved.
+    if index.name.starts_with("sqlite_autoindex_") {
+        stmt.name(format!("{table_name}_{}_key", index.columns.join("_")));
+    } else {
+        stmt.name(&index.name);
+    }
+    stmt.table(Alias::new(table_name));
+    for column in &index.columns {
+        stmt.col(Alias::new(column));
+    }
+    if index.unique {
+        stmt.unique();
+    }
+    stmt
+}
+
+/// Foreign keys are the same if they have the same columns and actions, unnamed
+/// foreign keys match any name.
+fn same_foreign_key(current: &ForeignKeyInfo, target: &ForeignKeyInfo) -> bool {
+    current.columns == target.columns
+        && current.referenced_table == target.referenced_table
+        && current.referenced_columns == target.referenced_columns
+        && current.on_update == target.on_update
+        && current.on_delete == target.on_delete
+        && (target.name.is_none() || current.name == target.name)
+}
+
+/// Render `stmt` with its values inlined, e.g for scripts meant to be reviewed.
+pub(crate) fn inline(driver: &Drivers, stmt: &impl QueryStatementWriter) -> String {
+    match driver {
+        Drivers::SQLite => stmt.to_string(SqliteQueryBuilder),
+        Drivers::PostgreSQL => stmt.to_string(PostgresQueryBuilder),
+        Drivers::MySQL => stmt.to_string(MysqlQueryBuilder),
+    }
+}
+
+pub(crate) fn quoted(builder: &dyn SchemaBuilder, name: &str) -> String {
+    let mut quoted = String::new();
+    Alias::new(name).prepare(&mut quoted, builder.quote());
+    quoted
+}
+
+fn invalid(reason: String) -> TxError {
+    TxError::InvalidTableDefinition(reason)
+}
+
+fn unsupported(reason: String) -> TxError {
+    TxError::UnsupportedSchemaChange(reason)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn column(name: &str, previous_name: Option<&str>) -> ColumnDefinition {
+        ColumnDefinition {
+            name: name.to_string(),
+            previous_name: previous_name.map(str::to_string),
+            r#type: CustomColumnType::Text,
+            nullable: true,
+            auto_increment: false,
+            default_value: None,
+            max_length: None,
+            numeric_precision: None,
+            numeric_scale: None,
+        }
+    }
+
+    fn table(name: &str, columns: Vec<ColumnDefinition>) -> TableDefinition {
+        TableDefinition {
+            name: name.to_string(),
+            columns,
+            primary_key: vec![],
+            indexes: vec![],
+            foreign_keys: vec![],
+        }
+    }
+
+    #[test]
+    fn rebuilt_trigger_follows_renames() {
+        let current = table("posts", vec![column("title", None), column("body", None)]);
+        let target = table(
+            "articles",
+            vec![column("headline", Some("title")), column("body", None)],
+        );
+        let trigger = "CREATE TRIGGER posts_update AFTER UPDATE OF title ON posts \
+            BEGIN INSERT INTO log VALUES (NEW.title, NEW.body); END";
+
+        assert_eq!(
+            rebuild_trigger(trigger, &current, &target)
+                .unwrap()
+                .unwrap(),
+            "CREATE TRIGGER posts_update AFTER UPDATE OF \"headline\" ON \"articles\" \
+            BEGIN INSERT INTO log VALUES (NEW.\"headline\", NEW.body); END"
+        );
+    }
+
+    #[test]
+    fn trigger_using_dropped_column_isnt_rebuilt() {
+        let current = table("posts", vec![column("title", None), column("body", None)]);
+        let target = table("posts", vec![column("title", None)]);
+        let trigger = "CREATE TRIGGER t AFTER INSERT ON posts BEGIN SELECT NEW.body; END";
+
+        assert_eq!(rebuild_trigger(trigger, &current, &target).unwrap(), None);
+    }
+
+    #[test]
+    fn keywords_arent_mistaken_for_columns() {
+        let current = table("t", vec![column("update", None), column("end", None)]);
+        let target = table(
+            "t",
+            vec![column("update", None), column("stop", Some("end"))],
+        );
+        let trigger = "CREATE TRIGGER t_end AFTER UPDATE ON t BEGIN SELECT NEW.end; END";
+
+        assert_eq!(
+            rebuild_trigger(trigger, &current, &target)
+                .unwrap()
+                .unwrap(),
+            "CREATE TRIGGER t_end AFTER UPDATE ON t BEGIN SELECT NEW.\"stop\"; END"
+        );
+    }
+}
@@ -0,0 +1,325 @@
+//! Comparison of two schemas and the schema changes migrating one into the other.
+
+use crate::{
+    ddl::{ColumnDefinition, SchemaChange, TableDefinition},
+    schema::{ForeignKeyInfo, IndexInfo, Schema, TableInfo},
+};
+use serde::{Deserialize, Serialize};
+use specta::Type;
+use std::collections::HashSet;
+
+#[derive(Serialize, Deserialize, Type, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum DiffKind {
+    /// Only exists in the `to` schema.
+    Added,
+    /// Only exists in the `from` schema.
+    Removed,
+    Changed,
+}
+
+#[derive(Serialize, Deserialize, Type, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+/// A difference of a single item, `from` and `to` are its state in each schema.
+pub struct ItemDiff<T> {
+    pub kind: DiffKind,
+    pub from: Option<T>,
+    pub to: Option<T>,
+}
+
+#[derive(Serialize, Deserialize, Type, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+/// The differences of a table, added and removed tables list all of their items.
+pub struct TableDiff {
+    pub table_name: String,
+    pub kind: DiffKind,
+    pub columns: Vec<ItemDiff<ColumnDefinition>>,
+    /// Names of the primary key columns, if they differ.
+    pub primary_key: Option<ItemDiff<Vec<String>>>,
+    pub indexes: Vec<ItemDiff<IndexInfo>>,
+    pub foreign_keys: Vec<ItemDiff<ForeignKeyInfo>>,
+}
+
+#[derive(Serialize, Deserialize, Type, Clone, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct SchemaDiff {
+    /// Tables that differ between the two schemas.
+    pub tables: Vec<TableDiff>,
+    /// Tables that failed to be discovered in either schema, so they weren't compared.
+    pub skipped: Vec<String>,
+    /// Statements migrating the `from` database so its schema matches the `to` one.
+    pub script: Vec<String>,
+}
+
+/// Compare the discovered tables of both schemas, without generating the script.
+pub fn diff_schemas(from: &Schema, to: &Schema) -> SchemaDiff {
+    let skipped = skipped_tables(from, to);
+    let mut tables = vec![];
+
+    for table in from.tables() {
+        if skipped.contains(&table.name) {
+            continue;
+        }
+        let target = to.table(&table.name).map(TableDefinition::from);
+        if let Some(diff) = diff_table(Some(&TableDefinition::from(&table)), target.as_ref()) {
+            tables.push(diff);
+        }
+    }
+    for table in to.tables() {
+        if skipped.contains(&table.name) || from.table(&table.name).is_some() {
+            continue;
+        }
+        tables.extend(diff_table(None, Some(&TableDefinition::from(&table))));
+    }
+
+    let mut skipped: Vec<String> = skipped.into_iter().collect();
+    skipped.sort();
+    SchemaDiff {
+        tables,
+        skipped,
+        script: vec![],
+    }
+}
+
+/// Changes migrating `from` so it matches `to`: referenced tables are created before
+/// the tables referencing them, and dropped after them.
+pub fn migration_changes(from: &Schema, to: &Schema) -> Vec<SchemaChange> {
+    let skipped = skipped_tables(from, to);
+    let (from_tables, to_tables) = (from.tables(), to.tables());
+
+    let created = to_tables
+        .iter()
+        .filter(|t| !skipped.contains(&t.name) && from.table(&t.name).is_none())
+        .collect();
+    let mut changes: Vec<SchemaChange> = dependency_order(created)
+        .into_iter()
+        .map(|t| SchemaChange::Create(TableDefinition::from(t)))
+        .collect();
+
+    for table in &from_tables {
+        let Some(target) = to
+            .table(&table.name)
+            .filter(|_| !skipped.contains(&table.name))
+        else {
+            continue;
+        };
+        let (current, target) = (TableDefinition::from(table), TableDefinition::from(target));
+        if diff_table(Some(&current), Some(&target)).is_some() {
+            changes.push(SchemaChange::Alter {
+                table_name: table.name.clone(),
+                definition: target,
+            });
+        }
+    }
+
+    let dropped = from_tables
+        .iter()
+        .filter(|t| !skipped.contains(&t.name) && to.table(&t.name).is_none())
+        .collect();
+    changes.extend(
+        dependency_order(dropped)
+            .into_iter()
+            .rev()
+            .map(|t| SchemaChange::Drop {
+                table_name: t.name.clone(),
+            }),
+    );
+    changes
+}
+
+fn skipped_tables(from: &Schema, to: &Schema) -> HashSet<String> {
+    from.failures()
+        .into_iter()
+        .chain(to.failures())
+        .map(|f| f.table_name)
+        .collect()
+}
+
+fn diff_table(from: Option<&TableDefinition>, to: Option<&TableDefinition>) -> Option<TableDiff> {
+    let (table_name, kind) = match (from, to) {
+        (Some(from), Some(_)) => (from.name.clone(), DiffKind::Changed),
+        (Some(from), None) => (from.name.clone(), DiffKind::Removed),
+        (None, Some(to)) => (to.name.clone(), DiffKind::Added),
+        (None, None) => return None,
+    };
+    let columns = diff_items(
+        from.map_or(&[][..], |t| &t.columns),
+        to.map_or(&[][..], |t| &t.columns),
+        |a, b| a.name == b.name,
+        ColumnDefinition::same_spec,
+    );
+    let indexes = diff_items(
+        from.map_or(&[][..], |t| &t.indexes),
+        to.map_or(&[][..], |t| &t.indexes),
+        same_index,
+        |a, b| a.columns == b.columns && a.unique == b.unique,
+    );
+    let foreign_keys = diff_items(
+        from.map_or(&[][..], |t| &t.foreign_keys),
+        to.map_or(&[][..], |t| &t.foreign_keys),
+        same_constraint,
+        PartialEq::eq,
+    );
+
+    let from_pk = from.map(|t| t.primary_key.clone()).unwrap_or_default();
+    let to_pk = to.map(|t| t.primary_key.clone()).unwrap_or_default();
+    let primary_key = (from_pk != to_pk).then(|| ItemDiff {
+        kind: match (from_pk.is_empty(), to_pk.is_empty()) {
+            (true, _) => DiffKind::Added,
+            (_, true) => DiffKind::Removed,
+            _ => DiffKind::Changed,
+        },
+        from: Some(from_pk).filter(|pk| !pk.is_empty()),
+        to: Some(to_pk).filter(|pk| !pk.is_empty()),
+    });
+
+    if kind == DiffKind::Changed
+        && columns.is_empty()
+        && primary_key.is_none()
+        && indexes.is_empty()
+        && foreign_keys.is_empty()
+    {
+        return None;
+    }
+    Some(TableDiff {
+        table_name,
+        kind,
+        columns,
+        primary_key,
+        indexes,
+        foreign_keys,
+    })
+}
+
+/// Pair the items with `same_item`, and report the ones missing from either side
+/// or that aren't `equal`.
+fn diff_items<T: Clone>(
+    from: &[T],
+    to: &[T],
+    same_item: impl Fn(&T, &T) -> bool,
+    equal: impl Fn(&T, &T) -> bool,
+) -> Vec<ItemDiff<T>> {
+    let mut diffs = vec![];
+    for item in from {
+        match to.iter().find(|t| same_item(item, t)) {
+            Some(target) if equal(item, target) => {}
+            target => diffs.push(ItemDiff {
+                kind: if target.is_some() {
+                    DiffKind::Changed
+                } else {
+                    DiffKind::Removed
+                },
+                from: Some(item.clone()),
+                to: target.cloned(),
+            }),
+        }
+    }
+    for item in to {
+        if !from.iter().any(|f| same_item(f, item)) {
+            diffs.push(ItemDiff {
+                kind: DiffKind::Added,
+                from: None,
+                to: Some(item.clone()),
+            });
+        }
+    }
+    diffs
+}
+
+/// Indexes are paired by name, SQLite's names for the indexes of `UNIQUE` constraints
+/// depend on the order they were declared in, so those are paired by their columns.
+fn same_index(from: &IndexInfo, to: &IndexInfo) -> bool {
+    let generated = |index: &IndexInfo| index.name.starts_with("sqlite_autoindex_");
+    from.name == to.name || ((generated(from) || generated(to)) && from.columns == to.columns)
+}
+
+/// Foreign keys are paired by name, unnamed ones (e.g SQLite's) by their columns.
+fn same_constraint(from: &ForeignKeyInfo, to: &ForeignKeyInfo) -> bool {
+    match (&from.name, &to.name) {
+        (Some(from), Some(to)) => from == to,
+        _ => from.columns == to.columns,
+    }
+}
+
+/// Order `tables` so the tables they reference come first, tables in a reference
+/// cycle are kept in their original order.
+pub(crate) fn dependency_order(mut tables: Vec<&TableInfo>) -> Vec<&TableInfo> {
+    let mut ordered = Vec::with_capacity(tables.len());
+    while !tables.is_empty() {
+        let pending: HashSet<String> = tables.iter().map(|t| t.name.clone()).collect();
+        let (ready, blocked): (Vec<_>, Vec<_>) = tables.into_iter().partition(|t| {
+            t.foreign_keys
+                .iter()
+                .all(|fk| fk.referenced_table == t.name || !pending.contains(&fk.referenced_table))
+        });
+        if ready.is_empty() {
+            ordered.extend(blocked);
+            break;
+        }
+        ordered.extend(ready);
+        tables = blocked;
+    }
+    ordered
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::DatabaseConnection;
+
+    async fn database(stmts: &[&str]) -> DatabaseConnection {
+        let mut conn = DatabaseConnection::in_memory().await.unwrap();
+        for stmt in stmts {
+            conn.execute(stmt).await.unwrap();
+        }
+        conn.refresh_schema(None).await.unwrap();
+        conn
+    }
+
+    #[tokio::test]
+    async fn script_migrates_the_from_schema() {
+        let mut from = database(&[
+            "CREATE TABLE t (id INTEGER PRIMARY KEY, a TEXT)",
+            "CREATE TABLE old (id INTEGER PRIMARY KEY)",
+        ])
+        .await;
+        let mut to = database(&[
+            "CREATE TABLE t (id INTEGER PRIMARY KEY, a TEXT, b INTEGER)",
+            "CREATE TABLE parent (id INTEGER PRIMARY KEY)",
+            "CREATE TABLE child (id INTEGER PRIMARY KEY, parent_id INTEGER REFERENCES parent (id))",
+        ])
+        .await;
+
+        let diff = from.diff_schema(&mut to).await.unwrap();
+        let mut tables: Vec<_> = diff
+            .tables
+            .iter()
+            .map(|t| (t.table_name.as_str(), t.kind))
+            .collect();
+        tables.sort_by_key(|(name, _)| *name);
+        assert_eq!(
+            tables,
+            [
+                ("child", DiffKind::Added),
+                ("old", DiffKind::Removed),
+                ("parent", DiffKind::Added),
+                ("t", DiffKind::Changed),
+            ]
+        );
+        let position = |table: &str| {
+            diff.script
+                .iter()
+                .position(|stmt| stmt.starts_with(&format!("CREATE TABLE \"{table}\"")))
+                .unwrap()
+        };
+        assert!(position("parent") < position("child"));
+
+        for stmt in &diff.script {
+            from.execute(stmt).await.unwrap();
+        }
+        from.refresh_schema(None).await.unwrap();
+        let diff = from.diff_schema(&mut to).await.unwrap();
+        assert!(diff.tables.is_empty(), "{:?}", diff.tables);
+        assert!(diff.script.is_empty());
+    }
+}
@@ -0,0 +1,671 @@
+//! SQL scripts recreating a whole database, and restoring them.
+
+use crate::{
+    DatabaseConnection, PostgresHandler,
+    data_diff::literal,
+    ddl::{create_index, inline, quoted, schema_builder},
+    diff::dependency_order,
+    postgres::SequenceInfo,
+    query::RowValue,
+    schema::{CustomColumnType, TableDiscoveryFailure, TableInfo},
+};
+use futures_util::TryStreamExt;
+use sea_query::{
+    Alias, Asterisk, InsertStatement, Query, SimpleExpr, Table, Value, extension::postgres::Type,
+};
+use sea_query_binder::SqlxValues;
+use serde::{Deserialize, Serialize};
+use specta::Type as SpectaType;
+use std::{
+    fs::File,
+    io::{BufRead, BufReader, BufWriter, Write},
+    ops::Range,
+};
+use tx_lib::{Result, TxError, types::Drivers};
+
+/// Number of rows inserted by each `INSERT` statement of a dump.
+const ROWS_PER_INSERT: usize = 100;
+
+/// Number of rows dumped between two progress reports.
+const DUMP_PROGRESS_INTERVAL: u64 = 10_000;
+
+/// Number of statements executed between two progress reports.
+const RESTORE_PROGRESS_INTERVAL: u64 = 100;
+
+/// Longest part of a failed statement reported with its error.
+const MAX_STATEMENT_LENGTH: usize = 200;
+
+#[derive(Serialize, Deserialize, SpectaType, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct DumpOptions {
+    /// File the script is written to, it's overwritten if it exists.
+    pub path: String,
+    /// Drop the dumped tables, and Postgres' types and sequences, before creating them so
+    /// the script can restore a database that still has them.
+    pub drop_existing: bool,
+    /// Dump the tables' rows along with their schema.
+    pub data: bool,
+}
+
+#[derive(Serialize, Deserialize, SpectaType, Clone, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct DumpSummary {
+    pub tables: Vec<String>,
+    pub rows_dumped: u64,
+    /// Tables whose details couldn't be discovered, they're left out of the dump.
+    pub skipped_tables: Vec<TableDiscoveryFailure>,
+}
+
+#[derive(Serialize, Deserialize, SpectaType, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct RestoreOptions {
+    pub path: String,
+    /// Execute the script in a single transaction, rolled back on the first failing
+    /// statement. Otherwise failing statements are reported and the next ones executed.
+    ///
+    /// MySQL commits schema changes immediately, even in a transaction.
+    pub atomic: bool,
+}
+
+#[derive(Serialize, Deserialize, SpectaType, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct StatementError {
+    /// Line of the script the statement starts at.
+    pub line: u64,
+    /// Start of the statement.
+    pub statement: String,
+    pub message: String,
+}
+
+#[derive(Serialize, Deserialize, SpectaType, Clone, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct RestoreSummary {
+    pub statements_executed: u64,
+    pub errors: Vec<StatementError>,
+}
+
+/// Write a script recreating the database's tables, with their indexes and rows, in the
+/// connection's dialect.
+///
+/// Postgres' enum types and sequences are recreated too, sequences resuming from their
+/// current value. `on_progress` is called periodically with the number of rows dumped so
+/// far, and once all of them are.
+pub async fn dump_database(
+    conn: &mut DatabaseConnection,
+    options: &DumpOptions,
+    mut on_progress: impl FnMut(u64),
+) -> Result<DumpSummary> {
+    conn.discover_remaining_tables().await?;
+    let schema = conn.get_schema().await;
+    let tables = schema.tables();
+    let tables = dependency_order(tables.iter().collect());
+    let driver = conn.driver();
+    let builder = schema_builder(&driver);
+    let sequences = match &*conn {
+        DatabaseConnection::Postgres { pool, .. } => PostgresHandler::sequences(pool).await?,
+        _ => vec![],
+    };
+    let mut enums = vec![];
+    if driver == Drivers::PostgreSQL {
+        for column in tables.iter().flat_map(|t| &t.columns) {
+            if let CustomColumnType::Enum(def) = &column.r#type
+                && !enums.contains(def)
+            {
+                enums.push(def.clone());
+            }
+        }
+    }
+    let mut summary = DumpSummary {
+        tables: tables.iter().map(|t| t.name.clone()).collect(),
+        skipped_tables: schema.failures(),
+        ..Default::default()
+    };
+
+    // Written next to the destination first, so a failed dump doesn't leave a partial
+    // script behind, or overwrite a previous one.
+    let temp_path = format!("{}.part", options.path);
+    let written = async {
+        let mut out = BufWriter::new(File::create(&temp_path)?);
+        writeln!(out, "-- {driver} dump of {} tables", tables.len())?;
+        for failure in &summary.skipped_tables {
+            writeln!(
+                out,
+                "-- Skipped table {}: {}",
+                failure.table_name,
+                failure.reason.replace('\n', " ")
+            )?;
+        }
+
+        if options.drop_existing {
+            writeln!(out)?;
+            for table in tables.iter().rev() {
+                let stmt = Table::drop()
+                    .table(Alias::new(&table.name))
+                    .if_exists()
+                    .cascade()
+                    .build_any(builder.as_ref());
+                writeln!(out, "{stmt};")?;
+            }
+            for sequence in sequences.iter().filter(|s| !s.owned) {
+                let name = quoted(builder.as_ref(), &sequence.name);
+                writeln!(out, "DROP SEQUENCE IF EXISTS {name};")?;
+            }
+            for def in &enums {
+                let stmt = Type::drop()
+                    .name(Alias::new(&def.name))
+                    .if_exists()
+                    .to_string(sea_query::PostgresQueryBuilder);
+                writeln!(out, "{stmt};")?;
+            }
+        }
+
+        writeln!(out)?;
+        for def in &enums {
+            let stmt = Type::create()
+                .as_enum(Alias::new(&def.name))
+                .values(def.variants.iter().map(Alias::new))
+                .to_string(sea_query::PostgresQueryBuilder);
+            writeln!(out, "{stmt};")?;
+        }
+        for sequence in sequences.iter().filter(|s| !s.owned) {
+            writeln!(out, "{};", create_sequence(builder.as_ref(), sequence))?;
+        }
+        for table in &tables {
+            writeln!(out, "{};", table.create_statement)?;
+        }
+
+        if options.data {
+            for table in &tables {
+                dump_rows(conn, table, &mut out, &mut summary, &mut on_progress).await?;
+            }
+        }
+
+        // Indexes are created once the rows are inserted, it's faster than updating them
+        // for each row.
+        writeln!(out)?;
+        for table in &tables {
+            for index in &table.indexes {
+                // Those created along with the table, e.g by `UNIQUE` constraints.
+                if index.name.starts_with("sqlite_autoindex_")
+                    || table
+                        .create_statement
+                        .contains(&quoted(builder.as_ref(), &index.name))
+                {
+                    continue;
+                }
+                writeln!(
+                    out,
+                    "{};",
+                    create_index(&table.name, index).build_any(builder.as_ref())
+                )?;
+            }
+        }
+        for sequence in &sequences {
+            if let Some(last_value) = sequence.last_value {
+                let name = quoted(builder.as_ref(), &sequence.name);
+                writeln!(out, "SELECT setval('{name}', {last_value}, true);")?;
+            }
+        }
+        out.flush()?;
+        Ok::<_, TxError>(())
+    }
+    .await
+    .and_then(|_| Ok(std::fs::rename(&temp_path, &options.path)?));
+    if let Err(e) = written {
+        let _ = std::fs::remove_file(&temp_path);
+        return Err(e);
+    }
+    on_progress(summary.rows_dumped);
+
+    Ok(summary)
+}
+
+async fn dump_rows(
+    conn: &DatabaseConnection,
+    table: &TableInfo,
+    out: &mut impl Write,
+    summary: &mut DumpSummary,
+    on_progress: &mut impl FnMut(u64),
+) -> Result<()> {
+    let driver = conn.driver();
+    let select = inline(
+        &driver,
+        &Query::select()
+            .column(Asterisk)
+            .from(Alias::new(&table.name))
+            .to_owned(),
+    );
+    let mut insert: Option<InsertStatement> = None;
+    let mut batched = 0;
+
+    writeln!(out)?;
+    let mut rows = conn.fetch_raw_stream(&select, SqlxValues(sea_query::Values(vec![])));
+    while let Some(row) = rows.try_next().await? {
+        let mut row = row.values()?;
+        insert
+            .get_or_insert_with(|| {
+                Query::insert()
+                    .into_table(Alias::new(&table.name))
+                    .columns(table.columns.iter().map(|c| Alias::new(&c.name)))
+                    .to_owned()
+            })
+            .values_panic(table.columns.iter().map(|c| {
+                SimpleExpr::Value(match row.remove(&c.name).unwrap_or_default() {
+                    RowValue::Decoded(value) => literal(&value),
+                    // Written as hex, e.g `x'00FF'` or Postgres' `'\x00FF'`.
+                    RowValue::Bytes(bytes) => Value::Bytes(Some(Box::new(bytes))),
+                })
+            }));
+        batched += 1;
+        summary.rows_dumped += 1;
+
+        if batched == ROWS_PER_INSERT
+            && let Some(stmt) = insert.take()
+        {
+            writeln!(out, "{};", inline(&driver, &stmt))?;
+            batched = 0;
+        }
+        if summary.rows_dumped.is_multiple_of(DUMP_PROGRESS_INTERVAL) {
+            on_progress(summary.rows_dumped);
+        }
+    }
+    if let Some(stmt) = insert {
+        writeln!(out, "{};", inline(&driver, &stmt))?;
+    }
+    Ok(())
+}
+
+fn create_sequence(builder: &dyn sea_query::SchemaBuilder, sequence: &SequenceInfo) -> String {
+    format!(
+        "CREATE SEQUENCE {} INCREMENT BY {} MINVALUE {} MAXVALUE {} START WITH {}{}",
+        quoted(builder, &sequence.name),
+        sequence.increment_by,
+        sequence.min_value,
+        sequence.max_value,
+        sequence.start_value,
+        if sequence.cycle { " CYCLE" } else { "" }
+    )
+}
+
+/// Execute the statements of a SQL script, e.g one written by [`dump_database`]. The
+/// script is read one statement at a time, so it doesn't have to fit in memory.
+///
+/// `on_progress` is called periodically with the number of statements executed so far,
+/// the number of bytes of the script read so far and the size of the script.
+pub async fn restore_database(
+    conn: &mut DatabaseConnection,
+    options: &RestoreOptions,
+    mut on_progress: impl FnMut(u64, u64, u64),
+) -> Result<RestoreSummary> {
+    let file = File::open(&options.path)?;
+    let total_bytes = file.metadata()?.len();
+    let mut statements = Statements::new(BufReader::new(file), conn.driver());
+    let mut summary = RestoreSummary::default();
+
+    let mut tx = match options.atomic {
+        true => Some(conn.begin().await?),
+        false => None,
+    };
+    while let Some((line, statement)) = statements.next_statement()? {
+        let result = match &mut tx {
+            Some(tx) => {
+                tx.execute_with(&statement, SqlxValues(sea_query::Values(vec![])))
+                    .await
+            }
+            None => conn.execute(&statement).await,
+        };
+        match result {
+            Ok(_) => summary.statements_executed += 1,
+            Err(e) if tx.is_some() => {
+                return Err(TxError::Restore(format!("line {line}: {e}")));
+            }
+            Err(e) => summary.errors.push(StatementError {
+                line,
+                statement: statement.chars().take(MAX_STATEMENT_LENGTH).collect(),
+                message: e.to_string(),
+            }),
+        }
+        let done = summary.statements_executed + summary.errors.len() as u64;
+        if done.is_multiple_of(RESTORE_PROGRESS_INTERVAL) {
+            on_progress(done, statements.bytes_read, total_bytes);
+        }
+    }
+    if let Some(tx) = tx {
+        tx.commit().await?;
+    }
+    let done = summary.statements_executed + summary.errors.len() as u64;
+    on_progress(done, total_bytes, total_bytes);
+
+    conn.refresh_schema(None).await?;
+    Ok(summary)
+}
+
+/// Statements of a script read one at a time, along with the line they start at.
+struct Statements<R> {
+    reader: R,
+    driver: Drivers,
+    /// Part of the script read but not split into statements yet.
+    buffer: String,
+    /// Line of the buffer's first character.
+    line: u64,
+    bytes_read: u64,
+    eof: bool,
+}
+
+impl<R: BufRead> Statements<R> {
+    fn new(reader: R, driver: Drivers) -> Self {
+        Self {
+            reader,
+            driver,
+            buffer: String::new(),
+            line: 1,
+            bytes_read: 0,
+            eof: false,
+        }
+    }
+
+    fn next_statement(&mut self) -> Result<Option<(u64, String)>> {
+        loop {
+            if let Some((line, range, consumed)) =
+                next_statement(&self.buffer, &self.driver, self.eof)
+            {
+                let statement = (self.line + line, self.buffer[range].to_string());
+                self.line += count_lines(&self.buffer.as_bytes()[..consumed]);
+                self.buffer.drain(..consumed);
+                return Ok(Some(statement));
+            }
+            if self.eof {
+                return Ok(None);
+            }
+            // Lines are read until one may end a statement, so the buffer is split again
+            // only when it could hold a new one.
+            loop {
+                let read = self.reader.read_line(&mut self.buffer)?;
+                self.bytes_read += read as u64;
+                if read == 0 {
+                    self.eof = true;
+                    break;
+                }
+                if self.buffer[self.buffer.len() - read..].contains(';') {
+                    break;
+                }
+            }
+        }
+    }
+}
+
+/// First statement of a script, they're separated by `;` outside of strings, quoted
+/// identifiers and comments. Returns the line the statement starts at, counted from 0,
+/// its range and the length of the script it spans up to its `;`.
+///
+/// `None` if no statement is complete yet, unless the script is `complete` in which case
+/// its last statement doesn't need a `;`.
+fn next_statement(
+    script: &str,
+    driver: &Drivers,
+    complete: bool,
+) -> Option<(u64, Range<usize>, usize)> {
+    let bytes = script.as_bytes();
+    let mut line = 0;
+    // Offset and line of the current statement's first character.
+    let mut start: Option<(usize, u64)> = None;
+    let mut i = 0;
+
+    while i < bytes.len() {
+        let byte = bytes[i];
+        let next = bytes.get(i + 1).copied();
+        match byte {
+            b'\n' => line += 1,
+            b'-' if next == Some(b'-') => {
+                i = skip_line(bytes, i);
+                continue;
+            }
+            b'#' if *driver == Drivers::MySQL => {
+                i = skip_line(bytes, i);
+                continue;
+            }
+            b'/' if next == Some(b'*') => {
+                let end = script[i + 2..]
+                    .find("*/")
+                    .map_or(bytes.len(), |end| i + 2 + end + 2);
+                line += count_lines(&bytes[i..end]);
+                i = end;
+                continue;
+            }
+            b';' => {
+                if let Some((offset, first_line)) = start {
+                    let end = offset + script[offset..i].trim_end().len();
+                    return Some((first_line, offset..end, i + 1));
+                }
+            }
+            byte if byte.is_ascii_whitespace() => {}
+            byte => {
+                start.get_or_insert((i, line));
+                let end = match byte {
+                    b'\'' | b'"' | b'`' => Some(skip_quoted(bytes, i, escapes(bytes, i, driver))),
+                    b'$' if *driver == Drivers::PostgreSQL => skip_dollar_quoted(script, i),
+                    _ => None,
+                };
+                if let Some(end) = end {
+                    line += count_lines(&bytes[i..end]);
+                    i = end;
+                    continue;
+                }
+            }
+        }
+        i += 1;
+    }
+    let (offset, first_line) = start.filter(|_| complete)?;
+    let end = offset + script[offset..].trim_end().len();
+    Some((first_line, offset..end, script.len()))
+}
+
+/// Offset of the line break ending the comment starting at `start`.
+fn skip_line(bytes: &[u8], start: usize) -> usize {
+    bytes[start..]
+        .iter()
+        .position(|b| *b == b'\n')
+        .map_or(bytes.len(), |end| start + end)
+}
+
+/// Whether backslashes escape characters in the string starting at `start`, as they do
+/// in MySQL strings and Postgres' `E'...'` strings.
+fn escapes(bytes: &[u8], start: usize, driver: &Drivers) -> bool {
+    match driver {
+        Drivers::MySQL => true,
+        Drivers::PostgreSQL => {
+            bytes[start] == b'\''
+                && start > 0
+                && bytes[start - 1].eq_ignore_ascii_case(&b'e')
+                && (start < 2 || !is_identifier(bytes[start - 2]))
+        }
+        Drivers::SQLite => false,
+    }
+}
+
+/// Offset after the closing quote of the string or identifier starting at `start`,
+/// quotes are escaped by doubling them.
+fn skip_quoted(bytes: &[u8], start: usize, escapes: bool) -> usize {
+    let quote = bytes[start];
+    let mut i = start + 1;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'\\' if escapes => i += 1,
+            byte if byte == quote => {
+                if bytes.get(i + 1) != Some(&quote) {
+                    return i + 1;
+                }
+                i += 1;
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+    bytes.len()
+}
+
+/// Offset after the end of the `$tag$...$tag$` string starting at `start`, `None` if
+/// there's no such string, e.g for `$1` parameters.
+fn skip_dollar_quoted(script: &str, start: usize) -> Option<usize> {
+    let tag_length = script[start + 1..]
+        .bytes()
+        .position(|b| !is_identifier(b))
+        .filter(|length| script.as_bytes().get(start + 1 + length) == Some(&b'$'))?;
+    let tag = &script[start..start + tag_length + 2];
+    if tag[1..].starts_with(|c: char| c.is_ascii_digit()) {
+        return None;
+    }
+    let body = start + tag.len();
+    Some(
+        script[body..]
+            .find(tag)
+            .map_or(script.len(), |end| body + end + tag.len()),
+    )
+}
+
+fn is_identifier(byte: u8) -> bool {
+    byte.is_ascii_alphanumeric() || byte == b'_'
+}
+
+fn count_lines(bytes: &[u8]) -> u64 {
+    bytes.iter().filter(|b| **b == b'\n').count() as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::DecodedRow;
+
+    fn statements(script: &str, driver: Drivers) -> Vec<(u64, String)> {
+        let mut statements = Statements::new(script.as_bytes(), driver);
+        std::iter::from_fn(|| statements.next_statement().unwrap()).collect()
+    }
+
+    #[test]
+    fn statements_are_split_outside_of_strings_and_comments() {
+        let script = "-- a; comment\nINSERT INTO t VALUES ('a;\nb');;\n/* c;\n */ SELECT \"d;\"\n;\nSELECT 1";
+        assert_eq!(
+            statements(script, Drivers::SQLite),
+            vec![
+                (2, "INSERT INTO t VALUES ('a;\nb')".to_string()),
+                (5, "SELECT \"d;\"".to_string()),
+                (7, "SELECT 1".to_string()),
+            ]
+        );
+
+        let script = "CREATE FUNCTION f() RETURNS int AS $body$\nSELECT 1;\n$body$ LANGUAGE sql;\nSELECT E'\\';'";
+        assert_eq!(
+            statements(script, Drivers::PostgreSQL),
+            vec![
+                (
+                    1,
+                    "CREATE FUNCTION f() RETURNS int AS $body$\nSELECT 1;\n$body$ LANGUAGE sql"
+                        .to_string()
+                ),
+                (4, "SELECT E'\\';'".to_string()),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn dumped_databases_are_restored() {
+        let path = std::env::temp_dir()
+            .join(format!("tx-dump-{}.sql", std::process::id()))
+            .to_string_lossy()
+            .into_owned();
+        let mut source = DatabaseConnection::in_memory().await.unwrap();
+        source
+            .execute("CREATE TABLE t (id INTEGER PRIMARY KEY, name TEXT)")
+            .await
+            .unwrap();
+        source
+            .execute("INSERT INTO t VALUES (1, 'a;b'), (2, 'it''s')")
+            .await
+            .unwrap();
+        source.refresh_schema(None).await.unwrap();
+        let options = DumpOptions {
+            path: path.clone(),
+            drop_existing: false,
+            data: true,
+        };
+        dump_database(&mut source, &options, |_| {}).await.unwrap();
+        assert!(!std::path::Path::new(&format!("{path}.part")).exists());
+
+        let mut target = DatabaseConnection::in_memory().await.unwrap();
+        let options = RestoreOptions {
+            path: path.clone(),
+            atomic: true,
+        };
+        let mut progress = vec![];
+        let summary = restore_database(&mut target, &options, |done, read, total| {
+            progress.push((done, read, total))
+        })
+        .await
+        .unwrap();
+        let size = std::fs::metadata(&path).unwrap().len();
+        std::fs::remove_file(&path).unwrap();
+
+        assert!(summary.errors.is_empty());
+        assert_eq!(
+            progress.last(),
+            Some(&(summary.statements_executed, size, size))
+        );
+        let names: Vec<_> = target
+            .fetch_all(
+                "SELECT name FROM t ORDER BY id",
+                SqlxValues(sea_query::Values(vec![])),
+            )
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|row| DecodedRow::from(row).0.remove("name").unwrap())
+            .collect();
+        assert_eq!(names, vec!["a;b", "it's"]);
+    }
+
+    #[tokio::test]
+    async fn binary_values_are_dumped_as_hex() {
+        let path = std::env::temp_dir()
+            .join(format!("tx-dump-blob-{}.sql", std::process::id()))
+            .to_string_lossy()
+            .into_owned();
+        let mut source = DatabaseConnection::in_memory().await.unwrap();
+        source
+            .execute("CREATE TABLE t (id INTEGER PRIMARY KEY, data BLOB)")
+            .await
+            .unwrap();
+        source
+            .execute("INSERT INTO t VALUES (1, X'00FF273B'), (2, NULL)")
+            .await
+            .unwrap();
+        source.refresh_schema(None).await.unwrap();
+        let options = DumpOptions {
+            path: path.clone(),
+            drop_existing: false,
+            data: true,
+        };
+        dump_database(&mut source, &options, |_| {}).await.unwrap();
+
+        let mut target = DatabaseConnection::in_memory().await.unwrap();
+        let options = RestoreOptions {
+            path: path.clone(),
+            atomic: true,
+        };
+        restore_database(&mut target, &options, |_, _, _| {})
+            .await
+            .unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let data: Vec<_> = target
+            .fetch_all(
+                "SELECT quote(data) AS data FROM t ORDER BY id",
+                SqlxValues(sea_query::Values(vec![])),
+            )
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|row| DecodedRow::from(row).0.remove("data").unwrap())
+            .collect();
+        assert_eq!(data, vec!["X'00FF273B'", "NULL"]);
+    }
+}
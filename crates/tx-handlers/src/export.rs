@@ -0,0 +1,742 @@
+//! Writing the rows of a query to a file, streamed so exports don't have to fit in memory.
+
+use crate::{
+    DatabaseConnection,
+    data_diff::literal,
+    ddl::inline,
+    parquet_writer::ParquetWriter,
+    query::DecodedRow,
+    schema::{ColumnInfo, CustomColumnType, TableInfo, parse_date, parse_datetime, parse_time},
+    xlsx_writer::XlsxWriter,
+};
+use chrono::{NaiveDate, NaiveDateTime, NaiveTime};
+use futures_util::TryStreamExt;
+use rust_decimal::Decimal;
+use sea_query::{Alias, Query, SimpleExpr};
+use sea_query_binder::SqlxValues;
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use specta::Type;
+use std::{
+    borrow::Cow,
+    fs::File,
+    io::{BufWriter, Write},
+};
+use tx_lib::{Result, TxError, types::Drivers};
+
+/// Number of rows written between two progress reports.
+const PROGRESS_INTERVAL: u64 = 10_000;
+
+/// Number of rows read ahead of the file writes.
+const PENDING_ROWS: usize = 1_000;
+
+/// Most digits of decimals that aren't written as text, the rest don't fit a [`Decimal`].
+const MAX_DECIMAL_PRECISION: u32 = 28;
+
+#[derive(Serialize, Deserialize, Type, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum CsvQuoting {
+    /// Only quote fields containing the delimiter, quotes or line breaks.
+    #[default]
+    Necessary,
+    Always,
+    /// Quote every field that isn't a number.
+    NonNumeric,
+    /// Never quote fields, even if it makes the file ambiguous.
+    Never,
+}
+
+#[derive(Serialize, Deserialize, Type, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct CsvOptions {
+    /// Must be an ASCII character.
+    pub delimiter: char,
+    pub quoting: CsvQuoting,
+    /// Write the column names as the first record.
+    pub header: bool,
+}
+
+impl Default for CsvOptions {
+    fn default() -> Self {
+        Self {
+            delimiter: ',',
+            quoting: CsvQuoting::default(),
+            header: true,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Type, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub enum ExportFormat {
+    Csv(CsvOptions),
+    /// A single array of objects.
+    Json,
+    /// One object per line.
+    Ndjson,
+    /// `INSERT` statements in the connection's dialect, one per row.
+    #[serde(rename_all = "camelCase")]
+    Sql {
+        /// Table the rows are inserted into, defaults to the exported table.
+        table_name: Option<String>,
+    },
+    /// Columns are written with the Parquet type matching theirs.
+    Parquet,
+    /// A single sheet with a header row and typed cells.
+    Xlsx,
+}
+
+#[derive(Serialize, Deserialize, Type, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportOptions {
+    /// File the rows are written to, it's overwritten if it exists.
+    pub path: String,
+    pub format: ExportFormat,
+}
+
+#[derive(Serialize, Deserialize, Type, Clone, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportSummary {
+    /// Columns of the exported rows, in the order they were written.
+    pub columns: Vec<String>,
+    pub rows_exported: u64,
+}
+
+/// A column of the exported rows, with the type typed formats write its values as.
+pub(crate) struct ExportColumn {
+    pub(crate) name: String,
+    pub(crate) r#type: CustomColumnType,
+    /// Precision and scale of decimal columns.
+    pub(crate) decimal: Option<(u32, u32)>,
+}
+
+impl From<&ColumnInfo> for ExportColumn {
+    fn from(value: &ColumnInfo) -> Self {
+        match (&value.r#type, value.numeric_precision, value.numeric_scale) {
+            (CustomColumnType::Float | CustomColumnType::Integer, Some(_), Some(_)) => {
+                Self::decimal(value.name.clone(), Some(value))
+            }
+            _ => Self {
+                name: value.name.clone(),
+                r#type: value.r#type.clone(),
+                decimal: None,
+            },
+        }
+    }
+}
+
+impl ExportColumn {
+    /// Column of a query's result, typed by the name the driver reports for its type
+    /// unless it's a column of `table`.
+    fn new(name: String, type_name: &str, table: Option<&TableInfo>) -> Self {
+        let column = table.and_then(|t| t.columns.iter().find(|c| c.name == name));
+        // SQLite reports the type of every column with a numeric affinity as `NUMERIC`,
+        // e.g dates, and Postgres' `NUMERIC` columns are typed as integers.
+        let numeric = column.is_none_or(|c| {
+            matches!(
+                c.r#type,
+                CustomColumnType::Float | CustomColumnType::Integer
+            )
+        });
+        if numeric && matches!(type_name, "NUMERIC" | "DECIMAL") {
+            return Self::decimal(name, column);
+        }
+        if let Some(column) = column {
+            return Self::from(column);
+        }
+        let r#type = match type_name {
+            "INTEGER" | "INT2" | "INT4" | "INT8" | "TINYINT" | "SMALLINT" | "INT" | "MEDIUMINT"
+            | "BIGINT" => CustomColumnType::Integer,
+            name if name.ends_with(" UNSIGNED") => CustomColumnType::PositiveInteger,
+            "YEAR" => CustomColumnType::Year,
+            "REAL" | "FLOAT4" | "FLOAT8" | "FLOAT" | "DOUBLE" => CustomColumnType::Float,
+            "BOOL" | "BOOLEAN" => CustomColumnType::Boolean,
+            "DATE" => CustomColumnType::Date,
+            "TIMESTAMP" | "TIMESTAMPTZ" | "DATETIME" => CustomColumnType::DateTime,
+            "TIME" => CustomColumnType::Time,
+            "BYTEA" | "BLOB" | "BINARY" | "VARBINARY" | "TINYBLOB" | "MEDIUMBLOB" | "LONGBLOB" => {
+                CustomColumnType::Binary
+            }
+            "JSON" | "JSONB" => CustomColumnType::Json,
+            _ => CustomColumnType::Text,
+        };
+        Self {
+            name,
+            r#type,
+            decimal: None,
+        }
+    }
+
+    /// Decimal column, written as text unless its precision and scale are known and its
+    /// values fit a [`Decimal`], e.g for unconstrained `NUMERIC` columns.
+    fn decimal(name: String, column: Option<&ColumnInfo>) -> Self {
+        let decimal = column
+            .and_then(|c| c.numeric_precision.zip(c.numeric_scale))
+            .filter(|(precision, _)| *precision <= MAX_DECIMAL_PRECISION);
+        Self {
+            name,
+            r#type: match decimal {
+                Some(_) => CustomColumnType::Float,
+                None => CustomColumnType::Text,
+            },
+            decimal,
+        }
+    }
+}
+
+/// Value of an exported cell, converted to its column's type.
+pub(crate) enum Cell {
+    Boolean(bool),
+    Integer(i64),
+    PositiveInteger(u64),
+    Float(f64),
+    Decimal(Decimal),
+    Date(NaiveDate),
+    DateTime(NaiveDateTime),
+    Time(NaiveTime),
+    Binary(Vec<u8>),
+    Text(String),
+}
+
+impl ExportColumn {
+    /// Convert a decoded value to the column's type, `None` for `NULL`s.
+    pub(crate) fn cell(&self, value: &JsonValue) -> Result<Option<Cell>> {
+        let text = match value {
+            JsonValue::Null => return Ok(None),
+            JsonValue::String(s) => Cow::Borrowed(s.trim()),
+            value => Cow::Owned(value.to_string()),
+        };
+        let invalid = |kind: &str| {
+            TxError::InvalidValue(format!(
+                "`{text}` isn't a valid {kind} for column `{}`",
+                self.name
+            ))
+        };
+
+        let cell = match &self.r#type {
+            CustomColumnType::Boolean => Cell::Boolean(match value {
+                JsonValue::Bool(b) => *b,
+                JsonValue::Number(n) => n.as_f64() != Some(0.0),
+                _ => match text.to_lowercase().as_str() {
+                    "true" | "t" | "1" => true,
+                    "false" | "f" | "0" => false,
+                    _ => return Err(invalid("boolean")),
+                },
+            }),
+            CustomColumnType::Integer | CustomColumnType::Year => {
+                Cell::Integer(text.parse().map_err(|_| invalid("integer"))?)
+            }
+            CustomColumnType::PositiveInteger => {
+                Cell::PositiveInteger(text.parse().map_err(|_| invalid("positive integer"))?)
+            }
+            CustomColumnType::Float if self.decimal.is_some() => Cell::Decimal(
+                text.parse()
+                    .or_else(|_| Decimal::from_scientific(&text))
+                    .map_err(|_| invalid("decimal"))?,
+            ),
+            CustomColumnType::Float => Cell::Float(text.parse().map_err(|_| invalid("number"))?),
+            CustomColumnType::Date => Cell::Date(parse_date(&text).ok_or_else(|| invalid("date"))?),
+            CustomColumnType::DateTime => {
+                Cell::DateTime(parse_datetime(&text).ok_or_else(|| invalid("date time"))?)
+            }
+            CustomColumnType::Time => Cell::Time(parse_time(&text).ok_or_else(|| invalid("time"))?),
+            CustomColumnType::Binary => Cell::Binary(match value {
+                JsonValue::Array(bytes) => bytes
+                    .iter()
+                    .map(|b| b.as_u64().and_then(|b| u8::try_from(b).ok()))
+                    .collect::<Option<_>>()
+                    .ok_or_else(|| invalid("binary value"))?,
+                _ => text.as_bytes().to_vec(),
+            }),
+            _ => Cell::Text(match value {
+                JsonValue::String(s) => s.clone(),
+                value => value.to_string(),
+            }),
+        };
+        Ok(Some(cell))
+    }
+}
+
+/// Write the rows returned by `stmt` to `options.path`, each row is written as soon as it's
+/// read, except for XLSX workbooks which are saved once complete.
+///
+/// The file is only created once the query returned its first row, and rows are written
+/// to a temporary file in the same directory on a blocking thread, the temporary file
+/// replaces `options.path` once every row is written so a failed export leaves it as it was.
+///
+/// `table` is the table the rows are read from, if any, its columns' types are used by
+/// typed formats. `on_progress` is called periodically with the number of rows exported so
+/// far, and once all of them are.
+pub async fn export_rows(
+    conn: &DatabaseConnection,
+    stmt: &str,
+    values: SqlxValues,
+    table: Option<&TableInfo>,
+    options: &ExportOptions,
+    mut on_progress: impl FnMut(u64),
+) -> Result<ExportSummary> {
+    let mut rows = conn.fetch_raw_stream(stmt, values);
+    let first = rows.try_next().await?;
+    let columns: Vec<ExportColumn> = match &first {
+        Some(row) => row
+            .columns()
+            .into_iter()
+            .zip(row.column_type_names())
+            .map(|(name, type_name)| ExportColumn::new(name, &type_name, table))
+            .collect(),
+        // There's no row to read the columns from, a table's are known anyway.
+        None => table
+            .map(|t| t.columns.iter().map(ExportColumn::from).collect())
+            .unwrap_or_default(),
+    };
+    let names: Vec<String> = columns.iter().map(|c| c.name.clone()).collect();
+
+    let temp_path = format!("{}.part", options.path);
+    let (sender, receiver) = tokio::sync::mpsc::channel(PENDING_ROWS);
+    let writer = {
+        let path = temp_path.clone();
+        let format = options.format.clone();
+        let driver = conn.driver();
+        let table_name = table.map(|t| t.name.clone());
+        tokio::task::spawn_blocking(move || {
+            write_rows(&path, &format, driver, table_name, columns, receiver)
+        })
+    };
+
+    let read = async {
+        let mut rows_read = 0;
+        let mut next = first;
+        while let Some(row) = next {
+            let mut row = DecodedRow::from(row);
+            let values: Vec<JsonValue> = names
+                .iter()
+                .map(|name| row.0.remove(name).unwrap_or_default())
+                .collect();
+            if sender.send(values).await.is_err() {
+                // The writer failed, its error is the one reported.
+                break;
+            }
+            rows_read += 1;
+            if rows_read % PROGRESS_INTERVAL == 0 {
+                on_progress(rows_read);
+            }
+            next = rows.try_next().await?;
+        }
+        Ok::<_, TxError>(())
+    }
+    .await;
+    drop(sender);
+    drop(rows);
+
+    let written = match writer.await {
+        Ok(written) => written,
+        Err(e) => Err(TxError::Export(e.to_string())),
+    };
+    let rows_exported = match read.and(written) {
+        Ok(rows_exported) => rows_exported,
+        Err(e) => {
+            let _ = std::fs::remove_file(&temp_path);
+            return Err(e);
+        }
+    };
+    if let Err(e) = std::fs::rename(&temp_path, &options.path) {
+        let _ = std::fs::remove_file(&temp_path);
+        return Err(e.into());
+    }
+    on_progress(rows_exported);
+
+    Ok(ExportSummary {
+        columns: names,
+        rows_exported,
+    })
+}
+
+/// Write the rows received from `receiver` to `path`, returning the number of rows written.
+fn write_rows(
+    path: &str,
+    format: &ExportFormat,
+    driver: Drivers,
+    table_name: Option<String>,
+    columns: Vec<ExportColumn>,
+    mut receiver: tokio::sync::mpsc::Receiver<Vec<JsonValue>>,
+) -> Result<u64> {
+    let mut writer = RowWriter::new(path, format, driver, table_name)?;
+    writer.start(&columns)?;
+    let mut rows_written = 0;
+    while let Some(values) = receiver.blocking_recv() {
+        rows_written += 1;
+        writer.write_row(&columns, &values).map_err(|e| match e {
+            TxError::InvalidValue(reason) => {
+                TxError::Export(format!("row {rows_written}: {reason}"))
+            }
+            e => e,
+        })?;
+    }
+    writer.finish()?;
+    Ok(rows_written)
+}
+
+type Output = BufWriter<File>;
+
+enum RowWriter {
+    Csv {
+        writer: csv::Writer<Output>,
+        header: bool,
+    },
+    Json {
+        out: Output,
+        first: bool,
+    },
+    Ndjson(Output),
+    Sql {
+        out: Output,
+        driver: Drivers,
+        table_name: String,
+    },
+    /// The writer is created once the columns are known.
+    Parquet {
+        out: Option<File>,
+        writer: Option<ParquetWriter>,
+    },
+    Xlsx(Box<XlsxWriter>),
+}
+
+impl RowWriter {
+    fn new(
+        path: &str,
+        format: &ExportFormat,
+        driver: Drivers,
+        table_name: Option<String>,
+    ) -> Result<Self> {
+        if let ExportFormat::Xlsx = format {
+            return Ok(RowWriter::Xlsx(Box::new(XlsxWriter::new(path))));
+        }
+        let file = File::create(path)?;
+        let writer = match format {
+            ExportFormat::Csv(options) => {
+                if !options.delimiter.is_ascii() {
+                    return Err(TxError::Export(format!(
+                        "the delimiter `{}` isn't an ASCII character",
+                        options.delimiter
+                    )));
+                }
+                let writer = csv::WriterBuilder::new()
+                    .delimiter(options.delimiter as u8)
+                    .quote_style(match options.quoting {
+                        CsvQuoting::Necessary => csv::QuoteStyle::Necessary,
+                        CsvQuoting::Always => csv::QuoteStyle::Always,
+                        CsvQuoting::NonNumeric => csv::QuoteStyle::NonNumeric,
+                        CsvQuoting::Never => csv::QuoteStyle::Never,
+                    })
+                    .from_writer(BufWriter::new(file));
+                RowWriter::Csv {
+                    writer,
+                    header: options.header,
+                }
+            }
+            ExportFormat::Json => RowWriter::Json {
+                out: BufWriter::new(file),
+                first: true,
+            },
+            ExportFormat::Ndjson => RowWriter::Ndjson(BufWriter::new(file)),
+            ExportFormat::Sql { table_name: target } => {
+                let Some(table_name) = target.clone().or(table_name) else {
+                    return Err(TxError::Export(
+                        "INSERT statements require a table name".to_string(),
+                    ));
+                };
+                RowWriter::Sql {
+                    out: BufWriter::new(file),
+                    driver,
+                    table_name,
+                }
+            }
+            ExportFormat::Parquet => RowWriter::Parquet {
+                out: Some(file),
+                writer: None,
+            },
+            ExportFormat::Xlsx => unreachable!(),
+        };
+        Ok(writer)
+    }
+
+    /// Write what precedes the rows, `columns` is empty if they're unknown.
+    fn start(&mut self, columns: &[ExportColumn]) -> Result<()> {
+        match self {
+            RowWriter::Csv { writer, header } => {
+                if *header && !columns.is_empty() {
+                    writer
+                        .write_record(columns.iter().map(|c| &c.name))
+                        .map_err(csv_error)?;
+                }
+            }
+            RowWriter::Json { out, .. } => out.write_all(b"[")?,
+            RowWriter::Ndjson(_) | RowWriter::Sql { .. } => {}
+            RowWriter::Parquet { out, writer } => {
+                if let Some(out) = out.take() {
+                    *writer = Some(ParquetWriter::new(out, columns)?);
+                }
+            }
+            RowWriter::Xlsx(writer) => writer.start(columns)?,
+        }
+        Ok(())
+    }
+
+    fn write_row(&mut self, columns: &[ExportColumn], values: &[JsonValue]) -> Result<()> {
+        match self {
+            RowWriter::Csv { writer, .. } => writer
+                .write_record(values.iter().map(|v| csv_field(v).into_owned()))
+                .map_err(csv_error)?,
+            RowWriter::Json { out, first } => {
+                if !*first {
+                    out.write_all(b",")?;
+                }
+                out.write_all(b"\n")?;
+                write_object(out, columns, values)?;
+                *first = false;
+            }
+            RowWriter::Ndjson(out) => {
+                write_object(out, columns, values)?;
+                out.write_all(b"\n")?;
+            }
+            RowWriter::Sql {
+                out,
+                driver,
+                table_name,
+            } => {
+                let stmt = Query::insert()
+                    .into_table(Alias::new(table_name.as_str()))
+                    .columns(columns.iter().map(|c| Alias::new(&c.name)))
+                    .values_panic(values.iter().map(|v| SimpleExpr::Value(literal(v))))
+                    .to_owned();
+                writeln!(out, "{};", inline(driver, &stmt))?;
+            }
+            RowWriter::Parquet { writer, .. } => {
+                if let Some(writer) = writer {
+                    writer.write_row(columns, values)?;
+                }
+            }
+            RowWriter::Xlsx(writer) => writer.write_row(columns, values)?,
+        }
+        Ok(())
+    }
+
+    fn finish(self) -> Result<()> {
+        match self {
+            RowWriter::Csv { mut writer, .. } => writer.flush()?,
+            RowWriter::Json { mut out, first } => {
+                if !first {
+                    out.write_all(b"\n")?;
+                }
+                out.write_all(b"]\n")?;
+                out.flush()?;
+            }
+            RowWriter::Ndjson(mut out) | RowWriter::Sql { mut out, .. } => out.flush()?,
+            RowWriter::Parquet { writer, .. } => {
+                if let Some(writer) = writer {
+                    writer.finish()?;
+                }
+            }
+            RowWriter::Xlsx(writer) => writer.finish()?,
+        }
+        Ok(())
+    }
+}
+
+/// Text of a cell in a CSV file, `NULL`s are written as empty fields.
+fn csv_field(value: &JsonValue) -> Cow<'_, str> {
+    match value {
+        JsonValue::Null => Cow::Borrowed(""),
+        JsonValue::String(s) => Cow::Borrowed(s),
+        value => Cow::Owned(value.to_string()),
+    }
+}
+
+/// Write a row as a JSON object, keeping the order of its columns.
+fn write_object(
+    out: &mut impl Write,
+    columns: &[ExportColumn],
+    values: &[JsonValue],
+) -> Result<()> {
+    out.write_all(b"{")?;
+    for (i, (column, value)) in columns.iter().zip(values).enumerate() {
+        if i > 0 {
+            out.write_all(b",")?;
+        }
+        serde_json::to_writer(&mut *out, &column.name)?;
+        out.write_all(b":")?;
+        serde_json::to_writer(&mut *out, value)?;
+    }
+    out.write_all(b"}")?;
+    Ok(())
+}
+
+fn csv_error(e: csv::Error) -> TxError {
+    TxError::Export(e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use parquet::basic::{ConvertedType, Type as PhysicalType};
+    use sea_query::Values;
+
+    fn temp_path(name: &str) -> String {
+        std::env::temp_dir()
+            .join(format!("tx-export-{}-{name}", std::process::id()))
+            .to_string_lossy()
+            .into_owned()
+    }
+
+    async fn export(
+        conn: &DatabaseConnection,
+        query: &str,
+        options: &ExportOptions,
+    ) -> Result<u64> {
+        let summary = export_rows(
+            conn,
+            query,
+            SqlxValues(Values(vec![])),
+            None,
+            options,
+            |_| {},
+        )
+        .await?;
+        Ok(summary.rows_exported)
+    }
+
+    #[tokio::test]
+    async fn rows_are_written_in_order() {
+        let conn = DatabaseConnection::in_memory().await.unwrap();
+        let options = ExportOptions {
+            path: temp_path("rows.ndjson"),
+            format: ExportFormat::Ndjson,
+        };
+        let query = "SELECT 1 AS id, 'a' AS name UNION ALL SELECT 2, NULL";
+        assert_eq!(export(&conn, query, &options).await.unwrap(), 2);
+
+        let written = std::fs::read_to_string(&options.path).unwrap();
+        std::fs::remove_file(&options.path).unwrap();
+        assert_eq!(
+            written,
+            "{\"id\":1,\"name\":\"a\"}\n{\"id\":2,\"name\":null}\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn failed_exports_leave_the_file_untouched() {
+        let conn = DatabaseConnection::in_memory().await.unwrap();
+        let path = temp_path("failed.sql");
+        std::fs::write(&path, "previous").unwrap();
+
+        // The query fails before anything is written.
+        let options = ExportOptions {
+            path: path.clone(),
+            format: ExportFormat::Ndjson,
+        };
+        assert!(
+            export(&conn, "SELECT * FROM missing", &options)
+                .await
+                .is_err()
+        );
+        // The rows can't be written without a table name.
+        let options = ExportOptions {
+            path: path.clone(),
+            format: ExportFormat::Sql { table_name: None },
+        };
+        assert!(matches!(
+            export(&conn, "SELECT 1 AS id", &options).await,
+            Err(TxError::Export(_))
+        ));
+
+        let content = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(content, "previous");
+        assert!(!std::path::Path::new(&format!("{path}.part")).exists());
+    }
+
+    async fn typed_table() -> (DatabaseConnection, TableInfo) {
+        let mut conn = DatabaseConnection::in_memory().await.unwrap();
+        conn.execute(
+            "CREATE TABLE t (id INTEGER PRIMARY KEY, price DECIMAL(10, 2), big DECIMAL(16, 2))",
+        )
+        .await
+        .unwrap();
+        conn.execute("INSERT INTO t VALUES (1, 12, 123), (2, NULL, NULL)")
+            .await
+            .unwrap();
+        let mut table = conn.table_info("t").await.unwrap();
+        // SQLite's discovery rejects precisions over 16, as other drivers would report it.
+        table.columns[2].numeric_precision = Some(40);
+        (conn, table)
+    }
+
+    #[tokio::test]
+    async fn parquet_columns_are_typed() {
+        use parquet::file::reader::{FileReader, SerializedFileReader};
+
+        let (conn, table) = typed_table().await;
+        let options = ExportOptions {
+            path: temp_path("typed.parquet"),
+            format: ExportFormat::Parquet,
+        };
+        let summary = export_rows(
+            &conn,
+            "SELECT * FROM t",
+            SqlxValues(Values(vec![])),
+            Some(&table),
+            &options,
+            |_| {},
+        )
+        .await
+        .unwrap();
+        assert_eq!(summary.rows_exported, 2);
+
+        let reader = SerializedFileReader::new(File::open(&options.path).unwrap()).unwrap();
+        std::fs::remove_file(&options.path).unwrap();
+        let metadata = reader.metadata();
+        assert_eq!(metadata.file_metadata().num_rows(), 2);
+        let schema = metadata.file_metadata().schema_descr();
+        let types: Vec<_> = (0..schema.num_columns())
+            .map(|i| {
+                (
+                    schema.column(i).physical_type(),
+                    schema.column(i).converted_type(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            types,
+            vec![
+                (PhysicalType::INT64, ConvertedType::NONE),
+                (PhysicalType::INT64, ConvertedType::DECIMAL),
+                // Too wide for a `Decimal`.
+                (PhysicalType::BYTE_ARRAY, ConvertedType::UTF8),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn xlsx_workbooks_are_saved() {
+        let (conn, table) = typed_table().await;
+        let options = ExportOptions {
+            path: temp_path("typed.xlsx"),
+            format: ExportFormat::Xlsx,
+        };
+        export_rows(
+            &conn,
+            "SELECT * FROM t",
+            SqlxValues(Values(vec![])),
+            Some(&table),
+            &options,
+            |_| {},
+        )
+        .await
+        .unwrap();
+
+        let content = std::fs::read(&options.path).unwrap();
+        std::fs::remove_file(&options.path).unwrap();
+        // Workbooks are zip archives.
+        assert!(content.starts_with(b"PK"));
+    }
+}
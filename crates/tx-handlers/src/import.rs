@@ -0,0 +1,810 @@
+//! Inserting the rows of a CSV or JSON file into a table, converting each value to the
+//! type of the column it's mapped to.
+
+use crate::{
+    DatabaseConnection,
+    ddl::{ColumnDefinition, SchemaChange, TableDefinition},
+    query::DecodedRow,
+    schema::{ColumnInfo, CustomColumnType, RowRecord, parse_datetime},
+    transfer::{MAX_PARAMETERS, transfer_type},
+};
+use chrono::{NaiveDate, NaiveTime};
+use parquet::{
+    errors::ParquetError,
+    file::reader::{FileReader, SerializedFileReader},
+    record::reader::RowIter,
+};
+use sea_query::{Alias, InsertStatement, Query, SimpleExpr, Values};
+use sea_query_binder::{SqlxBinder, SqlxValues};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map as JsonMap, Value as JsonValue};
+use specta::Type;
+use std::{
+    fs::File,
+    io::{BufRead, BufReader, Read},
+};
+use tx_lib::{Result, TxError};
+use uuid::Uuid;
+
+/// Number of rows read to infer the types of a file's columns.
+const INFERENCE_ROWS: usize = 1_000;
+
+/// Most row errors listed in an import's summary, the others are only counted.
+const MAX_REPORTED_ERRORS: usize = 1_000;
+
+#[derive(Serialize, Deserialize, Type, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct CsvImportOptions {
+    /// Must be an ASCII character.
+    pub delimiter: char,
+    /// Read the column names from the first record, otherwise they're named `column1`,
+    /// `column2`...
+    pub header: bool,
+}
+
+impl Default for CsvImportOptions {
+    fn default() -> Self {
+        Self {
+            delimiter: ',',
+            header: true,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Type, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub enum ImportFormat {
+    /// Empty fields are imported as `NULL`s.
+    Csv(CsvImportOptions),
+    /// An array of objects, or one object per line.
+    Json,
+    Parquet,
+}
+
+/// A column of the imported file.
+#[derive(Serialize, Deserialize, Type, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct FileColumn {
+    pub name: String,
+    /// Type inferred from the first rows of the file.
+    pub r#type: CustomColumnType,
+    /// Whether some of the first rows have no value for the column.
+    pub nullable: bool,
+}
+
+#[derive(Serialize, Deserialize, Type, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportPreview {
+    /// Columns of CSV and Parquet files in the order they're stored in, JSON ones in the
+    /// order their keys first appear.
+    pub columns: Vec<FileColumn>,
+    /// First rows of the file.
+    pub rows: Vec<DecodedRow>,
+}
+
+#[derive(Serialize, Deserialize, Type, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ColumnMapping {
+    /// Column of the file.
+    pub source: String,
+    /// Column of the table the values are inserted into.
+    pub target: String,
+    /// Type of the column when the table is created, ignored otherwise.
+    pub r#type: CustomColumnType,
+}
+
+#[derive(Serialize, Deserialize, Type, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportOptions {
+    pub path: String,
+    pub format: ImportFormat,
+    pub table_name: String,
+    /// Create the table from the mapped columns if it doesn't exist.
+    pub create_table: bool,
+    /// File columns that aren't mapped are left out.
+    pub columns: Vec<ColumnMapping>,
+    /// Number of rows inserted per statement, each batch in its own transaction.
+    pub batch_size: u32,
+}
+
+#[derive(Serialize, Deserialize, Type, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportRowError {
+    /// Line of the file the row starts at, or its position in Parquet files.
+    pub line: u64,
+    pub message: String,
+}
+
+#[derive(Serialize, Deserialize, Type, Clone, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportSummary {
+    pub created_table: bool,
+    pub rows_imported: u64,
+    pub rows_failed: u64,
+    /// Errors of the first failed rows.
+    pub errors: Vec<ImportRowError>,
+}
+
+impl ImportSummary {
+    fn fail_row(&mut self, line: u64, message: String) {
+        self.rows_failed += 1;
+        if self.errors.len() < MAX_REPORTED_ERRORS {
+            self.errors.push(ImportRowError { line, message });
+        }
+    }
+}
+
+/// Read the first rows of a file and infer the types of its columns, `limit` is the
+/// number of rows returned.
+pub fn preview_import(path: &str, format: &ImportFormat, limit: u32) -> Result<ImportPreview> {
+    let mut reader = RowReader::open(path, format)?;
+    let mut rows = vec![];
+    while rows.len() < INFERENCE_ROWS.max(limit as usize) {
+        match reader.next_row()? {
+            Some(row) => rows.push(row.values),
+            None => break,
+        }
+    }
+
+    let names = match reader.columns() {
+        Some(columns) => columns.to_vec(),
+        None => {
+            let mut names: Vec<String> = vec![];
+            for key in rows.iter().flat_map(|row| row.keys()) {
+                if !names.contains(key) {
+                    names.push(key.clone());
+                }
+            }
+            names
+        }
+    };
+    let sample = &rows[..rows.len().min(INFERENCE_ROWS)];
+    let columns = names
+        .into_iter()
+        .map(|name| {
+            let values: Vec<&JsonValue> = sample
+                .iter()
+                .filter_map(|row| row.get(&name))
+                .filter(|v| !v.is_null())
+                .collect();
+            FileColumn {
+                r#type: infer_type(&values),
+                nullable: values.len() < sample.len(),
+                name,
+            }
+        })
+        .collect();
+
+    rows.truncate(limit as usize);
+    Ok(ImportPreview {
+        columns,
+        rows: rows.into_iter().map(DecodedRow).collect(),
+    })
+}
+
+/// Insert the rows of `options.path` into `options.table_name`.
+///
+/// Rows are inserted in batches, each in its own transaction. Rows whose values don't
+/// fit their columns are skipped, and the rows of a batch the database rejects are
+/// inserted one by one so the failing ones can be told apart. `on_progress` is called
+/// after each batch with the number of rows imported so far.
+pub async fn import_file(
+    conn: &mut DatabaseConnection,
+    options: &ImportOptions,
+    on_progress: impl FnMut(u64),
+) -> Result<ImportSummary> {
+    import_rows(conn, options, false, on_progress).await
+}
+
+/// Insert the rows of `options.path` like [`import_file`], values that don't fit their
+/// column's type are inserted as text if `keep_mismatched` instead of skipping their row,
+/// for SQLite to store them with the `TEXT` affinity.
+pub(crate) async fn import_rows(
+    conn: &mut DatabaseConnection,
+    options: &ImportOptions,
+    keep_mismatched: bool,
+    mut on_progress: impl FnMut(u64),
+) -> Result<ImportSummary> {
+    if options.columns.is_empty() {
+        return Err(TxError::Import("no column is mapped".to_string()));
+    }
+    let mut reader = RowReader::open(&options.path, &options.format)?;
+    if let Some(columns) = reader.columns()
+        && let Some(mapping) = options
+            .columns
+            .iter()
+            .find(|m| !columns.contains(&m.source))
+    {
+        return Err(TxError::Import(format!(
+            "the file has no column `{}`",
+            mapping.source
+        )));
+    }
+    let mut summary = ImportSummary::default();
+
+    if options.create_table && !conn.table_exists(&options.table_name).await? {
+        conn.apply_schema_change(&SchemaChange::Create(table_definition(options)))
+            .await?;
+        summary.created_table = true;
+    }
+    let table = conn.table_info(&options.table_name).await?;
+    let columns = options
+        .columns
+        .iter()
+        .map(|mapping| {
+            table
+                .columns
+                .iter()
+                .find(|c| c.name == mapping.target)
+                .map(|c| (mapping.source.as_str(), c))
+                .ok_or_else(|| {
+                    TxError::Import(format!(
+                        "table `{}` has no column `{}`",
+                        table.name, mapping.target
+                    ))
+                })
+        })
+        .collect::<Result<Vec<(&str, &ColumnInfo)>>>()?;
+
+    let driver = conn.driver();
+    let batch_rows = (options.batch_size.max(1) as usize)
+        .min(MAX_PARAMETERS / columns.len())
+        .max(1);
+    let insert = Query::insert()
+        .into_table(Alias::new(&table.name))
+        .columns(columns.iter().map(|(_, c)| Alias::new(&c.name)))
+        .to_owned();
+
+    let mut batch: Vec<(u64, Vec<SimpleExpr>)> = Vec::with_capacity(batch_rows);
+    let mut exhausted = false;
+    while !exhausted {
+        match reader.next_row()? {
+            Some(mut row) => {
+                let values = columns
+                    .iter()
+                    .map(|(source, column)| {
+                        let value = match row.values.remove(*source).unwrap_or_default() {
+                            value @ (JsonValue::Array(_) | JsonValue::Object(_))
+                                if column.r#type != CustomColumnType::Json =>
+                            {
+                                JsonValue::String(value.to_string())
+                            }
+                            value => value,
+                        };
+                        let record = |value, column_type| RowRecord {
+                            column_name: column.name.clone(),
+                            value,
+                            column_type,
+                        };
+                        let column_type = transfer_type(&driver, &column.r#type);
+                        match record(value.clone(), column_type).try_into_value() {
+                            Err(TxError::InvalidValue(_)) if keep_mismatched => {
+                                record(value, CustomColumnType::Text).try_into_value()
+                            }
+                            converted => converted,
+                        }
+                        .map(SimpleExpr::Value)
+                    })
+                    .collect::<Result<Vec<_>>>();
+                match values {
+                    Ok(values) => batch.push((row.line, values)),
+                    Err(TxError::InvalidValue(reason)) => summary.fail_row(row.line, reason),
+                    Err(e) => return Err(e),
+                }
+                if batch.len() < batch_rows {
+                    continue;
+                }
+            }
+            None => exhausted = true,
+        }
+        if batch.is_empty() {
+            continue;
+        }
+
+        insert_batch(conn, &insert, &mut batch, &mut summary).await?;
+        on_progress(summary.rows_imported);
+    }
+
+    Ok(summary)
+}
+
+/// Insert the rows of `batch` in a transaction, or one by one if the database rejects
+/// some of them, each in a savepoint of a single transaction so the batch is still
+/// committed at once.
+async fn insert_batch(
+    conn: &DatabaseConnection,
+    insert: &InsertStatement,
+    batch: &mut Vec<(u64, Vec<SimpleExpr>)>,
+    summary: &mut ImportSummary,
+) -> Result<()> {
+    let mut stmt = insert.clone();
+    for (_, values) in batch.iter() {
+        stmt.values_panic(values.clone());
+    }
+    let (stmt, values) = stmt.build_any_sqlx(conn.into_builder().as_ref());
+    let mut tx = conn.begin().await?;
+    match tx.execute_with(&stmt, values).await {
+        Ok(_) => {
+            tx.commit().await?;
+            summary.rows_imported += batch.len() as u64;
+            batch.clear();
+            return Ok(());
+        }
+        Err(TxError::Database(sqlx::Error::Database(_))) => drop(tx),
+        Err(e) => return Err(e),
+    }
+
+    let no_values = || SqlxValues(Values(vec![]));
+    let mut tx = conn.begin().await?;
+    let mut rows_imported = 0;
+    let mut failures = vec![];
+    for (line, values) in batch.drain(..) {
+        let (stmt, values) = insert
+            .clone()
+            .values_panic(values)
+            .build_any_sqlx(conn.into_builder().as_ref());
+        tx.execute_with("SAVEPOINT import_row", no_values()).await?;
+        match tx.execute_with(&stmt, values).await {
+            Ok(_) => rows_imported += 1,
+            Err(TxError::Database(sqlx::Error::Database(e))) => {
+                tx.execute_with("ROLLBACK TO SAVEPOINT import_row", no_values())
+                    .await?;
+                failures.push((line, e.message().to_string()));
+            }
+            Err(e) => return Err(e),
+        }
+        tx.execute_with("RELEASE SAVEPOINT import_row", no_values())
+            .await?;
+    }
+    tx.commit().await?;
+
+    summary.rows_imported += rows_imported;
+    for (line, message) in failures {
+        summary.fail_row(line, message);
+    }
+    Ok(())
+}
+
+/// Definition of a table with the mapped columns, all nullable.
+fn table_definition(options: &ImportOptions) -> TableDefinition {
+    TableDefinition {
+        name: options.table_name.clone(),
+        columns: options
+            .columns
+            .iter()
+            .map(|mapping| ColumnDefinition {
+                name: mapping.target.clone(),
+                previous_name: None,
+                r#type: mapping.r#type.clone(),
+                nullable: true,
+                auto_increment: false,
+                default_value: None,
+                max_length: None,
+                numeric_precision: None,
+                numeric_scale: None,
+            })
+            .collect(),
+        primary_key: vec![],
+        indexes: vec![],
+        foreign_keys: vec![],
+    }
+}
+
+/// The most specific type all the values fit, falling back to text.
+fn infer_type(values: &[&JsonValue]) -> CustomColumnType {
+    if values.is_empty() {
+        return CustomColumnType::Text;
+    }
+    [
+        CustomColumnType::Boolean,
+        CustomColumnType::Integer,
+        CustomColumnType::Float,
+        CustomColumnType::Date,
+        CustomColumnType::DateTime,
+        CustomColumnType::Time,
+        CustomColumnType::Uuid,
+        CustomColumnType::Json,
+    ]
+    .into_iter()
+    .find(|column_type| values.iter().all(|v| fits_type(v, column_type)))
+    .unwrap_or(CustomColumnType::Text)
+}
+
+fn fits_type(value: &JsonValue, column_type: &CustomColumnType) -> bool {
+    let text = match value {
+        JsonValue::String(s) => s.trim(),
+        JsonValue::Bool(_) => return *column_type == CustomColumnType::Boolean,
+        JsonValue::Number(n) => {
+            return match column_type {
+                CustomColumnType::Integer => n.is_i64(),
+                CustomColumnType::Float => true,
+                _ => false,
+            };
+        }
+        JsonValue::Array(_) | JsonValue::Object(_) => {
+            return *column_type == CustomColumnType::Json;
+        }
+        JsonValue::Null => return true,
+    };
+    match column_type {
+        CustomColumnType::Boolean => {
+            text.eq_ignore_ascii_case("true") || text.eq_ignore_ascii_case("false")
+        }
+        CustomColumnType::Integer => text.parse::<i64>().is_ok(),
+        // Rules out `inf` and `NaN`.
+        CustomColumnType::Float => {
+            text.parse::<f64>().is_ok() && text.bytes().any(|b| b.is_ascii_digit())
+        }
+        CustomColumnType::Date => NaiveDate::parse_from_str(text, "%Y-%m-%d").is_ok(),
+        CustomColumnType::DateTime => parse_datetime(text).is_some(),
+        CustomColumnType::Time => NaiveTime::parse_from_str(text, "%H:%M:%S%.f").is_ok(),
+        // The hyphenated form only, other strings of 32 hex digits are likely something else.
+        CustomColumnType::Uuid => text.len() == 36 && Uuid::parse_str(text).is_ok(),
+        _ => false,
+    }
+}
+
+struct SourceRow {
+    /// Line of the file the row starts at.
+    line: u64,
+    values: JsonMap<String, JsonValue>,
+}
+
+enum RowReader {
+    Csv {
+        reader: csv::Reader<File>,
+        columns: Vec<String>,
+        /// First record of files without a header, read to count their columns.
+        first: Option<csv::StringRecord>,
+    },
+    Json(JsonRows),
+    Parquet {
+        rows: RowIter<'static>,
+        columns: Vec<String>,
+        /// Position of the next row.
+        position: u64,
+    },
+}
+
+impl RowReader {
+    fn open(path: &str, format: &ImportFormat) -> Result<Self> {
+        let reader = match format {
+            ImportFormat::Csv(options) => {
+                if !options.delimiter.is_ascii() {
+                    return Err(TxError::Import(format!(
+                        "the delimiter `{}` isn't an ASCII character",
+                        options.delimiter
+                    )));
+                }
+                let mut reader = csv::ReaderBuilder::new()
+                    .delimiter(options.delimiter as u8)
+                    .has_headers(false)
+                    .flexible(true)
+                    .from_path(path)
+                    .map_err(csv_error)?;
+                let mut record = csv::StringRecord::new();
+                if !reader.read_record(&mut record).map_err(csv_error)? {
+                    return Err(TxError::Import("the file is empty".to_string()));
+                }
+                if options.header {
+                    RowReader::Csv {
+                        reader,
+                        columns: record.iter().map(|c| c.trim().to_string()).collect(),
+                        first: None,
+                    }
+                } else {
+                    RowReader::Csv {
+                        reader,
+                        columns: (1..=record.len()).map(|i| format!("column{i}")).collect(),
+                        first: Some(record),
+                    }
+                }
+            }
+            ImportFormat::Json => RowReader::Json(JsonRows::new(File::open(path)?)?),
+            ImportFormat::Parquet => {
+                let reader = SerializedFileReader::new(File::open(path)?).map_err(parquet_error)?;
+                let columns = reader
+                    .metadata()
+                    .file_metadata()
+                    .schema()
+                    .get_fields()
+                    .iter()
+                    .map(|field| field.name().to_string())
+                    .collect();
+                RowReader::Parquet {
+                    rows: reader.into_iter(),
+                    columns,
+                    position: 1,
+                }
+            }
+        };
+        Ok(reader)
+    }
+
+    /// Names of the columns, unknown until all the rows are read for JSON files.
+    fn columns(&self) -> Option<&[String]> {
+        match self {
+            RowReader::Csv { columns, .. } | RowReader::Parquet { columns, .. } => Some(columns),
+            RowReader::Json(_) => None,
+        }
+    }
+
+    fn next_row(&mut self) -> Result<Option<SourceRow>> {
+        match self {
+            RowReader::Csv {
+                reader,
+                columns,
+                first,
+            } => {
+                let record = match first.take() {
+                    Some(record) => record,
+                    None => {
+                        let mut record = csv::StringRecord::new();
+                        if !reader.read_record(&mut record).map_err(csv_error)? {
+                            return Ok(None);
+                        }
+                        record
+                    }
+                };
+                let values = columns
+                    .iter()
+                    .enumerate()
+                    .map(|(i, name)| {
+                        let value = match record.get(i) {
+                            None | Some("") => JsonValue::Null,
+                            Some(value) => JsonValue::String(value.to_string()),
+                        };
+                        (name.clone(), value)
+                    })
+                    .collect();
+                Ok(Some(SourceRow {
+                    line: record.position().map(|p| p.line()).unwrap_or_default(),
+                    values,
+                }))
+            }
+            RowReader::Json(rows) => rows.next_row(),
+            RowReader::Parquet { rows, position, .. } => {
+                let Some(row) = rows.next().transpose().map_err(parquet_error)? else {
+                    return Ok(None);
+                };
+                // Dates, times and decimals are converted to strings, nested values to
+                // arrays and objects.
+                let JsonValue::Object(values) = row.to_json_value() else {
+                    unreachable!("rows are converted to objects");
+                };
+                let line = *position;
+                *position += 1;
+                Ok(Some(SourceRow { line, values }))
+            }
+        }
+    }
+}
+
+/// Objects of a JSON file, parsed one at a time as the file is read.
+struct JsonRows {
+    reader: LineReader<BufReader<File>>,
+    /// Whether the rows are the elements of an array, rather than one object per line.
+    array: bool,
+}
+
+impl JsonRows {
+    fn new(file: File) -> Result<Self> {
+        let mut rows = Self {
+            reader: LineReader {
+                inner: BufReader::new(file),
+                line: 1,
+            },
+            array: false,
+        };
+        rows.reader.skip_whitespace()?;
+        if rows.reader.peek()? == Some(b'[') {
+            rows.array = true;
+            rows.reader.consume();
+        }
+        Ok(rows)
+    }
+
+    fn next_row(&mut self) -> Result<Option<SourceRow>> {
+        self.reader.skip_whitespace()?;
+        match self.reader.peek()? {
+            None if self.array => {
+                return Err(TxError::Import("the JSON array isn't closed".to_string()));
+            }
+            None => return Ok(None),
+            Some(b']') if self.array => return Ok(None),
+            _ => {}
+        }
+
+        let line = self.reader.line;
+        let mut deserializer = serde_json::Deserializer::from_reader(&mut self.reader);
+        let value = JsonValue::deserialize(&mut deserializer).map_err(|e| {
+            // The error's own position is relative to the row.
+            let message = e.to_string();
+            let message = message.split(" at line ").next().unwrap_or_default();
+            TxError::Import(format!("line {}: {message}", line + e.line() as u64 - 1))
+        })?;
+
+        if self.array {
+            self.reader.skip_whitespace()?;
+            match self.reader.peek()? {
+                Some(b',') => self.reader.consume(),
+                Some(b']') => {}
+                _ => {
+                    return Err(TxError::Import(format!(
+                        "line {}: expected `,` or `]` after a row",
+                        self.reader.line
+                    )));
+                }
+            }
+        }
+        match value {
+            JsonValue::Object(values) => Ok(Some(SourceRow { line, values })),
+            _ => Err(TxError::Import(format!(
+                "line {line}: rows must be JSON objects"
+            ))),
+        }
+    }
+}
+
+/// Keeps track of the line the next byte read is at.
+struct LineReader<R> {
+    inner: R,
+    line: u64,
+}
+
+impl<R: BufRead> LineReader<R> {
+    fn peek(&mut self) -> Result<Option<u8>> {
+        Ok(self.inner.fill_buf()?.first().copied())
+    }
+
+    /// Skip the byte returned by [`Self::peek`].
+    fn consume(&mut self) {
+        let mut byte = [0];
+        // The byte is already buffered.
+        let _ = self.read(&mut byte);
+    }
+
+    fn skip_whitespace(&mut self) -> Result<()> {
+        while self.peek()?.is_some_and(|b| b.is_ascii_whitespace()) {
+            self.consume();
+        }
+        Ok(())
+    }
+}
+
+impl<R: BufRead> Read for LineReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        self.line += buf[..read].iter().filter(|b| **b == b'\n').count() as u64;
+        Ok(read)
+    }
+}
+
+fn csv_error(e: csv::Error) -> TxError {
+    TxError::Import(e.to_string())
+}
+
+fn parquet_error(e: ParquetError) -> TxError {
+    TxError::Import(e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::export::{ExportFormat, ExportOptions, export_rows};
+
+    fn temp_file(name: &str, content: &str) -> String {
+        let path = std::env::temp_dir()
+            .join(format!("tx-import-{}-{name}", std::process::id()))
+            .to_string_lossy()
+            .into_owned();
+        std::fs::write(&path, content).unwrap();
+        path
+    }
+
+    fn mapping(names: &[&str], r#type: CustomColumnType) -> Vec<ColumnMapping> {
+        names
+            .iter()
+            .map(|name| ColumnMapping {
+                source: name.to_string(),
+                target: name.to_string(),
+                r#type: r#type.clone(),
+            })
+            .collect()
+    }
+
+    async fn names(conn: &DatabaseConnection, query: &str) -> Vec<JsonValue> {
+        conn.fetch_all(query, SqlxValues(Values(vec![])))
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|row| DecodedRow::from(row).0.remove("name").unwrap())
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn rejected_rows_are_reported_and_the_others_imported() {
+        let mut conn = DatabaseConnection::in_memory().await.unwrap();
+        conn.execute("CREATE TABLE t (id INTEGER PRIMARY KEY, name TEXT NOT NULL)")
+            .await
+            .unwrap();
+        let path = temp_file("rejected.csv", "id,name\n1,a\n2,\n3,c\n1,d\n");
+        let options = ImportOptions {
+            path: path.clone(),
+            format: ImportFormat::Csv(CsvImportOptions::default()),
+            table_name: "t".to_string(),
+            // The table already exists.
+            create_table: true,
+            columns: mapping(&["id", "name"], CustomColumnType::Text),
+            batch_size: 10,
+        };
+        let summary = import_file(&mut conn, &options, |_| {}).await.unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert!(!summary.created_table);
+        assert_eq!(summary.rows_imported, 2);
+        let lines: Vec<u64> = summary.errors.iter().map(|e| e.line).collect();
+        assert_eq!(lines, vec![3, 5]);
+        assert_eq!(
+            names(&conn, "SELECT name FROM t ORDER BY id").await,
+            vec![JsonValue::from("a"), JsonValue::from("c")]
+        );
+    }
+
+    #[tokio::test]
+    async fn exported_rows_are_imported_back() {
+        let mut conn = DatabaseConnection::in_memory().await.unwrap();
+        conn.execute("CREATE TABLE t (id INTEGER PRIMARY KEY, name TEXT)")
+            .await
+            .unwrap();
+        conn.execute("INSERT INTO t VALUES (1, 'a'), (2, NULL), (3, 'line\nbreak')")
+            .await
+            .unwrap();
+        let path = temp_file("exported.json", "");
+        let export = ExportOptions {
+            path: path.clone(),
+            format: ExportFormat::Json,
+        };
+        export_rows(
+            &conn,
+            "SELECT * FROM t",
+            SqlxValues(Values(vec![])),
+            None,
+            &export,
+            |_| {},
+        )
+        .await
+        .unwrap();
+
+        let options = ImportOptions {
+            path: path.clone(),
+            format: ImportFormat::Json,
+            table_name: "copy".to_string(),
+            create_table: true,
+            columns: mapping(&["name"], CustomColumnType::Text),
+            batch_size: 2,
+        };
+        let summary = import_file(&mut conn, &options, |_| {}).await.unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert!(summary.created_table);
+        assert_eq!(summary.rows_imported, 3);
+        assert_eq!(
+            names(&conn, "SELECT name FROM copy").await,
+            names(&conn, "SELECT name FROM t ORDER BY id").await
+        );
+    }
+
+    #[test]
+    fn json_errors_report_their_line() {
+        let path = temp_file("invalid.json", "[\n  {\"a\": 1},\n  {\"a\": }\n]\n");
+        let mut reader = RowReader::open(&path, &ImportFormat::Json).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let first = reader.next_row().unwrap().unwrap();
+        assert_eq!((first.line, first.values["a"].clone()), (2, 1.into()));
+        match reader.next_row() {
+            Err(TxError::Import(message)) => assert!(message.starts_with("line 3:"), "{message}"),
+            _ => panic!("the second row is invalid"),
+        }
+    }
+}
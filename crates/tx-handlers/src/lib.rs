@@ -6,19 +6,58 @@
 use home::home_dir;
 use tx_lib::{Result, TxError};
 
+mod data_diff;
+mod data_file;
 mod database;
+mod ddl;
+mod diff;
+mod dump;
+mod export;
+mod import;
+mod maintenance;
 mod mysql;
+mod parquet_writer;
 mod postgres;
+mod pragma;
 mod query;
 mod schema;
 mod sqlite;
+mod ssh;
+mod transfer;
+mod xlsx_writer;
 
-pub use database::DatabaseConnection;
+pub use data_diff::{ColumnValueDiff, DataDiff, RowDiff, RowDiffKind, diff_table_data};
+pub use data_file::{DataFile, open_data_file};
+pub use database::{DatabaseConnection, DatabaseTransaction};
+pub use ddl::{
+    ColumnDefinition, SchemaChange, TableDefinition, alter_table, create_table, drop_table,
+    rebuild_table,
+};
+pub use diff::{DiffKind, ItemDiff, SchemaDiff, TableDiff, diff_schemas, migration_changes};
+pub use dump::{
+    DumpOptions, DumpSummary, RestoreOptions, RestoreSummary, StatementError, dump_database,
+    restore_database,
+};
+pub use export::{CsvOptions, CsvQuoting, ExportFormat, ExportOptions, ExportSummary, export_rows};
+pub use import::{
+    ColumnMapping, CsvImportOptions, FileColumn, ImportFormat, ImportOptions, ImportPreview,
+    ImportRowError, ImportSummary, import_file, preview_import,
+};
+pub use maintenance::{
+    BackupSummary, CheckpointMode, IntegrityCheck, VacuumSummary, WalCheckpoint, analyze,
+    backup_database, integrity_check, vacuum, vacuum_into, wal_checkpoint,
+};
 pub use mysql::MySQLHandler;
 pub use postgres::PostgresHandler;
+pub use pragma::{Pragma, list_pragmas};
 pub use query::{DecodedRow, ExecResult, QueryResult, QueryResultRow};
-pub use schema::{ColumnInfo, CustomColumnType, CustomEnumDef, RowRecord, TableInfo};
+pub use schema::{
+    ColumnInfo, CustomColumnType, CustomEnumDef, ForeignKeyAction, ForeignKeyInfo, IndexInfo,
+    RowRecord, Schema, TableDiscoveryFailure, TableInfo,
+};
 pub use sqlite::SQLiteHandler;
+pub use ssh::Tunnel;
+pub use transfer::{TransferMode, TransferOptions, TransferSummary, transfer_table};
 
 /// Replaces homedir-relative paths `~` with the users home dir.
 fn _expand_conn_string(conn_string: &str) -> Result<String> {
@@ -37,14 +76,5 @@ fn _expand_conn_string(conn_string: &str) -> Result<String> {
 ///
 /// Typically used with `SELECT *`.
 pub fn decode_raw_rows(rows: Vec<QueryResult>) -> Result<Vec<DecodedRow>> {
-    let mut result = Vec::<DecodedRow>::new();
-
-    for row in rows {
-        match row.row {
-            QueryResultRow::SqlxMySql(my_sql_row) => result.push(my_sql_row.into()),
-            QueryResultRow::SqlxPostgres(pg_row) => result.push(pg_row.into()),
-            QueryResultRow::SqlxSqlite(sqlite_row) => result.push(sqlite_row.into()),
-        }
-    }
-    Ok(result)
+    Ok(rows.into_iter().map(DecodedRow::from).collect())
 }
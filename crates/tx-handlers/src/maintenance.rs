@@ -0,0 +1,364 @@
+//! Maintenance operations of SQLite databases.
+
+use crate::DatabaseConnection;
+use libsqlite3_sys::{
+    SQLITE_BUSY, SQLITE_DONE, SQLITE_LOCKED, SQLITE_OK, SQLITE_OPEN_CREATE, SQLITE_OPEN_READWRITE,
+    sqlite3, sqlite3_backup_finish, sqlite3_backup_init, sqlite3_backup_pagecount,
+    sqlite3_backup_remaining, sqlite3_backup_step, sqlite3_close, sqlite3_errmsg, sqlite3_open_v2,
+};
+use serde::{Deserialize, Serialize};
+use specta::Type;
+use sqlx::SqlitePool;
+use std::{
+    ffi::{CStr, CString},
+    ptr::{self, NonNull},
+    thread,
+    time::Duration,
+};
+use tx_lib::{Result, TxError};
+
+/// Number of pages copied by each step of a backup, the source database is only locked
+/// while a step runs.
+const PAGES_PER_STEP: i32 = 256;
+
+/// Time waited before retrying a backup step when the source database is locked.
+const BUSY_DELAY: Duration = Duration::from_millis(100);
+
+/// Number of retries of a backup step before giving up on a locked source database,
+/// about 30 seconds.
+const MAX_BUSY_RETRIES: u32 = 300;
+
+#[derive(Serialize, Deserialize, Type, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct VacuumSummary {
+    pub size_before: u64,
+    /// Size of the vacuumed database, or of its copy for `VACUUM INTO`.
+    pub size_after: u64,
+    pub bytes_reclaimed: u64,
+}
+
+#[derive(Serialize, Deserialize, Type, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct IntegrityCheck {
+    pub ok: bool,
+    /// Problems found in the database, empty when it's ok.
+    pub messages: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Type, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum CheckpointMode {
+    /// Checkpoint as many frames as possible without waiting for readers or writers.
+    Passive,
+    /// Wait for writers, then checkpoint every frame.
+    Full,
+    /// Like `Full`, then wait for readers so the next writer restarts the WAL file.
+    Restart,
+    /// Like `Restart`, then truncate the WAL file.
+    Truncate,
+}
+
+#[derive(Serialize, Deserialize, Type, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct WalCheckpoint {
+    /// The checkpoint couldn't complete because of another connection.
+    pub busy: bool,
+    /// Frames in the WAL file, `None` if the database isn't in WAL mode.
+    pub wal_frames: Option<i64>,
+    /// Frames copied back into the database, `None` if it isn't in WAL mode.
+    pub checkpointed_frames: Option<i64>,
+}
+
+#[derive(Serialize, Deserialize, Type, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct BackupSummary {
+    pub pages_copied: u64,
+    pub bytes_copied: u64,
+}
+
+fn sqlite_pool(conn: &DatabaseConnection) -> Result<&SqlitePool> {
+    match conn {
+        DatabaseConnection::Sqlite { pool, .. } => Ok(pool),
+        conn => Err(TxError::UnsupportedDriver(conn.driver().to_string())),
+    }
+}
+
+async fn database_size(pool: &SqlitePool) -> Result<u64> {
+    let page_count: i64 = sqlx::query_scalar("PRAGMA page_count")
+        .fetch_one(pool)
+        .await?;
+    let page_size: i64 = sqlx::query_scalar("PRAGMA page_size")
+        .fetch_one(pool)
+        .await?;
+    Ok((page_count * page_size) as u64)
+}
+
+/// Rebuild a SQLite database, releasing the space left by deleted rows.
+pub async fn vacuum(conn: &DatabaseConnection) -> Result<VacuumSummary> {
+    let pool = sqlite_pool(conn)?;
+    let size_before = database_size(pool).await?;
+    sqlx::query("VACUUM").execute(pool).await?;
+    let size_after = database_size(pool).await?;
+
+    Ok(VacuumSummary {
+        size_before,
+        size_after,
+        bytes_reclaimed: size_before.saturating_sub(size_after),
+    })
+}
+
+/// Write a vacuumed copy of a SQLite database to `path`, which must not exist.
+pub async fn vacuum_into(conn: &DatabaseConnection, path: &str) -> Result<VacuumSummary> {
+    let size_before = database_size(sqlite_pool(conn)?).await?;
+    conn.vacuum_into(path).await?;
+    let size_after = std::fs::metadata(path)?.len();
+
+    Ok(VacuumSummary {
+        size_before,
+        size_after,
+        bytes_reclaimed: size_before.saturating_sub(size_after),
+    })
+}
+
+/// Look for corruption in a SQLite database, `quick` skips the slower checks of indexes'
+/// contents.
+pub async fn integrity_check(conn: &DatabaseConnection, quick: bool) -> Result<IntegrityCheck> {
+    let pragma = match quick {
+        true => "PRAGMA quick_check",
+        false => "PRAGMA integrity_check",
+    };
+    let messages: Vec<String> = sqlx::query_scalar(pragma)
+        .fetch_all(sqlite_pool(conn)?)
+        .await?;
+    let ok = messages.len() == 1 && messages[0] == "ok";
+
+    Ok(IntegrityCheck {
+        ok,
+        messages: if ok { vec![] } else { messages },
+    })
+}
+
+/// Gather statistics about the tables and indexes of a SQLite database for the query
+/// planner.
+pub async fn analyze(conn: &DatabaseConnection) -> Result<()> {
+    sqlx::query("ANALYZE").execute(sqlite_pool(conn)?).await?;
+    Ok(())
+}
+
+/// Copy the frames of a SQLite database's WAL file back into the database.
+pub async fn wal_checkpoint(
+    conn: &DatabaseConnection,
+    mode: CheckpointMode,
+) -> Result<WalCheckpoint> {
+    let mode = match mode {
+        CheckpointMode::Passive => "PASSIVE",
+        CheckpointMode::Full => "FULL",
+        CheckpointMode::Restart => "RESTART",
+        CheckpointMode::Truncate => "TRUNCATE",
+    };
+    let (busy, wal_frames, checkpointed_frames): (i64, i64, i64) =
+        sqlx::query_as(&format!("PRAGMA wal_checkpoint({mode})"))
+            .fetch_one(sqlite_pool(conn)?)
+            .await?;
+
+    Ok(WalCheckpoint {
+        busy: busy != 0,
+        wal_frames: (wal_frames >= 0).then_some(wal_frames),
+        checkpointed_frames: (checkpointed_frames >= 0).then_some(checkpointed_frames),
+    })
+}
+
+/// Copy a SQLite database to `path` with the online backup API, the copy is consistent
+/// even if the database is written to meanwhile. `path` is overwritten if it's a database.
+///
+/// The backup runs on a blocking thread and fails if the database stays locked by other
+/// connections for too long. `on_progress` is called after each step with the number of
+/// pages copied so far and the number of pages in the database.
+pub async fn backup_database(
+    conn: &DatabaseConnection,
+    path: &str,
+    mut on_progress: impl FnMut(u64, u64),
+) -> Result<BackupSummary> {
+    let pool = sqlite_pool(conn)?;
+    let page_size: i64 = sqlx::query_scalar("PRAGMA page_size")
+        .fetch_one(pool)
+        .await?;
+    let mut source = pool.acquire().await?;
+    let path = path.to_string();
+    let runtime = tokio::runtime::Handle::current();
+    let (sender, mut receiver) = tokio::sync::mpsc::unbounded_channel();
+
+    let task = tokio::task::spawn_blocking(move || {
+        let mut handle = runtime.block_on(source.lock_handle())?;
+        backup(handle.as_raw_handle(), &path, &mut |copied, total| {
+            // The receiver is only dropped once the backup is done.
+            let _ = sender.send((copied, total));
+        })
+    });
+    while let Some((copied, total)) = receiver.recv().await {
+        on_progress(copied, total);
+    }
+    let pages_copied = task.await.map_err(|e| TxError::Backup(e.to_string()))??;
+
+    Ok(BackupSummary {
+        pages_copied,
+        bytes_copied: pages_copied * page_size as u64,
+    })
+}
+
+/// Destination database of a backup, closed when dropped.
+struct Destination(*mut sqlite3);
+
+impl Drop for Destination {
+    fn drop(&mut self) {
+        // SAFETY: the handle was returned by `sqlite3_open_v2` and is closed only once.
+        unsafe { sqlite3_close(self.0) };
+    }
+}
+
+impl Destination {
+    fn error(&self) -> TxError {
+        // SAFETY: the handle is open, its error message is copied before it's changed.
+        let message = unsafe { CStr::from_ptr(sqlite3_errmsg(self.0)) };
+        TxError::Backup(message.to_string_lossy().into_owned())
+    }
+}
+
+fn backup(
+    source: NonNull<sqlite3>,
+    path: &str,
+    on_progress: &mut impl FnMut(u64, u64),
+) -> Result<u64> {
+    let path = CString::new(path)
+        .map_err(|_| TxError::Backup("the path contains a nul character".to_string()))?;
+    let mut destination = Destination(ptr::null_mut());
+
+    // SAFETY: `source` stays locked for the duration of the backup, and the backup is
+    // finished before `destination` is closed.
+    unsafe {
+        let rc = sqlite3_open_v2(
+            path.as_ptr(),
+            &mut destination.0,
+            SQLITE_OPEN_READWRITE | SQLITE_OPEN_CREATE,
+            ptr::null(),
+        );
+        if rc != SQLITE_OK {
+            return Err(destination.error());
+        }
+        let backup = sqlite3_backup_init(
+            destination.0,
+            c"main".as_ptr(),
+            source.as_ptr(),
+            c"main".as_ptr(),
+        );
+        if backup.is_null() {
+            return Err(destination.error());
+        }
+
+        let mut busy_retries = 0;
+        let pages = loop {
+            let rc = sqlite3_backup_step(backup, PAGES_PER_STEP);
+            let pages = sqlite3_backup_pagecount(backup) as u64;
+            match rc {
+                SQLITE_OK => {
+                    busy_retries = 0;
+                    on_progress(pages - sqlite3_backup_remaining(backup) as u64, pages);
+                }
+                SQLITE_DONE => {
+                    on_progress(pages, pages);
+                    break Some(pages);
+                }
+                SQLITE_BUSY | SQLITE_LOCKED if busy_retries < MAX_BUSY_RETRIES => {
+                    busy_retries += 1;
+                    thread::sleep(BUSY_DELAY);
+                }
+                SQLITE_BUSY | SQLITE_LOCKED => break None,
+                _ => break Some(pages),
+            }
+        };
+        // Failed steps' errors are reported by `sqlite3_backup_finish`.
+        let rc = sqlite3_backup_finish(backup);
+        match pages {
+            None => Err(TxError::Backup(format!(
+                "the database stayed locked for more than {} seconds",
+                (BUSY_DELAY * MAX_BUSY_RETRIES).as_secs()
+            ))),
+            Some(_) if rc != SQLITE_OK => Err(destination.error()),
+            Some(pages) => Ok(pages),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tx_lib::{connection::ConnectOptions, types::Drivers};
+
+    #[tokio::test]
+    async fn databases_are_backed_up() {
+        let path = std::env::temp_dir()
+            .join(format!("tx-backup-{}.db", std::process::id()))
+            .to_string_lossy()
+            .into_owned();
+        let conn = DatabaseConnection::in_memory().await.unwrap();
+        conn.execute("CREATE TABLE t (id INTEGER PRIMARY KEY)")
+            .await
+            .unwrap();
+        conn.execute("INSERT INTO t VALUES (1), (2)").await.unwrap();
+
+        let mut progress = vec![];
+        let summary = backup_database(&conn, &path, |copied, total| progress.push((copied, total)))
+            .await
+            .unwrap();
+        assert_eq!(
+            progress.last(),
+            Some(&(summary.pages_copied, summary.pages_copied))
+        );
+
+        let copy = DatabaseConnection::in_memory().await.unwrap();
+        copy.execute(&format!("ATTACH '{path}' AS copy"))
+            .await
+            .unwrap();
+        let rows: i64 = sqlx::query_scalar("SELECT count(*) FROM copy.t")
+            .fetch_one(sqlite_pool(&copy).unwrap())
+            .await
+            .unwrap();
+        copy.close().await;
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(rows, 2);
+    }
+
+    #[tokio::test]
+    async fn read_only_databases_are_vacuumed_into_a_copy() {
+        let path = |name: &str| {
+            std::env::temp_dir()
+                .join(format!("tx-vacuum-{}-{name}.db", std::process::id()))
+                .to_string_lossy()
+                .into_owned()
+        };
+        let (source, copy) = (path("source"), path("copy"));
+        let _ = std::fs::remove_file(&source);
+        let url = format!("sqlite://{source}?mode=rwc");
+        let conn = DatabaseConnection::connect(&url, &Drivers::SQLite, &ConnectOptions::default())
+            .await
+            .unwrap();
+        conn.execute("CREATE TABLE t (id INTEGER PRIMARY KEY)")
+            .await
+            .unwrap();
+        conn.close().await;
+
+        let options = ConnectOptions {
+            read_only: true,
+            ..Default::default()
+        };
+        let conn = DatabaseConnection::connect(&url, &Drivers::SQLite, &options)
+            .await
+            .unwrap();
+        vacuum_into(&conn, &copy).await.unwrap();
+        let written = conn.execute("INSERT INTO t VALUES (1)").await;
+        conn.close().await;
+        std::fs::remove_file(&source).unwrap();
+        std::fs::remove_file(&copy).unwrap();
+        assert!(written.is_err());
+    }
+}
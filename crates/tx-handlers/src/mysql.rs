@@ -1,14 +1,26 @@
 use crate::{
     DecodedRow, ExecResult, QueryResult, QueryResultRow,
-    schema::{ColumnInfo, CustomColumnType, Schema, TableInfo, TablesNames},
+    schema::{
+        ColumnInfo, CustomColumnType, ForeignKeyAction, ForeignKeyInfo, IndexInfo, Schema,
+        TableDiscovery, TableInfo, TablesNames,
+    },
+};
+use rust_decimal::Decimal;
+use sea_query::{Expr, Iden, MysqlQueryBuilder};
+use sea_schema::mysql::{
+    def::{
+        ColumnDefault, ColumnKey, ForeignKeyAction as SeaForeignKeyAction, StringAttr,
+        Type as SeaColumnType,
+    },
+    discovery::SchemaDiscovery,
+    query::{SchemaQueryBuilder, TableQueryResult, TablesFields},
 };
-use sea_query::MysqlQueryBuilder;
-use sea_schema::mysql::def::{ColumnKey, Type as SeaColumnType};
 use serde_json::{Map as JsonMap, Value as JsonValue};
 use sqlx::{
     Column, Row, Value, ValueRef,
-    mysql::{MySqlQueryResult, MySqlRow},
+    mysql::{MySqlPool, MySqlQueryResult, MySqlRow},
 };
+use std::collections::HashMap;
 
 #[derive(Debug)]
 pub struct MySQLHandler;
@@ -17,13 +29,117 @@ impl MySQLHandler {
     pub fn new() -> Box<Self> {
         Box::new(MySQLHandler {})
     }
+
+    /// List the names of all tables without discovering their details.
+    pub(crate) async fn tables_names(pool: &MySqlPool) -> tx_lib::Result<TablesNames> {
+        let mut discovery = Self::schema_discovery(pool).await?;
+        Ok(discovery.discover_tables().await?.into())
+    }
+
+    /// Changes whenever the database schema changes.
+    ///
+    /// MySQL has no schema version counter, so this hashes the `information_schema` entries
+    /// describing the current database's tables, columns, indexes and foreign keys. Only
+    /// their definitions are included, e.g `UPDATE_TIME` also changes when rows are written.
+    pub(crate) async fn schema_fingerprint(pool: &MySqlPool) -> tx_lib::Result<String> {
+        // `GROUP_CONCAT` is truncated to 1024 bytes by default, so the entries' hashes
+        // are combined with `BIT_XOR` instead.
+        let fingerprint = sqlx::query_scalar(
+            r#"
+            SELECT CONCAT(
+                COUNT(*),
+                ':',
+                COALESCE(BIT_XOR(CAST(CONV(LEFT(MD5(entry), 16), 16, 10) AS UNSIGNED)), 0)
+            ) FROM (
+                SELECT CONCAT_WS(
+                    ':', TABLE_NAME, TABLE_TYPE, COALESCE(ENGINE, ''),
+                    COALESCE(TABLE_COLLATION, ''), COALESCE(CREATE_OPTIONS, ''), TABLE_COMMENT
+                ) AS entry
+                FROM information_schema.TABLES
+                WHERE TABLE_SCHEMA = DATABASE()
+                UNION ALL
+                SELECT CONCAT_WS(
+                    ':', TABLE_NAME, COLUMN_NAME, ORDINAL_POSITION, COLUMN_TYPE, IS_NULLABLE,
+                    COALESCE(COLUMN_DEFAULT, 'NULL'), EXTRA, COLUMN_KEY,
+                    COALESCE(COLLATION_NAME, ''), COLUMN_COMMENT
+                )
+                FROM information_schema.COLUMNS
+                WHERE TABLE_SCHEMA = DATABASE()
+                UNION ALL
+                SELECT CONCAT_WS(
+                    ':', TABLE_NAME, INDEX_NAME, SEQ_IN_INDEX, COALESCE(COLUMN_NAME, ''),
+                    NON_UNIQUE
+                )
+                FROM information_schema.STATISTICS
+                WHERE TABLE_SCHEMA = DATABASE()
+                UNION ALL
+                SELECT CONCAT_WS(
+                    ':', TABLE_NAME, CONSTRAINT_NAME, REFERENCED_TABLE_NAME, UPDATE_RULE,
+                    DELETE_RULE
+                )
+                FROM information_schema.REFERENTIAL_CONSTRAINTS
+                WHERE CONSTRAINT_SCHEMA = DATABASE()
+            ) AS catalog
+            "#,
+        )
+        .fetch_one(pool)
+        .await?;
+        Ok(fingerprint)
+    }
+
+    /// Discover the details of each table in `tables_names`, a failure
+    /// to discover one table doesn't affect the others.
+    pub(crate) async fn discover_tables(
+        pool: &MySqlPool,
+        tables_names: &[String],
+    ) -> tx_lib::Result<Vec<TableDiscovery>> {
+        let discovery = Self::schema_discovery(pool).await?;
+        // Only look up the requested tables instead of listing all of them.
+        let mut query = discovery.query.query_tables(discovery.schema.clone());
+        query.and_where(Expr::col(TablesFields::TableName).is_in(tables_names.iter().cloned()));
+        let mut existing: HashMap<String, _> = discovery
+            .executor
+            .fetch_all(query)
+            .await?
+            .iter()
+            .map(|row| TableQueryResult::from(row).parse())
+            .map(|info| (info.name.clone(), info))
+            .collect();
+
+        let mut result = Vec::with_capacity(tables_names.len());
+        for table_name in tables_names {
+            let Some(info) = existing.remove(table_name) else {
+                result.push(TableDiscovery::Missing);
+                continue;
+            };
+            result.push(match discovery.discover_table(info).await {
+                Ok(table) => TableDiscovery::Found((&table).into()),
+                Err(e) => TableDiscovery::Failed(e.to_string()),
+            });
+        }
+        Ok(result)
+    }
+
+    /// Discovery of the database selected by the connection's URL. Queries depend on the
+    /// server version, so it has to be discovered first.
+    async fn schema_discovery(pool: &MySqlPool) -> tx_lib::Result<SchemaDiscovery> {
+        let database: Option<String> = sqlx::query_scalar("SELECT DATABASE()")
+            .fetch_one(pool)
+            .await?;
+        let database = database.ok_or_else(|| {
+            tx_lib::TxError::SchemaDiscovery(
+                "no database is selected, add one to the URL".to_string(),
+            )
+        })?;
+        let mut discovery = SchemaDiscovery::new(pool.clone(), &database);
+        discovery.query = SchemaQueryBuilder::new(discovery.discover_system().await?);
+        Ok(discovery)
+    }
 }
 
 impl From<sea_schema::mysql::def::Schema> for Schema {
     fn from(value: sea_schema::mysql::def::Schema) -> Self {
-        Self {
-            tables: value.tables.iter().map(|t| t.into()).collect(),
-        }
+        value.tables.iter().map(TableInfo::from).collect()
     }
 }
 
@@ -34,16 +150,103 @@ impl From<&sea_schema::mysql::def::TableDef> for TableInfo {
             columns: value
                 .columns
                 .iter()
-                .map(|c| ColumnInfo {
-                    auto_generated: c.expression.is_some(),
-                    name: c.name.clone(),
-                    nullable: c.null,
-                    pk: c.key == ColumnKey::Primary,
-                    r#type: c.col_type.clone().into(),
+                .map(|c| {
+                    let (numeric_precision, numeric_scale) = numeric_precision_scale(&c.col_type);
+                    ColumnInfo {
+                        auto_generated: c.expression.is_some(),
+                        name: c.name.clone(),
+                        nullable: c.null,
+                        pk: c.key == ColumnKey::Primary,
+                        r#type: c.col_type.clone().into(),
+                        default_value: c.default.as_ref().map(default_value),
+                        max_length: max_length(&c.col_type),
+                        numeric_precision,
+                        numeric_scale,
+                        collation: string_attr(&c.col_type)
+                            .and_then(|attr| attr.collation.as_ref())
+                            .map(|collation| collation.to_string()),
+                        comment: (!c.comment.is_empty()).then(|| c.comment.clone()),
+                    }
                 })
                 .collect(),
             create_statement: value.write().to_string(MysqlQueryBuilder),
+            indexes: value
+                .indexes
+                .iter()
+                .filter(|index| index.name != "PRIMARY")
+                .map(|index| IndexInfo {
+                    name: index.name.clone(),
+                    columns: index.parts.iter().map(|part| part.column.clone()).collect(),
+                    unique: index.unique,
+                })
+                .collect(),
+            foreign_keys: value
+                .foreign_keys
+                .iter()
+                .map(|fk| ForeignKeyInfo {
+                    name: Some(fk.name.clone()),
+                    columns: fk.columns.clone(),
+                    referenced_table: fk.referenced_table.clone(),
+                    referenced_columns: fk.referenced_columns.clone(),
+                    on_update: fk_action(&fk.on_update),
+                    on_delete: fk_action(&fk.on_delete),
+                })
+                .collect(),
+        }
+    }
+}
+
+fn fk_action(action: &SeaForeignKeyAction) -> ForeignKeyAction {
+    match action {
+        SeaForeignKeyAction::Cascade => ForeignKeyAction::Cascade,
+        SeaForeignKeyAction::SetNull => ForeignKeyAction::SetNull,
+        SeaForeignKeyAction::SetDefault => ForeignKeyAction::SetDefault,
+        SeaForeignKeyAction::Restrict => ForeignKeyAction::Restrict,
+        SeaForeignKeyAction::NoAction => ForeignKeyAction::NoAction,
+    }
+}
+
+fn default_value(default: &ColumnDefault) -> String {
+    match default {
+        ColumnDefault::Null => "NULL".to_string(),
+        ColumnDefault::Int(v) => v.to_string(),
+        ColumnDefault::Real(v) => v.to_string(),
+        ColumnDefault::String(v) | ColumnDefault::CustomExpr(v) => v.clone(),
+        ColumnDefault::CurrentTimestamp => "CURRENT_TIMESTAMP".to_string(),
+    }
+}
+
+fn string_attr(col_type: &SeaColumnType) -> Option<&StringAttr> {
+    match col_type {
+        SeaColumnType::Char(attr)
+        | SeaColumnType::NChar(attr)
+        | SeaColumnType::Varchar(attr)
+        | SeaColumnType::NVarchar(attr)
+        | SeaColumnType::Binary(attr)
+        | SeaColumnType::Varbinary(attr)
+        | SeaColumnType::Text(attr)
+        | SeaColumnType::TinyText(attr)
+        | SeaColumnType::MediumText(attr)
+        | SeaColumnType::LongText(attr) => Some(attr),
+        SeaColumnType::Enum(def) => Some(&def.attr),
+        SeaColumnType::Set(def) => Some(&def.attr),
+        _ => None,
+    }
+}
+
+fn max_length(col_type: &SeaColumnType) -> Option<u32> {
+    match col_type {
+        SeaColumnType::Blob(attr) => attr.length,
+        t => string_attr(t).and_then(|attr| attr.length),
+    }
+}
+
+fn numeric_precision_scale(col_type: &SeaColumnType) -> (Option<u32>, Option<u32>) {
+    match col_type {
+        SeaColumnType::Decimal(attr) | SeaColumnType::Float(attr) | SeaColumnType::Double(attr) => {
+            (attr.maximum, attr.decimal)
         }
+        _ => (None, None),
     }
 }
 
@@ -79,18 +282,18 @@ impl From<SeaColumnType> for CustomColumnType {
             | SeaColumnType::TinyBlob
             | SeaColumnType::MediumBlob
             | SeaColumnType::LongBlob => CustomColumnType::Binary,
-            SeaColumnType::Enum(_) => todo!(),
-            SeaColumnType::Set(_) => todo!(),
-            SeaColumnType::Geometry(_) => todo!(),
-            SeaColumnType::Point(_) => todo!(),
-            SeaColumnType::LineString(_) => todo!(),
-            SeaColumnType::Polygon(_) => todo!(),
-            SeaColumnType::MultiPoint(_) => todo!(),
-            SeaColumnType::MultiLineString(_) => todo!(),
-            SeaColumnType::MultiPolygon(_) => todo!(),
-            SeaColumnType::GeometryCollection(_) => todo!(),
+            SeaColumnType::Enum(_) => CustomColumnType::UnSupported,
+            SeaColumnType::Set(_) => CustomColumnType::UnSupported,
+            SeaColumnType::Geometry(_) => CustomColumnType::UnSupported,
+            SeaColumnType::Point(_) => CustomColumnType::UnSupported,
+            SeaColumnType::LineString(_) => CustomColumnType::UnSupported,
+            SeaColumnType::Polygon(_) => CustomColumnType::UnSupported,
+            SeaColumnType::MultiPoint(_) => CustomColumnType::UnSupported,
+            SeaColumnType::MultiLineString(_) => CustomColumnType::UnSupported,
+            SeaColumnType::MultiPolygon(_) => CustomColumnType::UnSupported,
+            SeaColumnType::GeometryCollection(_) => CustomColumnType::UnSupported,
             SeaColumnType::Json => CustomColumnType::Json,
-            SeaColumnType::Unknown(_) => todo!(),
+            SeaColumnType::Unknown(_) => CustomColumnType::UnSupported,
         }
     }
 }
@@ -140,6 +343,10 @@ impl From<MySqlRow> for DecodedRow {
                     JsonValue::Number(v.decode::<u64>().into())
                 }
                 "BOOLEAN" => JsonValue::Bool(v.decode::<bool>()),
+                // Kept as text so they don't lose precision.
+                "DECIMAL" => v
+                    .try_decode::<Decimal>()
+                    .map_or(JsonValue::Null, |d| JsonValue::String(d.to_string())),
                 _ => JsonValue::Null,
             };
 
@@ -0,0 +1,257 @@
+//! Parquet files written one row group at a time, with each column's values typed.
+
+use crate::{
+    export::{Cell, ExportColumn},
+    schema::CustomColumnType,
+};
+use chrono::{DateTime, Timelike};
+use parquet::{
+    basic::{Compression, ConvertedType, Repetition, Type as PhysicalType},
+    data_type::{
+        BoolType, ByteArray, ByteArrayType, DoubleType, FixedLenByteArray, FixedLenByteArrayType,
+        Int32Type, Int64Type,
+    },
+    errors::ParquetError,
+    file::{properties::WriterProperties, writer::SerializedFileWriter},
+    schema::types::Type,
+};
+use serde_json::Value as JsonValue;
+use std::{fs::File, sync::Arc};
+use tx_lib::{Result, TxError};
+
+/// Number of rows buffered before they're written as a row group.
+const ROW_GROUP_SIZE: usize = 65_536;
+
+/// Largest precision of decimals stored in 64 bits integers, wider ones use 16 bytes.
+const INT64_DECIMAL_PRECISION: u32 = 18;
+
+pub(crate) struct ParquetWriter {
+    writer: SerializedFileWriter<File>,
+    columns: Vec<ColumnBuffer>,
+    rows: usize,
+}
+
+/// Buffered values of a column, `levels` tells which rows aren't `NULL`.
+struct ColumnBuffer {
+    values: Values,
+    levels: Vec<i16>,
+    /// Scale of decimal columns, which are stored as unscaled integers.
+    scale: Option<u32>,
+}
+
+enum Values {
+    Boolean(Vec<bool>),
+    Int32(Vec<i32>),
+    Int64(Vec<i64>),
+    Double(Vec<f64>),
+    FixedLen(Vec<FixedLenByteArray>),
+    Bytes(Vec<ByteArray>),
+}
+
+impl ParquetWriter {
+    pub(crate) fn new(out: File, columns: &[ExportColumn]) -> Result<Self> {
+        let fields = columns
+            .iter()
+            .map(|c| column_schema(c).map(Arc::new))
+            .collect::<std::result::Result<Vec<_>, _>>()
+            .map_err(parquet_error)?;
+        let schema = Type::group_type_builder("schema")
+            .with_fields(fields)
+            .build()
+            .map_err(parquet_error)?;
+        let properties = WriterProperties::builder()
+            .set_compression(Compression::SNAPPY)
+            .build();
+        let writer = SerializedFileWriter::new(out, Arc::new(schema), Arc::new(properties))
+            .map_err(parquet_error)?;
+
+        Ok(Self {
+            writer,
+            columns: columns.iter().map(ColumnBuffer::new).collect(),
+            rows: 0,
+        })
+    }
+
+    pub(crate) fn write_row(
+        &mut self,
+        columns: &[ExportColumn],
+        values: &[JsonValue],
+    ) -> Result<()> {
+        for ((column, buffer), value) in columns.iter().zip(&mut self.columns).zip(values) {
+            buffer.push(column.cell(value)?)?;
+        }
+        self.rows += 1;
+        if self.rows == ROW_GROUP_SIZE {
+            self.flush()?;
+        }
+        Ok(())
+    }
+
+    pub(crate) fn finish(mut self) -> Result<()> {
+        if self.rows > 0 {
+            self.flush()?;
+        }
+        self.writer.close().map_err(parquet_error)?;
+        Ok(())
+    }
+
+    /// Write the buffered rows as a row group.
+    fn flush(&mut self) -> Result<()> {
+        let mut row_group = self.writer.next_row_group().map_err(parquet_error)?;
+        for buffer in &mut self.columns {
+            let Some(mut column) = row_group.next_column().map_err(parquet_error)? else {
+                break;
+            };
+            let levels = Some(buffer.levels.as_slice());
+            match &buffer.values {
+                Values::Boolean(values) => {
+                    column.typed::<BoolType>().write_batch(values, levels, None)
+                }
+                Values::Int32(values) => column
+                    .typed::<Int32Type>()
+                    .write_batch(values, levels, None),
+                Values::Int64(values) => column
+                    .typed::<Int64Type>()
+                    .write_batch(values, levels, None),
+                Values::Double(values) => column
+                    .typed::<DoubleType>()
+                    .write_batch(values, levels, None),
+                Values::FixedLen(values) => column
+                    .typed::<FixedLenByteArrayType>()
+                    .write_batch(values, levels, None),
+                Values::Bytes(values) => column
+                    .typed::<ByteArrayType>()
+                    .write_batch(values, levels, None),
+            }
+            .map_err(parquet_error)?;
+            column.close().map_err(parquet_error)?;
+            buffer.clear();
+        }
+        row_group.close().map_err(parquet_error)?;
+        self.rows = 0;
+        Ok(())
+    }
+}
+
+impl ColumnBuffer {
+    fn new(column: &ExportColumn) -> Self {
+        let values = match (&column.r#type, column.decimal) {
+            (CustomColumnType::Boolean, _) => Values::Boolean(vec![]),
+            (CustomColumnType::Date, _) => Values::Int32(vec![]),
+            (CustomColumnType::Float, Some((precision, _)))
+                if precision > INT64_DECIMAL_PRECISION =>
+            {
+                Values::FixedLen(vec![])
+            }
+            (CustomColumnType::Float, Some(_))
+            | (
+                CustomColumnType::Integer
+                | CustomColumnType::PositiveInteger
+                | CustomColumnType::Year
+                | CustomColumnType::DateTime
+                | CustomColumnType::Time,
+                _,
+            ) => Values::Int64(vec![]),
+            (CustomColumnType::Float, None) => Values::Double(vec![]),
+            _ => Values::Bytes(vec![]),
+        };
+        Self {
+            values,
+            levels: vec![],
+            scale: column.decimal.map(|(_, scale)| scale),
+        }
+    }
+
+    fn push(&mut self, cell: Option<Cell>) -> Result<()> {
+        let Some(cell) = cell else {
+            self.levels.push(0);
+            return Ok(());
+        };
+        match (&mut self.values, cell) {
+            (Values::Boolean(values), Cell::Boolean(b)) => values.push(b),
+            (Values::Int32(values), Cell::Date(date)) => values.push(
+                date.signed_duration_since(DateTime::UNIX_EPOCH.date_naive())
+                    .num_days() as i32,
+            ),
+            (Values::Int64(values), Cell::Integer(i)) => values.push(i),
+            // Unsigned integers are stored with the same bits.
+            (Values::Int64(values), Cell::PositiveInteger(u)) => values.push(u as i64),
+            (Values::Int64(values), Cell::DateTime(date_time)) => {
+                values.push(date_time.and_utc().timestamp_micros())
+            }
+            (Values::Int64(values), Cell::Time(time)) => values.push(
+                time.num_seconds_from_midnight() as i64 * 1_000_000
+                    + time.nanosecond() as i64 / 1_000,
+            ),
+            (Values::Int64(values), Cell::Decimal(mut decimal)) => {
+                decimal.rescale(self.scale.unwrap_or_default());
+                values.push(decimal.mantissa() as i64)
+            }
+            (Values::FixedLen(values), Cell::Decimal(mut decimal)) => {
+                decimal.rescale(self.scale.unwrap_or_default());
+                values.push(decimal.mantissa().to_be_bytes().to_vec().into())
+            }
+            (Values::Double(values), Cell::Float(f)) => values.push(f),
+            (Values::Bytes(values), Cell::Binary(bytes)) => values.push(bytes.into()),
+            (Values::Bytes(values), Cell::Text(text)) => values.push(text.into_bytes().into()),
+            _ => {
+                return Err(TxError::Export(
+                    "a value doesn't match its column's type".to_string(),
+                ));
+            }
+        }
+        self.levels.push(1);
+        Ok(())
+    }
+
+    fn clear(&mut self) {
+        self.levels.clear();
+        match &mut self.values {
+            Values::Boolean(values) => values.clear(),
+            Values::Int32(values) => values.clear(),
+            Values::Int64(values) => values.clear(),
+            Values::Double(values) => values.clear(),
+            Values::FixedLen(values) => values.clear(),
+            Values::Bytes(values) => values.clear(),
+        }
+    }
+}
+
+/// Schema of an optional column, annotated with the logical type of its values.
+fn column_schema(column: &ExportColumn) -> std::result::Result<Type, ParquetError> {
+    let (physical_type, converted_type) = match (&column.r#type, column.decimal) {
+        (CustomColumnType::Boolean, _) => (PhysicalType::BOOLEAN, ConvertedType::NONE),
+        (CustomColumnType::Integer | CustomColumnType::Year, _) => {
+            (PhysicalType::INT64, ConvertedType::NONE)
+        }
+        (CustomColumnType::PositiveInteger, _) => (PhysicalType::INT64, ConvertedType::UINT_64),
+        (CustomColumnType::Float, Some((precision, _))) if precision > INT64_DECIMAL_PRECISION => {
+            (PhysicalType::FIXED_LEN_BYTE_ARRAY, ConvertedType::DECIMAL)
+        }
+        (CustomColumnType::Float, Some(_)) => (PhysicalType::INT64, ConvertedType::DECIMAL),
+        (CustomColumnType::Float, None) => (PhysicalType::DOUBLE, ConvertedType::NONE),
+        (CustomColumnType::Date, _) => (PhysicalType::INT32, ConvertedType::DATE),
+        (CustomColumnType::DateTime, _) => (PhysicalType::INT64, ConvertedType::TIMESTAMP_MICROS),
+        (CustomColumnType::Time, _) => (PhysicalType::INT64, ConvertedType::TIME_MICROS),
+        (CustomColumnType::Binary, _) => (PhysicalType::BYTE_ARRAY, ConvertedType::NONE),
+        (CustomColumnType::Json, _) => (PhysicalType::BYTE_ARRAY, ConvertedType::JSON),
+        _ => (PhysicalType::BYTE_ARRAY, ConvertedType::UTF8),
+    };
+
+    let mut builder = Type::primitive_type_builder(&column.name, physical_type)
+        .with_repetition(Repetition::OPTIONAL)
+        .with_converted_type(converted_type);
+    if let Some((precision, scale)) = column.decimal {
+        builder = builder
+            .with_precision(precision as i32)
+            .with_scale(scale as i32);
+        if physical_type == PhysicalType::FIXED_LEN_BYTE_ARRAY {
+            builder = builder.with_length(16);
+        }
+    }
+    builder.build()
+}
+
+fn parquet_error(e: ParquetError) -> TxError {
+    TxError::Export(e.to_string())
+}
@@ -1,32 +1,200 @@
 use crate::{
     query::{DecodedRow, ExecResult, QueryResult, QueryResultRow},
-    schema::{ColumnInfo, CustomColumnType, CustomEnumDef, Schema, TableInfo, TablesNames},
+    schema::{
+        ColumnInfo, CustomColumnType, CustomEnumDef, ForeignKeyAction, ForeignKeyInfo, IndexInfo,
+        Schema, TableDiscovery, TableInfo, TablesNames,
+    },
 };
-use sea_query::PostgresQueryBuilder;
-use sea_schema::postgres::def::Type as SeaColumnType;
+use rust_decimal::Decimal;
+use sea_query::{Expr, PostgresQueryBuilder};
+use sea_schema::postgres::def::{ForeignKeyAction as SeaForeignKeyAction, Type as SeaColumnType};
+use sea_schema::postgres::discovery::SchemaDiscovery;
+use sea_schema::postgres::query::{TableQueryResult, TablesFields};
 use sea_schema::postgres::writer;
 use serde_json::{Map as JsonMap, Value as JsonValue};
 use sqlx::{
     Column, Postgres, Row, Type, Value, ValueRef,
     decode::Decode,
-    postgres::{PgQueryResult, PgRow, PgTypeInfo, PgTypeKind, PgValueRef},
+    postgres::{PgPool, PgQueryResult, PgRow, PgTypeInfo, PgTypeKind, PgValueFormat, PgValueRef},
     types::chrono::{NaiveDate, NaiveDateTime, NaiveTime},
 };
+use std::collections::HashMap;
 
 #[derive(Debug)]
 pub struct PostgresHandler;
 
+/// The postgres schema tablex discovers tables from.
+const DEFAULT_SCHEMA: &str = "public";
+
 impl PostgresHandler {
     pub fn new() -> Box<Self> {
         Box::new(PostgresHandler {})
     }
+
+    /// List the names of all tables without discovering their details.
+    pub(crate) async fn tables_names(pool: &PgPool) -> tx_lib::Result<TablesNames> {
+        let tables = SchemaDiscovery::new(pool.clone(), DEFAULT_SCHEMA)
+            .discover_tables()
+            .await?;
+        Ok(tables.into())
+    }
+
+    /// Changes whenever the database schema changes.
+    ///
+    /// Postgres has no schema version counter, so this hashes the row versions (`xmin`)
+    /// of the catalog entries describing the schema's relations, columns and constraints.
+    pub(crate) async fn schema_fingerprint(pool: &PgPool) -> tx_lib::Result<String> {
+        let fingerprint = sqlx::query_scalar(
+            r#"
+            SELECT md5(COALESCE(string_agg(entry, ',' ORDER BY entry), '')) FROM (
+                SELECT c.oid::text || ':' || c.xmin::text AS entry
+                FROM pg_class c
+                JOIN pg_namespace n ON n.oid = c.relnamespace
+                WHERE n.nspname = $1
+                UNION ALL
+                SELECT a.attrelid::text || '.' || a.attnum::text || ':' || a.xmin::text
+                FROM pg_attribute a
+                JOIN pg_class c ON c.oid = a.attrelid
+                JOIN pg_namespace n ON n.oid = c.relnamespace
+                WHERE n.nspname = $1
+                UNION ALL
+                SELECT co.oid::text || ':' || co.xmin::text
+                FROM pg_constraint co
+                JOIN pg_namespace n ON n.oid = co.connamespace
+                WHERE n.nspname = $1
+            ) AS catalog
+            "#,
+        )
+        .bind(DEFAULT_SCHEMA)
+        .fetch_one(pool)
+        .await?;
+        Ok(fingerprint)
+    }
+
+    /// Discover the details of each table in `tables_names`, a failure
+    /// to discover one table doesn't affect the others.
+    pub(crate) async fn discover_tables(
+        pool: &PgPool,
+        tables_names: &[String],
+    ) -> tx_lib::Result<Vec<TableDiscovery>> {
+        let discovery = SchemaDiscovery::new(pool.clone(), DEFAULT_SCHEMA);
+        // Only look up the requested tables instead of listing all of them.
+        let mut query = discovery.query.query_tables(discovery.schema.clone());
+        query.and_where(Expr::col(TablesFields::TableName).is_in(tables_names.iter().cloned()));
+        let mut existing: HashMap<String, _> = discovery
+            .executor
+            .fetch_all(query)
+            .await?
+            .iter()
+            .map(|row| TableQueryResult::from(row).parse())
+            .map(|info| (info.name.clone(), info))
+            .collect();
+        let enums: HashMap<String, Vec<String>> = discovery
+            .discover_enums()
+            .await?
+            .into_iter()
+            .map(|def| (def.typename, def.values))
+            .collect();
+
+        let mut result = Vec::with_capacity(tables_names.len());
+        for table_name in tables_names {
+            let Some(info) = existing.remove(table_name) else {
+                result.push(TableDiscovery::Missing);
+                continue;
+            };
+            let table = match discovery.discover_table(info, &enums).await {
+                Ok(table) => table,
+                Err(e) => {
+                    result.push(TableDiscovery::Failed(e.to_string()));
+                    continue;
+                }
+            };
+            let mut info = TableInfo::from(&table);
+            result.push(match Self::indexes(pool, table_name).await {
+                Ok(indexes) => {
+                    info.indexes = indexes;
+                    TableDiscovery::Found(info)
+                }
+                Err(e) => TableDiscovery::Failed(e.to_string()),
+            });
+        }
+        Ok(result)
+    }
+
+    /// Indexes of `table_name`, excluding the primary key.
+    ///
+    /// Not covered by sea-schema's discovery.
+    async fn indexes(pool: &PgPool, table_name: &str) -> sqlx::Result<Vec<IndexInfo>> {
+        let rows: Vec<(String, bool, Vec<String>)> = sqlx::query_as(
+            r#"
+            SELECT i.relname::text, ix.indisunique, array_agg(a.attname::text ORDER BY k.ord)
+            FROM pg_index ix
+            JOIN pg_class t ON t.oid = ix.indrelid
+            JOIN pg_class i ON i.oid = ix.indexrelid
+            JOIN pg_namespace n ON n.oid = t.relnamespace
+            CROSS JOIN LATERAL unnest(ix.indkey) WITH ORDINALITY AS k(attnum, ord)
+            JOIN pg_attribute a ON a.attrelid = t.oid AND a.attnum = k.attnum
+            WHERE n.nspname = $1 AND t.relname = $2 AND NOT ix.indisprimary
+            GROUP BY i.relname, ix.indisunique
+            ORDER BY i.relname
+            "#,
+        )
+        .bind(DEFAULT_SCHEMA)
+        .bind(table_name)
+        .fetch_all(pool)
+        .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|(name, unique, columns)| IndexInfo {
+                name,
+                columns,
+                unique,
+            })
+            .collect())
+    }
+
+    /// Sequences of the schema, including the ones generating `serial` columns.
+    pub(crate) async fn sequences(pool: &PgPool) -> sqlx::Result<Vec<SequenceInfo>> {
+        sqlx::query_as(
+            r#"
+            SELECT s.sequencename::text AS name, s.start_value, s.increment_by, s.min_value,
+                s.max_value, s.cycle, s.last_value,
+                EXISTS (
+                    SELECT 1 FROM pg_depend d
+                    WHERE d.classid = 'pg_class'::regclass AND d.objid = c.oid
+                        AND d.deptype IN ('a', 'i')
+                ) AS owned
+            FROM pg_sequences s
+            JOIN pg_namespace n ON n.nspname = s.schemaname
+            JOIN pg_class c ON c.relname = s.sequencename AND c.relnamespace = n.oid
+            WHERE s.schemaname = $1
+            ORDER BY s.sequencename
+            "#,
+        )
+        .bind(DEFAULT_SCHEMA)
+        .fetch_all(pool)
+        .await
+    }
+}
+
+#[derive(sqlx::FromRow)]
+pub(crate) struct SequenceInfo {
+    pub(crate) name: String,
+    pub(crate) start_value: i64,
+    pub(crate) increment_by: i64,
+    pub(crate) min_value: i64,
+    pub(crate) max_value: i64,
+    pub(crate) cycle: bool,
+    /// `None` until the sequence is first used.
+    pub(crate) last_value: Option<i64>,
+    /// Whether the sequence belongs to a column, which creates it.
+    pub(crate) owned: bool,
 }
 
 impl From<sea_schema::postgres::def::Schema> for Schema {
     fn from(value: sea_schema::postgres::def::Schema) -> Self {
-        Self {
-            tables: value.tables.iter().map(|t| t.into()).collect(),
-        }
+        value.tables.iter().map(TableInfo::from).collect()
     }
 }
 
@@ -38,25 +206,75 @@ impl From<&sea_schema::postgres::def::TableDef> for TableInfo {
             columns: value
                 .columns
                 .iter()
-                .map(|c| ColumnInfo {
-                    auto_generated: c.generated.is_some()
-                        || c.default
-                            .as_ref()
-                            .is_some_and(|exp| exp.0.starts_with("nextval")),
-                    name: c.name.clone(),
-                    nullable: c.not_null.is_none(),
-                    pk: value
-                        .primary_key_constraints
-                        .iter()
-                        .any(|pk| pk.columns.contains(&c.name)),
-                    r#type: c.col_type.clone().into(),
+                .map(|c| {
+                    let (numeric_precision, numeric_scale) = numeric_precision_scale(&c.col_type);
+                    ColumnInfo {
+                        auto_generated: c.generated.is_some()
+                            || c.default
+                                .as_ref()
+                                .is_some_and(|exp| exp.0.starts_with("nextval")),
+                        name: c.name.clone(),
+                        nullable: c.not_null.is_none(),
+                        pk: value
+                            .primary_key_constraints
+                            .iter()
+                            .any(|pk| pk.columns.contains(&c.name)),
+                        r#type: c.col_type.clone().into(),
+                        default_value: c.default.as_ref().map(|exp| exp.0.clone()),
+                        max_length: max_length(&c.col_type),
+                        numeric_precision,
+                        numeric_scale,
+                        // Postgres discovery doesn't report these.
+                        collation: None,
+                        comment: None,
+                    }
                 })
                 .collect(),
             create_statement: value.write().to_string(PostgresQueryBuilder),
+            indexes: vec![],
+            foreign_keys: value
+                .reference_constraints
+                .iter()
+                .map(|fk| ForeignKeyInfo {
+                    name: Some(fk.name.clone()),
+                    columns: fk.columns.clone(),
+                    referenced_table: fk.table.clone(),
+                    referenced_columns: fk.foreign_columns.clone(),
+                    on_update: fk.on_update.as_ref().map(fk_action).unwrap_or_default(),
+                    on_delete: fk.on_delete.as_ref().map(fk_action).unwrap_or_default(),
+                })
+                .collect(),
         }
     }
 }
 
+fn fk_action(action: &SeaForeignKeyAction) -> ForeignKeyAction {
+    match action {
+        SeaForeignKeyAction::Cascade => ForeignKeyAction::Cascade,
+        SeaForeignKeyAction::SetNull => ForeignKeyAction::SetNull,
+        SeaForeignKeyAction::SetDefault => ForeignKeyAction::SetDefault,
+        SeaForeignKeyAction::Restrict => ForeignKeyAction::Restrict,
+        SeaForeignKeyAction::NoAction => ForeignKeyAction::NoAction,
+    }
+}
+
+fn max_length(col_type: &SeaColumnType) -> Option<u32> {
+    match col_type {
+        SeaColumnType::Varchar(attr) | SeaColumnType::Char(attr) => attr.length.map(u32::from),
+        SeaColumnType::Bit(attr) | SeaColumnType::VarBit(attr) => attr.length.map(u32::from),
+        _ => None,
+    }
+}
+
+fn numeric_precision_scale(col_type: &SeaColumnType) -> (Option<u32>, Option<u32>) {
+    match col_type {
+        SeaColumnType::Decimal(attr) | SeaColumnType::Numeric(attr) => {
+            (attr.precision.map(u32::from), attr.scale.map(u32::from))
+        }
+        _ => (None, None),
+    }
+}
+
 impl From<SeaColumnType> for CustomColumnType {
     fn from(value: SeaColumnType) -> Self {
         match value {
@@ -160,6 +378,60 @@ impl Type<Postgres> for DynamicEnum {
     }
 }
 
+/// Text of a `NUMERIC` value in Postgres' binary format, for values that don't fit a
+/// [`Decimal`]. Its digits are stored in base 10000, the first one multiplied by
+/// 10000^`weight`.
+fn numeric_text(bytes: &[u8]) -> Option<String> {
+    let word = |i: usize| {
+        bytes
+            .get(i * 2..i * 2 + 2)
+            .map(|b| u16::from_be_bytes([b[0], b[1]]))
+    };
+    let digits_count = word(0)? as usize;
+    let weight = word(1)? as i16 as i64;
+    let sign = word(2)?;
+    let scale = word(3)? as usize;
+    let digits: Vec<u16> = (0..digits_count)
+        .map(|i| word(4 + i))
+        .collect::<Option<_>>()?;
+    let digit = |i: i64| {
+        usize::try_from(i)
+            .ok()
+            .and_then(|i| digits.get(i).copied())
+            .unwrap_or(0)
+    };
+
+    let mut text = match sign {
+        0xC000 => return Some("NaN".to_string()),
+        0xD000 => return Some("Infinity".to_string()),
+        0xF000 => return Some("-Infinity".to_string()),
+        0x4000 => "-".to_string(),
+        _ => String::new(),
+    };
+    if weight < 0 {
+        text.push('0');
+    }
+    for i in 0..=weight {
+        if i == 0 {
+            text.push_str(&digit(i).to_string());
+        } else {
+            text.push_str(&format!("{:04}", digit(i)));
+        }
+    }
+    if scale > 0 {
+        let mut fraction = String::new();
+        let mut i = weight + 1;
+        while fraction.len() < scale {
+            fraction.push_str(&format!("{:04}", digit(i)));
+            i += 1;
+        }
+        fraction.truncate(scale);
+        text.push('.');
+        text.push_str(&fraction);
+    }
+    Some(text)
+}
+
 impl From<PgRow> for DecodedRow {
     fn from(value: PgRow) -> Self {
         let mut row_data = JsonMap::default();
@@ -189,6 +461,16 @@ impl From<PgRow> for DecodedRow {
                 "INT4" => JsonValue::Number(v.decode::<i32>().into()),
                 "INT8" => JsonValue::Number(v.decode::<i64>().into()),
                 "BOOL" => JsonValue::Bool(v.decode::<bool>()),
+                // Kept as text so they don't lose precision.
+                "NUMERIC" => v
+                    .try_decode::<Decimal>()
+                    .map(|d| d.to_string())
+                    .ok()
+                    .or_else(|| match value_ref.format() {
+                        PgValueFormat::Text => value_ref.as_str().ok().map(str::to_string),
+                        PgValueFormat::Binary => numeric_text(value_ref.as_bytes().ok()?),
+                    })
+                    .map_or(JsonValue::Null, JsonValue::String),
                 "BYTEA" => JsonValue::Array(
                     v.decode::<Vec<u8>>()
                         .into_iter()
@@ -217,3 +499,37 @@ impl From<PgQueryResult> for ExecResult {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn numeric(weight: i16, sign: u16, scale: u16, digits: &[u16]) -> Vec<u8> {
+        [digits.len() as u16, weight as u16, sign, scale]
+            .iter()
+            .chain(digits)
+            .flat_map(|word| word.to_be_bytes())
+            .collect()
+    }
+
+    #[test]
+    fn wide_numerics_are_read_as_text() {
+        // 123456789012345678901234567890123456.25
+        let wide = numeric(
+            8,
+            0,
+            2,
+            &[1234, 5678, 9012, 3456, 7890, 1234, 5678, 9012, 3456, 2500],
+        );
+        assert_eq!(
+            numeric_text(&wide).unwrap(),
+            "123456789012345678901234567890123456.25"
+        );
+        assert_eq!(
+            numeric_text(&numeric(-2, 0x4000, 6, &[1200])).unwrap(),
+            "-0.000012"
+        );
+        assert_eq!(numeric_text(&numeric(1, 0, 0, &[7])).unwrap(), "70000");
+        assert_eq!(numeric_text(&numeric(0, 0xC000, 0, &[])).unwrap(), "NaN");
+    }
+}
@@ -0,0 +1,153 @@
+//! SQLite pragmas, overridden per saved connection and listed for inspection.
+
+use crate::{DatabaseConnection, DecodedRow};
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use specta::Type;
+use sqlx::sqlite::{SqliteConnectOptions, SqliteJournalMode, SqliteSynchronous};
+use std::time::Duration;
+use tx_lib::{
+    Result, TxError,
+    types::{JournalMode, SqlitePragmas, Synchronous},
+};
+
+/// Pragmas listed by [`list_pragmas`], those that can be overridden first.
+const PRAGMAS: &[&str] = &[
+    "foreign_keys",
+    "journal_mode",
+    "busy_timeout",
+    "synchronous",
+    "cache_size",
+    "auto_vacuum",
+    "encoding",
+    "locking_mode",
+    "temp_store",
+    "page_size",
+    "page_count",
+    "freelist_count",
+    "wal_autocheckpoint",
+    "mmap_size",
+    "recursive_triggers",
+    "secure_delete",
+    "user_version",
+    "application_id",
+];
+
+#[derive(Serialize, Deserialize, Type, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct Pragma {
+    pub name: String,
+    /// Value as returned by SQLite, e.g `1` for enabled flags.
+    pub value: JsonValue,
+}
+
+/// Current values of the pragmas of a SQLite connection.
+pub async fn list_pragmas(conn: &DatabaseConnection) -> Result<Vec<Pragma>> {
+    let DatabaseConnection::Sqlite { pool, .. } = conn else {
+        return Err(TxError::UnsupportedDriver(conn.driver().to_string()));
+    };
+    let mut pragmas = Vec::with_capacity(PRAGMAS.len());
+    for name in PRAGMAS {
+        let row = sqlx::query(&format!("PRAGMA {name}"))
+            .fetch_optional(pool)
+            .await?;
+        let value = row
+            .and_then(|row| DecodedRow::from(row).0.into_iter().next())
+            .map_or(JsonValue::Null, |(_, value)| value);
+        pragmas.push(Pragma {
+            name: name.to_string(),
+            value,
+        });
+    }
+    Ok(pragmas)
+}
+
+/// Override the options' pragmas with those set in `pragmas`.
+pub(crate) fn apply_pragmas(
+    mut options: SqliteConnectOptions,
+    pragmas: &SqlitePragmas,
+) -> SqliteConnectOptions {
+    if let Some(enabled) = pragmas.foreign_keys {
+        options = options.foreign_keys(enabled);
+    }
+    if let Some(mode) = pragmas.journal_mode {
+        options = options.journal_mode(match mode {
+            JournalMode::Delete => SqliteJournalMode::Delete,
+            JournalMode::Truncate => SqliteJournalMode::Truncate,
+            JournalMode::Persist => SqliteJournalMode::Persist,
+            JournalMode::Memory => SqliteJournalMode::Memory,
+            JournalMode::Wal => SqliteJournalMode::Wal,
+            JournalMode::Off => SqliteJournalMode::Off,
+        });
+    }
+    if let Some(timeout) = pragmas.busy_timeout {
+        options = options.busy_timeout(Duration::from_millis(timeout.into()));
+    }
+    if let Some(synchronous) = pragmas.synchronous {
+        options = options.synchronous(match synchronous {
+            Synchronous::Off => SqliteSynchronous::Off,
+            Synchronous::Normal => SqliteSynchronous::Normal,
+            Synchronous::Full => SqliteSynchronous::Full,
+            Synchronous::Extra => SqliteSynchronous::Extra,
+        });
+    }
+    if let Some(size) = pragmas.cache_size {
+        options = options.pragma("cache_size", size.to_string());
+    }
+    options
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tx_lib::{
+        connection::ConnectOptions,
+        types::{Drivers, SqliteOptions},
+    };
+
+    #[tokio::test]
+    async fn overridden_pragmas_are_applied() {
+        let path = std::env::temp_dir()
+            .join(format!("tx-pragmas-{}.db", std::process::id()))
+            .to_string_lossy()
+            .into_owned();
+        let sqlite = SqliteOptions {
+            pragmas: SqlitePragmas {
+                foreign_keys: Some(false),
+                journal_mode: Some(JournalMode::Wal),
+                busy_timeout: Some(1234),
+                synchronous: Some(Synchronous::Off),
+                cache_size: Some(-4000),
+            },
+            ..Default::default()
+        };
+        let conn = DatabaseConnection::connect(
+            &format!("sqlite://{path}?mode=rwc"),
+            &Drivers::SQLite,
+            &ConnectOptions {
+                sqlite,
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+        let pragmas = list_pragmas(&conn).await.unwrap();
+        conn.close().await;
+        for suffix in ["", "-wal", "-shm"] {
+            let _ = std::fs::remove_file(format!("{path}{suffix}"));
+        }
+
+        let value = |name: &str| {
+            pragmas
+                .iter()
+                .find(|pragma| pragma.name == name)
+                .map(|pragma| pragma.value.clone())
+                .unwrap()
+        };
+        assert_eq!(value("foreign_keys"), JsonValue::from(0));
+        assert_eq!(value("journal_mode"), JsonValue::from("wal"));
+        assert_eq!(value("busy_timeout"), JsonValue::from(1234));
+        assert_eq!(value("synchronous"), JsonValue::from(0));
+        assert_eq!(value("cache_size"), JsonValue::from(-4000));
+    }
+}
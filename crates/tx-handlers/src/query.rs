@@ -1,12 +1,134 @@
 use serde::{Deserialize, Serialize};
 use serde_json::{Map as JsonMap, Value as JsonValue};
 use specta::Type;
-use sqlx::{mysql::MySqlRow, postgres::PgRow, sqlite::SqliteRow};
+use sqlx::{
+    Column, ColumnIndex, Decode, Row, TypeInfo, ValueRef, mysql::MySqlRow, postgres::PgRow,
+    sqlite::SqliteRow,
+};
+use std::collections::HashMap;
+use tx_lib::{Result, TxError};
+
+/// Names the drivers give to the types of binary values.
+const BINARY_TYPES: [&str; 8] = [
+    "BLOB",
+    "BYTEA",
+    "BINARY",
+    "VARBINARY",
+    "TINYBLOB",
+    "MEDIUMBLOB",
+    "LONGBLOB",
+    "GEOMETRY",
+];
 
 pub struct QueryResult {
     pub row: QueryResultRow,
 }
 
+impl QueryResult {
+    /// Names of the row's columns, in the order they were selected.
+    pub fn columns(&self) -> Vec<String> {
+        match &self.row {
+            QueryResultRow::SqlxMySql(row) => column_names(row.columns()),
+            QueryResultRow::SqlxPostgres(row) => column_names(row.columns()),
+            QueryResultRow::SqlxSqlite(row) => column_names(row.columns()),
+        }
+    }
+    /// Names the driver gives to the types of the row's columns, e.g `INT8`.
+    pub fn column_type_names(&self) -> Vec<String> {
+        match &self.row {
+            QueryResultRow::SqlxMySql(row) => type_names(row.columns()),
+            QueryResultRow::SqlxPostgres(row) => type_names(row.columns()),
+            QueryResultRow::SqlxSqlite(row) => type_names(row.columns()),
+        }
+    }
+    /// Values of the row keyed by column name, to write them back e.g to another database.
+    ///
+    /// Unlike a [`DecodedRow`] binary values are kept as bytes, and values of types that
+    /// can't be decoded are an error instead of `NULL` so they aren't silently lost.
+    pub(crate) fn values(self) -> Result<HashMap<String, RowValue>> {
+        let raw = match &self.row {
+            QueryResultRow::SqlxMySql(row) => raw_values(row)?,
+            QueryResultRow::SqlxPostgres(row) => raw_values(row)?,
+            QueryResultRow::SqlxSqlite(row) => raw_values(row)?,
+        };
+        let mut decoded = DecodedRow::from(self).0;
+        raw.into_iter()
+            .map(|(name, raw)| {
+                let value = match raw {
+                    RawValue::Bytes(bytes) => RowValue::Bytes(bytes),
+                    RawValue::Null => RowValue::Decoded(JsonValue::Null),
+                    // JSON values may be a JSON `null`.
+                    RawValue::Other(type_name) => match decoded.remove(&name) {
+                        Some(JsonValue::Null) | None if !type_name.starts_with("JSON") => {
+                            return Err(TxError::UnsupportedDataType(format!(
+                                "{type_name} of column `{name}`"
+                            )));
+                        }
+                        value => RowValue::Decoded(value.unwrap_or_default()),
+                    },
+                };
+                Ok((name, value))
+            })
+            .collect()
+    }
+}
+
+/// A value read by [`QueryResult::values`].
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum RowValue {
+    /// Decoded the way a [`DecodedRow`]'s values are.
+    Decoded(JsonValue),
+    Bytes(Vec<u8>),
+}
+
+impl Default for RowValue {
+    fn default() -> Self {
+        RowValue::Decoded(JsonValue::Null)
+    }
+}
+
+enum RawValue {
+    Null,
+    Bytes(Vec<u8>),
+    /// Value of a non binary type, named by the driver.
+    Other(String),
+}
+
+fn raw_values<R>(row: &R) -> Result<Vec<(String, RawValue)>>
+where
+    R: Row,
+    usize: ColumnIndex<R>,
+    for<'r> Vec<u8>: Decode<'r, R::Database>,
+{
+    let mut values = Vec::with_capacity(row.columns().len());
+    for (i, column) in row.columns().iter().enumerate() {
+        let value_ref = row.try_get_raw(i)?;
+        let value = if value_ref.is_null() {
+            RawValue::Null
+        } else {
+            let type_name = value_ref.type_info().name().to_string();
+            if BINARY_TYPES.contains(&type_name.as_str()) {
+                RawValue::Bytes(row.try_get_unchecked(i)?)
+            } else {
+                RawValue::Other(type_name)
+            }
+        };
+        values.push((column.name().to_string(), value));
+    }
+    Ok(values)
+}
+
+fn column_names(columns: &[impl Column]) -> Vec<String> {
+    columns.iter().map(|c| c.name().to_string()).collect()
+}
+
+fn type_names(columns: &[impl Column]) -> Vec<String> {
+    columns
+        .iter()
+        .map(|c| c.type_info().name().to_string())
+        .collect()
+}
+
 #[allow(clippy::enum_variant_names)]
 pub enum QueryResultRow {
     SqlxMySql(MySqlRow),
@@ -21,6 +143,16 @@ pub enum QueryResultRow {
 /// Result is a map whose key is the column name and value is the column value in the row.
 pub struct DecodedRow(pub(crate) JsonMap<String, JsonValue>);
 
+impl From<QueryResult> for DecodedRow {
+    fn from(value: QueryResult) -> Self {
+        match value.row {
+            QueryResultRow::SqlxMySql(my_sql_row) => my_sql_row.into(),
+            QueryResultRow::SqlxPostgres(pg_row) => pg_row.into(),
+            QueryResultRow::SqlxSqlite(sqlite_row) => sqlite_row.into(),
+        }
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize, Type, Default)]
 pub struct ExecResult {
     pub(crate) rows_affected: u64,
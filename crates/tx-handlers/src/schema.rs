@@ -1,12 +1,118 @@
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, NaiveDate, NaiveDateTime, NaiveTime, Utc};
 use serde::{Deserialize, Serialize};
 use serde_json::Value as JsonValue;
 use specta::Type;
+use std::collections::HashMap;
+use tx_lib::{Result, TxError};
 use uuid::Uuid;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+/// Lazily discovered database schema.
+///
+/// Only the tables names are listed when connecting, each table's details are
+/// discovered on demand and cached here.
 pub struct Schema {
-    pub tables: Vec<TableInfo>,
+    pub tables_names: Vec<String>,
+    /// Identifies the version of the database schema this was listed from,
+    /// `None` if it's unknown.
+    pub fingerprint: Option<String>,
+    discovered: HashMap<String, TableInfo>,
+    #[serde(skip)]
+    failures: HashMap<String, String>,
+}
+
+/// Outcome of discovering a single table.
+pub(crate) enum TableDiscovery {
+    Found(TableInfo),
+    /// The table doesn't exist (anymore).
+    Missing,
+    Failed(String),
+}
+
+#[derive(Serialize, Deserialize, Type, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+/// A table whose details couldn't be discovered.
+pub struct TableDiscoveryFailure {
+    pub table_name: String,
+    pub reason: String,
+}
+
+impl Schema {
+    pub fn new(tables_names: Vec<String>) -> Self {
+        Self {
+            tables_names,
+            ..Default::default()
+        }
+    }
+
+    /// Tables discovered so far, in the same order as `tables_names`.
+    pub fn tables(&self) -> Vec<TableInfo> {
+        self.tables_names
+            .iter()
+            .filter_map(|name| self.discovered.get(name).cloned())
+            .collect()
+    }
+
+    pub fn table(&self, table_name: &str) -> Option<&TableInfo> {
+        self.discovered.get(table_name)
+    }
+
+    /// Tables that were neither discovered nor failed to be discovered yet.
+    pub fn undiscovered(&self) -> Vec<String> {
+        self.tables_names
+            .iter()
+            .filter(|name| {
+                !self.discovered.contains_key(*name) && !self.failures.contains_key(*name)
+            })
+            .cloned()
+            .collect()
+    }
+
+    pub fn failures(&self) -> Vec<TableDiscoveryFailure> {
+        self.tables_names
+            .iter()
+            .filter_map(|name| {
+                self.failures.get(name).map(|reason| TableDiscoveryFailure {
+                    table_name: name.clone(),
+                    reason: reason.clone(),
+                })
+            })
+            .collect()
+    }
+
+    /// Cache the outcome of discovering `table_name`.
+    pub(crate) fn record(&mut self, table_name: &str, discovery: TableDiscovery) {
+        self.discovered.remove(table_name);
+        self.failures.remove(table_name);
+
+        match discovery {
+            TableDiscovery::Found(table) => {
+                self.discovered.insert(table_name.to_string(), table);
+            }
+            TableDiscovery::Failed(reason) => {
+                self.failures.insert(table_name.to_string(), reason);
+            }
+            TableDiscovery::Missing => {
+                self.tables_names.retain(|name| name != table_name);
+                return;
+            }
+        }
+
+        if !self.tables_names.iter().any(|name| name == table_name) {
+            self.tables_names.push(table_name.to_string());
+        }
+    }
+}
+
+impl FromIterator<TableInfo> for Schema {
+    fn from_iter<I: IntoIterator<Item = TableInfo>>(iter: I) -> Self {
+        let mut schema = Schema::default();
+        for table in iter {
+            let name = table.name.clone();
+            schema.record(&name, TableDiscovery::Found(table));
+        }
+        schema
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize, Type, Clone)]
@@ -14,6 +120,64 @@ pub struct TableInfo {
     pub name: String,
     pub columns: Vec<ColumnInfo>,
     pub create_statement: String,
+    /// Secondary indexes, the primary key isn't included.
+    pub indexes: Vec<IndexInfo>,
+    pub foreign_keys: Vec<ForeignKeyInfo>,
+}
+
+#[derive(Serialize, Deserialize, Type, Clone, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct IndexInfo {
+    pub name: String,
+    pub columns: Vec<String>,
+    pub unique: bool,
+}
+
+#[derive(Serialize, Deserialize, Type, Clone, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct ForeignKeyInfo {
+    /// Constraint name, SQLite foreign keys are unnamed.
+    pub name: Option<String>,
+    pub columns: Vec<String>,
+    pub referenced_table: String,
+    pub referenced_columns: Vec<String>,
+    pub on_update: ForeignKeyAction,
+    pub on_delete: ForeignKeyAction,
+}
+
+#[derive(Serialize, Deserialize, Default, Type, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum ForeignKeyAction {
+    #[default]
+    NoAction,
+    Restrict,
+    Cascade,
+    SetNull,
+    SetDefault,
+}
+
+impl From<ForeignKeyAction> for sea_query::ForeignKeyAction {
+    fn from(value: ForeignKeyAction) -> Self {
+        match value {
+            ForeignKeyAction::NoAction => sea_query::ForeignKeyAction::NoAction,
+            ForeignKeyAction::Restrict => sea_query::ForeignKeyAction::Restrict,
+            ForeignKeyAction::Cascade => sea_query::ForeignKeyAction::Cascade,
+            ForeignKeyAction::SetNull => sea_query::ForeignKeyAction::SetNull,
+            ForeignKeyAction::SetDefault => sea_query::ForeignKeyAction::SetDefault,
+        }
+    }
+}
+
+impl From<&str> for ForeignKeyAction {
+    fn from(value: &str) -> Self {
+        match value.to_uppercase().as_str() {
+            "RESTRICT" => ForeignKeyAction::Restrict,
+            "CASCADE" => ForeignKeyAction::Cascade,
+            "SET NULL" => ForeignKeyAction::SetNull,
+            "SET DEFAULT" => ForeignKeyAction::SetDefault,
+            _ => ForeignKeyAction::NoAction,
+        }
+    }
 }
 
 #[derive(Serialize, Deserialize, Type, Clone, Debug)]
@@ -24,6 +188,16 @@ pub struct ColumnInfo {
     pub nullable: bool,
     pub pk: bool,
     pub r#type: CustomColumnType,
+    /// The default value expression as reported by the database, e.g `CURRENT_TIMESTAMP`.
+    pub default_value: Option<String>,
+    /// Maximum length for character and binary columns.
+    pub max_length: Option<u32>,
+    /// Total number of digits for numeric columns.
+    pub numeric_precision: Option<u32>,
+    /// Number of digits after the decimal point for numeric columns.
+    pub numeric_scale: Option<u32>,
+    pub collation: Option<String>,
+    pub comment: Option<String>,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -71,14 +245,31 @@ pub struct RowRecord {
     pub column_type: CustomColumnType,
 }
 
-impl From<RowRecord> for sea_query::Value {
-    fn from(value: RowRecord) -> Self {
-        match value.value {
-            JsonValue::Null => match value.column_type {
+impl RowRecord {
+    /// Convert the value to the column's type, failing on values that don't fit it.
+    ///
+    /// Strings are parsed for non text columns, e.g when they come from a CSV file
+    /// or from a database storing dates as text.
+    pub fn try_into_value(self) -> Result<sea_query::Value> {
+        let RowRecord {
+            column_name,
+            value,
+            column_type,
+        } = self;
+        let invalid = |kind: &str| {
+            TxError::InvalidValue(format!(
+                "{value} isn't a valid {kind} for column `{column_name}`"
+            ))
+        };
+
+        let converted = match (&value, &column_type) {
+            (JsonValue::Null, column_type) => match column_type {
                 CustomColumnType::String
                 | CustomColumnType::Text
                 | CustomColumnType::Year
-                | CustomColumnType::Enum(_) => sea_query::Value::String(None),
+                | CustomColumnType::Enum(_)
+                | CustomColumnType::Custom
+                | CustomColumnType::UnSupported => sea_query::Value::String(None),
                 CustomColumnType::Uuid => sea_query::Value::Uuid(None),
                 CustomColumnType::Float => sea_query::Value::Double(None),
                 CustomColumnType::PositiveInteger => sea_query::Value::BigInt(None),
@@ -89,54 +280,176 @@ impl From<RowRecord> for sea_query::Value {
                 CustomColumnType::Time => sea_query::Value::ChronoTime(None),
                 CustomColumnType::Json => sea_query::Value::Json(None),
                 CustomColumnType::Binary => sea_query::Value::Bytes(None),
-                CustomColumnType::Custom => todo!(),
-                CustomColumnType::UnSupported => todo!(),
             },
-            JsonValue::Bool(v) => sea_query::Value::Bool(Some(v)),
-            JsonValue::Number(number) => {
-                if number.is_f64() {
-                    sea_query::Value::Double(number.as_f64())
-                } else if number.is_i64() {
-                    sea_query::Value::BigInt(number.as_i64())
-                } else if number.is_u64() {
-                    sea_query::Value::BigUnsigned(number.as_u64())
+            (JsonValue::Bool(v), _) => sea_query::Value::Bool(Some(*v)),
+            // e.g SQLite stores booleans as integers.
+            (JsonValue::Number(number), CustomColumnType::Boolean) => {
+                sea_query::Value::Bool(Some(number.as_f64() != Some(0.0)))
+            }
+            (
+                JsonValue::Number(number),
+                CustomColumnType::String | CustomColumnType::Text | CustomColumnType::Enum(_),
+            ) => sea_query::Value::String(Some(Box::new(number.to_string()))),
+            (JsonValue::Number(number), _) => {
+                if let Some(v) = number.as_i64() {
+                    sea_query::Value::BigInt(Some(v))
+                } else if let Some(v) = number.as_u64() {
+                    sea_query::Value::BigUnsigned(Some(v))
                 } else {
-                    todo!()
+                    sea_query::Value::Double(number.as_f64())
                 }
             }
-            JsonValue::String(v) => match value.column_type {
+            (JsonValue::String(v), column_type) => match column_type {
                 CustomColumnType::String
                 | CustomColumnType::Text
                 | CustomColumnType::Year
-                | CustomColumnType::Enum(_) => sea_query::Value::String(Some(Box::new(v))),
-                CustomColumnType::Uuid => {
-                    sea_query::Value::Uuid(Some(Box::new(Uuid::parse_str(v.as_str()).unwrap())))
-                }
-                CustomColumnType::Date => {
-                    let date = v.parse::<DateTime<Utc>>().unwrap();
-                    sea_query::Value::ChronoDate(Some(Box::new(date.date_naive())))
+                | CustomColumnType::Enum(_)
+                | CustomColumnType::Custom
+                | CustomColumnType::UnSupported => {
+                    sea_query::Value::String(Some(Box::new(v.clone())))
                 }
-                CustomColumnType::DateTime => {
-                    let date = v.parse::<DateTime<Utc>>().unwrap();
-                    sea_query::Value::ChronoDateTime(Some(Box::new(date.naive_utc())))
+                CustomColumnType::Uuid => sea_query::Value::Uuid(Some(Box::new(
+                    Uuid::parse_str(v).map_err(|_| invalid("UUID"))?,
+                ))),
+                CustomColumnType::Integer => sea_query::Value::BigInt(Some(
+                    v.trim().parse().map_err(|_| invalid("integer"))?,
+                )),
+                CustomColumnType::PositiveInteger => sea_query::Value::BigUnsigned(Some(
+                    v.trim().parse().map_err(|_| invalid("positive integer"))?,
+                )),
+                CustomColumnType::Float => {
+                    sea_query::Value::Double(Some(v.trim().parse().map_err(|_| invalid("number"))?))
                 }
-                CustomColumnType::Time => {
-                    let date = v.parse::<DateTime<Utc>>().unwrap();
-                    sea_query::Value::ChronoTime(Some(Box::new(date.time())))
+                CustomColumnType::Boolean => {
+                    sea_query::Value::Bool(Some(match v.trim().to_lowercase().as_str() {
+                        "true" | "t" | "yes" | "y" | "1" => true,
+                        "false" | "f" | "no" | "n" | "0" => false,
+                        _ => return Err(invalid("boolean")),
+                    }))
                 }
+                CustomColumnType::Date => sea_query::Value::ChronoDate(Some(Box::new(
+                    parse_date(v).ok_or_else(|| invalid("date"))?,
+                ))),
+                CustomColumnType::DateTime => sea_query::Value::ChronoDateTime(Some(Box::new(
+                    parse_datetime(v).ok_or_else(|| invalid("date time"))?,
+                ))),
+                CustomColumnType::Time => sea_query::Value::ChronoTime(Some(Box::new(
+                    parse_time(v).ok_or_else(|| invalid("time"))?,
+                ))),
                 CustomColumnType::Json => sea_query::Value::Json(Some(Box::new(
-                    serde_json::from_str(v.as_str()).unwrap(),
+                    serde_json::from_str(v).map_err(|_| invalid("JSON"))?,
                 ))),
-
-                t => {
-                    dbg!(&t);
-                    unimplemented!()
+                CustomColumnType::Binary => {
+                    sea_query::Value::Bytes(Some(Box::new(v.clone().into_bytes())))
                 }
             },
-            JsonValue::Array(_) => todo!(),
-            JsonValue::Object(map) => {
-                sea_query::Value::Json(Some(Box::new(JsonValue::Object(map))))
+            (JsonValue::Array(_) | JsonValue::Object(_), _) => {
+                sea_query::Value::Json(Some(Box::new(value.clone())))
             }
+        };
+        Ok(converted)
+    }
+}
+
+impl TryFrom<RowRecord> for sea_query::Value {
+    type Error = TxError;
+
+    fn try_from(value: RowRecord) -> Result<Self> {
+        value.try_into_value()
+    }
+}
+
+pub(crate) fn parse_datetime(value: &str) -> Option<NaiveDateTime> {
+    let value = value.trim();
+    value
+        .parse::<DateTime<Utc>>()
+        .map(|date| date.naive_utc())
+        .ok()
+        .or_else(|| NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M:%S%.f").ok())
+        .or_else(|| NaiveDateTime::parse_from_str(value, "%Y-%m-%dT%H:%M:%S%.f").ok())
+        .or_else(|| {
+            NaiveDate::parse_from_str(value, "%Y-%m-%d")
+                .ok()
+                .and_then(|date| date.and_hms_opt(0, 0, 0))
+        })
+}
+
+pub(crate) fn parse_date(value: &str) -> Option<NaiveDate> {
+    let value = value.trim();
+    NaiveDate::parse_from_str(value, "%Y-%m-%d")
+        .ok()
+        .or_else(|| parse_datetime(value).map(|date| date.date()))
+}
+
+pub(crate) fn parse_time(value: &str) -> Option<NaiveTime> {
+    let value = value.trim();
+    NaiveTime::parse_from_str(value, "%H:%M:%S%.f")
+        .ok()
+        .or_else(|| NaiveTime::parse_from_str(value, "%H:%M").ok())
+        .or_else(|| value.parse::<DateTime<Utc>>().map(|date| date.time()).ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn table(name: &str) -> TableInfo {
+        TableInfo {
+            name: name.to_string(),
+            columns: vec![],
+            create_statement: String::new(),
+            indexes: vec![],
+            foreign_keys: vec![],
         }
     }
+
+    #[test]
+    fn record_tracks_each_table_discovery() {
+        let mut schema = Schema::new(vec!["a".into(), "b".into(), "c".into()]);
+        schema.record("a", TableDiscovery::Found(table("a")));
+        schema.record("b", TableDiscovery::Failed("boom".into()));
+        assert_eq!(schema.undiscovered(), vec!["c"]);
+        assert_eq!(schema.tables().len(), 1);
+        assert_eq!(schema.failures()[0].table_name, "b");
+
+        schema.record("c", TableDiscovery::Missing);
+        assert_eq!(schema.tables_names, vec!["a", "b"]);
+
+        schema.record("b", TableDiscovery::Found(table("b")));
+        assert!(schema.failures().is_empty());
+
+        schema.record("d", TableDiscovery::Found(table("d")));
+        assert_eq!(schema.tables_names, vec!["a", "b", "d"]);
+    }
+
+    #[test]
+    fn cached_schema_forgets_failures() {
+        let mut schema = Schema::new(vec!["a".into(), "b".into()]);
+        schema.fingerprint = Some("42".into());
+        schema.record("a", TableDiscovery::Found(table("a")));
+        schema.record("b", TableDiscovery::Failed("boom".into()));
+
+        let cached: Schema =
+            serde_json::from_str(&serde_json::to_string(&schema).unwrap()).unwrap();
+        assert_eq!(cached.fingerprint.as_deref(), Some("42"));
+        assert_eq!(cached.tables().len(), 1);
+        assert_eq!(cached.undiscovered(), vec!["b"]);
+    }
+
+    #[test]
+    fn invalid_values_are_rejected() {
+        let record = |value: JsonValue| RowRecord {
+            column_name: "id".to_string(),
+            value,
+            column_type: CustomColumnType::Integer,
+        };
+        assert_eq!(
+            sea_query::Value::try_from(record(" 42 ".into())).unwrap(),
+            sea_query::Value::BigInt(Some(42))
+        );
+        assert!(matches!(
+            sea_query::Value::try_from(record("forty two".into())),
+            Err(TxError::InvalidValue(_))
+        ));
+    }
 }
@@ -1,14 +1,45 @@
 use crate::{
     DecodedRow, ExecResult, QueryResult, QueryResultRow,
-    schema::{ColumnInfo, CustomColumnType, Schema, TableInfo, TablesNames},
+    ddl::{self, TableDefinition, quoted},
+    pragma::apply_pragmas,
+    schema::{
+        ColumnInfo, CustomColumnType, ForeignKeyInfo, IndexInfo, Schema, TableDiscovery, TableInfo,
+        TablesNames,
+    },
+};
+use libsqlite3_sys::{
+    SQLITE_DBCONFIG_ENABLE_LOAD_EXTENSION, SQLITE_OK, SQLITE_OPEN_READWRITE, sqlite3_close,
+    sqlite3_db_config, sqlite3_free, sqlite3_load_extension, sqlite3_open_v2,
+};
+use sea_query::{SqliteQueryBuilder, StringLen};
+use sea_schema::{
+    sea_query::ColumnType as SeaColumnType,
+    sqlite::{
+        def::{DefaultType, TableDef},
+        discovery::SchemaDiscovery,
+    },
 };
-use sea_query::SqliteQueryBuilder;
-use sea_schema::{sea_query::ColumnType as SeaColumnType, sqlite::def::TableDef};
 use serde_json::{Map as JsonMap, Value as JsonValue};
 use sqlx::{
-    Column, Row, Value, ValueRef,
-    sqlite::{SqliteQueryResult, SqliteRow},
+    Column, ConnectOptions, Connection, Row, Value, ValueRef,
+    sqlite::{
+        SqliteConnectOptions, SqliteConnection, SqlitePool, SqlitePoolOptions, SqliteQueryResult,
+        SqliteRow,
+    },
+};
+use std::{
+    collections::{HashMap, HashSet},
+    ffi::{CStr, CString, c_int},
+    path::Path,
+    ptr,
 };
+use tx_lib::{
+    Result, TxError,
+    types::{AttachedDatabase, SecretString, SqliteExtension, SqliteOptions},
+};
+
+/// Result code of files that aren't databases, or are encrypted with another key.
+const SQLITE_NOTADB: &str = "26";
 
 #[derive(Debug)]
 pub struct SQLiteHandler;
@@ -17,13 +48,608 @@ impl SQLiteHandler {
     pub fn new() -> Box<Self> {
         Box::new(SQLiteHandler {})
     }
+
+    /// List the names of all tables without discovering their details, those of attached
+    /// databases are qualified by their alias.
+    pub(crate) async fn tables_names(pool: &SqlitePool) -> Result<TablesNames> {
+        let rows = sqlx::query(
+            "SELECT name FROM sqlite_master WHERE type = 'table' AND name <> 'sqlite_sequence'",
+        )
+        .fetch_all(pool)
+        .await?;
+        let mut names: TablesNames = rows.iter().map(TableDef::from).collect::<Vec<_>>().into();
+
+        for (alias, _) in Self::attached_databases(pool).await? {
+            let tables: Vec<String> = sqlx::query_scalar(&format!(
+                "SELECT name FROM {}.sqlite_master WHERE type = 'table' AND name <> 'sqlite_sequence'",
+                quoted(&SqliteQueryBuilder, &alias)
+            ))
+            .fetch_all(pool)
+            .await?;
+            names
+                .0
+                .extend(tables.into_iter().map(|table| format!("{alias}.{table}")));
+        }
+        Ok(names)
+    }
+
+    /// Changes whenever the database schema changes, or a database is attached.
+    pub(crate) async fn schema_fingerprint(pool: &SqlitePool) -> Result<String> {
+        let version: i64 = sqlx::query_scalar("PRAGMA schema_version")
+            .fetch_one(pool)
+            .await?;
+        let mut fingerprint = version.to_string();
+
+        for (alias, _) in Self::attached_databases(pool).await? {
+            let version: i64 = sqlx::query_scalar(&format!(
+                "PRAGMA {}.schema_version",
+                quoted(&SqliteQueryBuilder, &alias)
+            ))
+            .fetch_one(pool)
+            .await?;
+            fingerprint.push_str(&format!(";{alias}:{version}"));
+        }
+        Ok(fingerprint)
+    }
+
+    /// Connect to the database at `url` with the pragmas, extensions, attached databases and
+    /// key of `sqlite`.
+    pub(crate) async fn connect(
+        url: &str,
+        sqlite: &SqliteOptions,
+        read_only: bool,
+    ) -> Result<SqlitePool> {
+        let mut options = apply_pragmas(url.parse::<SqliteConnectOptions>()?, &sqlite.pragmas);
+        if let Some(key) = &sqlite.key {
+            options = Self::apply_key(options, key);
+        }
+        if read_only {
+            // Unlike opening the file read-only, it covers the attached databases too.
+            options = options.pragma("query_only", "ON");
+        }
+        let options = Self::load_extensions(options, &sqlite.extensions)?;
+        let pool_options = Self::pool_options(&sqlite.attached_databases, sqlite.key.as_ref())?;
+
+        // The pool retries connections failing to attach databases until it times out, so
+        // a first connection is checked on its own to report why.
+        let mut conn = options.connect().await?;
+        let checked = async {
+            // Encrypted databases aren't read until the first query, which fails if the
+            // key is wrong.
+            sqlx::query("SELECT count(*) FROM sqlite_master")
+                .execute(&mut conn)
+                .await
+                .map_err(|e| match e {
+                    sqlx::Error::Database(e) if e.code().as_deref() == Some(SQLITE_NOTADB) => {
+                        TxError::DatabaseKey(match sqlite.key {
+                            Some(_) => "the key is wrong, or the file isn't a SQLite database".to_string(),
+                            None => "the file is encrypted or isn't a SQLite database, set its key if it's encrypted".to_string(),
+                        })
+                    }
+                    e => e.into(),
+                })?;
+            for database in &sqlite.attached_databases {
+                Self::attach(&mut conn, database, sqlite.key.as_ref())
+                    .await
+                    .map_err(|e| TxError::AttachDatabase(format!("`{}`: {e}", database.path)))?;
+            }
+            Ok::<_, TxError>(())
+        }
+        .await;
+        conn.close().await?;
+        checked?;
+
+        Ok(pool_options.connect_with(options).await?)
+    }
+
+    /// SQLCipher's key is applied before the other pragmas.
+    fn apply_key(options: SqliteConnectOptions, key: &SecretString) -> SqliteConnectOptions {
+        options.pragma("key", format!("'{}'", key.expose().replace('\'', "''")))
+    }
+
+    /// Add `extensions` to the options, each one is first loaded by an in-memory database
+    /// so a failing extension is reported by its path.
+    fn load_extensions(
+        mut options: SqliteConnectOptions,
+        extensions: &[SqliteExtension],
+    ) -> Result<SqliteConnectOptions> {
+        for extension in extensions {
+            Self::check_extension(extension)?;
+            options = match &extension.entry_point {
+                Some(entry_point) => {
+                    options.extension_with_entrypoint(extension.path.clone(), entry_point.clone())
+                }
+                None => options.extension(extension.path.clone()),
+            };
+        }
+        Ok(options)
+    }
+
+    /// Load `extension` into an in-memory database with SQLite's API, sqlx doesn't report
+    /// why an extension failed to load.
+    fn check_extension(extension: &SqliteExtension) -> Result<()> {
+        let error =
+            |message: &str| TxError::LoadExtension(format!("`{}`: {message}", extension.path));
+        let path = CString::new(extension.path.as_str())
+            .map_err(|_| error("the path contains a nul character"))?;
+        let entry_point = extension
+            .entry_point
+            .as_deref()
+            .map(CString::new)
+            .transpose()
+            .map_err(|_| error("the entry point contains a nul character"))?;
+        let mut db = ptr::null_mut();
+
+        // SAFETY: the database is closed before returning, and the error message is freed
+        // once it's copied.
+        unsafe {
+            let rc = sqlite3_open_v2(
+                c":memory:".as_ptr(),
+                &mut db,
+                SQLITE_OPEN_READWRITE,
+                ptr::null(),
+            );
+            if rc != SQLITE_OK {
+                sqlite3_close(db);
+                return Err(error("couldn't open an in-memory database"));
+            }
+            sqlite3_db_config(
+                db,
+                SQLITE_DBCONFIG_ENABLE_LOAD_EXTENSION,
+                1 as c_int,
+                ptr::null_mut::<c_int>(),
+            );
+            let mut message = ptr::null_mut();
+            let rc = sqlite3_load_extension(
+                db,
+                path.as_ptr(),
+                entry_point.as_ref().map_or(ptr::null(), |e| e.as_ptr()),
+                &mut message,
+            );
+            let result = match rc {
+                SQLITE_OK => Ok(()),
+                _ if message.is_null() => Err(error("unknown error")),
+                _ => Err(error(&CStr::from_ptr(message).to_string_lossy())),
+            };
+            sqlite3_free(message.cast());
+            sqlite3_close(db);
+            result
+        }
+    }
+
+    /// Pool options attaching `databases` to each opened connection, encrypted with `key`
+    /// if the main database is.
+    pub(crate) fn pool_options(
+        databases: &[AttachedDatabase],
+        key: Option<&SecretString>,
+    ) -> Result<SqlitePoolOptions> {
+        for (i, database) in databases.iter().enumerate() {
+            let alias = &database.alias;
+            if alias.is_empty() || alias.contains('.') {
+                return Err(TxError::AttachDatabase(format!(
+                    "`{alias}` isn't a valid alias, it must be non-empty and without dots"
+                )));
+            }
+            if ["main", "temp"].contains(&alias.to_lowercase().as_str())
+                || databases[..i]
+                    .iter()
+                    .any(|other| other.alias.eq_ignore_ascii_case(alias))
+            {
+                return Err(TxError::AttachDatabase(format!(
+                    "the alias `{alias}` is already used"
+                )));
+            }
+            // `ATTACH` would create an empty database instead.
+            if !Path::new(&database.path).exists() {
+                return Err(TxError::AttachDatabase(format!(
+                    "`{}` doesn't exist",
+                    database.path
+                )));
+            }
+        }
+
+        let databases = databases.to_vec();
+        let key = key.cloned();
+        Ok(SqlitePoolOptions::new().after_connect(move |conn, _| {
+            let databases = databases.clone();
+            let key = key.clone();
+            Box::pin(async move {
+                for database in &databases {
+                    Self::attach(conn, database, key.as_ref()).await?;
+                }
+                Ok(())
+            })
+        }))
+    }
+
+    /// Attach `database` to the connection, SQLCipher doesn't give attached databases the
+    /// main database's key when it's set by `PRAGMA key`.
+    async fn attach(
+        conn: &mut SqliteConnection,
+        database: &AttachedDatabase,
+        key: Option<&SecretString>,
+    ) -> sqlx::Result<()> {
+        let query = match key {
+            Some(key) => sqlx::query("ATTACH DATABASE ? AS ? KEY ?")
+                .bind(&database.path)
+                .bind(&database.alias)
+                .bind(key.expose()),
+            None => sqlx::query("ATTACH DATABASE ? AS ?")
+                .bind(&database.path)
+                .bind(&database.alias),
+        };
+        query.execute(conn).await?;
+        Ok(())
+    }
+
+    /// Aliases and files of the databases attached to the connections.
+    async fn attached_databases(pool: &SqlitePool) -> sqlx::Result<Vec<(String, String)>> {
+        sqlx::query_as(
+            "SELECT name, file FROM pragma_database_list WHERE name NOT IN ('main', 'temp')",
+        )
+        .fetch_all(pool)
+        .await
+    }
+
+    /// Discover the details of each table in `tables_names`, a failure
+    /// to discover one table doesn't affect the others.
+    ///
+    /// Tables of attached databases are discovered from a separate, read-only connection to
+    /// their file, as if they were in its main database. It's opened with the key and
+    /// extensions of `options`.
+    pub(crate) async fn discover_tables(
+        pool: &SqlitePool,
+        options: &SqliteOptions,
+        tables_names: &[String],
+    ) -> Result<Vec<TableDiscovery>> {
+        let attached = Self::attached_databases(pool).await?;
+        if attached.is_empty() {
+            return Self::discover_main_tables(pool, tables_names).await;
+        }
+
+        // Tables names grouped by database, along with their position in `tables_names`.
+        let mut main = vec![];
+        let mut by_alias: HashMap<&str, Vec<(usize, String)>> = HashMap::new();
+        for (i, table_name) in tables_names.iter().enumerate() {
+            match table_name
+                .split_once('.')
+                .filter(|(alias, _)| attached.iter().any(|(name, _)| name == alias))
+            {
+                Some((alias, table)) => by_alias
+                    .entry(alias)
+                    .or_default()
+                    .push((i, table.to_string())),
+                None => main.push((i, table_name.clone())),
+            }
+        }
+
+        let mut result: Vec<Option<TableDiscovery>> = tables_names.iter().map(|_| None).collect();
+        let (positions, names): (Vec<_>, Vec<_>) = main.into_iter().unzip();
+        for (i, discovery) in positions
+            .into_iter()
+            .zip(Self::discover_main_tables(pool, &names).await?)
+        {
+            result[i] = Some(discovery);
+        }
+
+        for (alias, tables) in by_alias {
+            let (positions, names): (Vec<_>, Vec<_>) = tables.into_iter().unzip();
+            let file = &attached.iter().find(|(name, _)| name == alias).unwrap().1;
+            let mut attached_options = SqliteConnectOptions::new().filename(file).read_only(true);
+            if let Some(key) = &options.key {
+                attached_options = Self::apply_key(attached_options, key);
+            }
+            let connected = match Self::load_extensions(attached_options, &options.extensions) {
+                Ok(attached_options) => SqlitePool::connect_with(attached_options)
+                    .await
+                    .map_err(TxError::from),
+                Err(e) => Err(e),
+            };
+            let discovered = match connected {
+                Ok(attached_pool) => {
+                    let discovered = Self::discover_main_tables(&attached_pool, &names).await;
+                    attached_pool.close().await;
+                    discovered
+                }
+                Err(e) => Err(e),
+            };
+            let discovered = match discovered {
+                Ok(discovered) => discovered,
+                Err(e) => names
+                    .iter()
+                    .map(|_| TableDiscovery::Failed(e.to_string()))
+                    .collect(),
+            };
+
+            for (i, discovery) in positions.into_iter().zip(discovered) {
+                result[i] = Some(match discovery {
+                    TableDiscovery::Found(mut info) => {
+                        info.name = format!("{alias}.{}", info.name);
+                        // Foreign keys can only reference tables of the same database.
+                        for fk in &mut info.foreign_keys {
+                            fk.referenced_table = format!("{alias}.{}", fk.referenced_table);
+                        }
+                        TableDiscovery::Found(info)
+                    }
+                    discovery => discovery,
+                });
+            }
+        }
+        Ok(result.into_iter().map(Option::unwrap).collect())
+    }
+
+    async fn discover_main_tables(
+        pool: &SqlitePool,
+        tables_names: &[String],
+    ) -> Result<Vec<TableDiscovery>> {
+        let existing: HashSet<String> = Self::tables_names(pool).await?.0.into_iter().collect();
+        let executor = SchemaDiscovery::new(pool.clone()).executor;
+
+        let mut result = Vec::with_capacity(tables_names.len());
+        for table_name in tables_names {
+            if !existing.contains(table_name) {
+                result.push(TableDiscovery::Missing);
+                continue;
+            }
+
+            let mut table = TableDef {
+                name: table_name.clone(),
+                ..Default::default()
+            };
+            let discovered = async {
+                table.pk_is_autoincrement(&executor).await?;
+                table.get_foreign_keys(&executor).await?;
+                table.get_column_info(&executor).await?;
+                table.get_constraints(&executor).await
+            }
+            .await;
+
+            if let Err(e) = discovered {
+                result.push(TableDiscovery::Failed(e.to_string()));
+                continue;
+            }
+
+            let mut info = TableInfo::from(&table);
+            let constraints = async {
+                let mut defaults = Self::default_values(pool, table_name).await?;
+                for column in &mut info.columns {
+                    column.default_value = defaults.remove(&column.name).flatten();
+                }
+                info.indexes = Self::indexes(pool, table_name).await?;
+                info.foreign_keys = Self::foreign_keys(pool, table_name).await?;
+                Ok::<_, sqlx::Error>(())
+            }
+            .await;
+            result.push(match constraints {
+                Ok(_) => TableDiscovery::Found(info),
+                Err(e) => TableDiscovery::Failed(e.to_string()),
+            });
+        }
+        Ok(result)
+    }
+
+    /// Statements rebuilding `current` so it matches `target`, including the re-creation
+    /// of the table's triggers. Triggers referring to dropped columns aren't recreated.
+    pub(crate) async fn rebuild_table_statements(
+        pool: &SqlitePool,
+        current: &TableDefinition,
+        target: &TableDefinition,
+    ) -> Result<Vec<String>> {
+        let mut stmts = ddl::rebuild_table(current, target)?;
+        let triggers: Vec<(String, String)> = sqlx::query_as(
+            "SELECT name, sql FROM sqlite_master WHERE type = 'trigger' AND tbl_name = ?",
+        )
+        .bind(&current.name)
+        .fetch_all(pool)
+        .await?;
+        for (name, sql) in triggers {
+            match ddl::rebuild_trigger(&sql, current, target)? {
+                Some(sql) => stmts.push(sql),
+                None => log::warn!(
+                    "Trigger {name} refers to a dropped column of {}, it won't be recreated",
+                    current.name
+                ),
+            }
+        }
+        Ok(stmts)
+    }
+
+    /// Rebuild `current` so it matches `target`, following SQLite's procedure for
+    /// the changes `ALTER TABLE` can't perform.
+    pub(crate) async fn rebuild_table(
+        pool: &SqlitePool,
+        current: &TableDefinition,
+        target: &TableDefinition,
+    ) -> Result<()> {
+        let stmts = Self::rebuild_table_statements(pool, current, target).await?;
+        Self::execute_rebuild(pool, &target.name, &stmts).await
+    }
+
+    /// Execute the statements rebuilding `table_name`.
+    ///
+    /// Foreign keys are disabled during the rebuild, and the foreign keys of the rebuilt
+    /// table and of the tables referencing it are checked before committing.
+    pub(crate) async fn execute_rebuild(
+        pool: &SqlitePool,
+        table_name: &str,
+        stmts: &[String],
+    ) -> Result<()> {
+        // Pragmas are per connection and can't be changed inside a transaction.
+        let mut conn = pool.acquire().await?;
+        let foreign_keys: bool = sqlx::query_scalar("PRAGMA foreign_keys")
+            .fetch_one(&mut *conn)
+            .await?;
+        sqlx::query("PRAGMA foreign_keys = OFF")
+            .execute(&mut *conn)
+            .await?;
+        // Keeps views and triggers referencing the table from failing the final rename.
+        sqlx::query("PRAGMA legacy_alter_table = ON")
+            .execute(&mut *conn)
+            .await?;
+
+        let result = Self::rebuild_in_transaction(&mut conn, table_name, stmts, foreign_keys).await;
+
+        // The connection goes back to the pool, restore its settings whatever the outcome.
+        sqlx::query("PRAGMA legacy_alter_table = OFF")
+            .execute(&mut *conn)
+            .await?;
+        if foreign_keys {
+            sqlx::query("PRAGMA foreign_keys = ON")
+                .execute(&mut *conn)
+                .await?;
+        }
+        result
+    }
+
+    async fn rebuild_in_transaction(
+        conn: &mut SqliteConnection,
+        table_name: &str,
+        stmts: &[String],
+        check_foreign_keys: bool,
+    ) -> Result<()> {
+        let mut tx = conn.begin().await?;
+        for stmt in stmts {
+            sqlx::query(stmt).execute(&mut *tx).await?;
+        }
+
+        if check_foreign_keys {
+            let mut checked: Vec<String> = sqlx::query_scalar(
+                r#"
+                SELECT DISTINCT m.name
+                FROM sqlite_master m, pragma_foreign_key_list(m.name) f
+                WHERE m.type = 'table' AND f."table" = ? COLLATE NOCASE
+                "#,
+            )
+            .bind(table_name)
+            .fetch_all(&mut *tx)
+            .await?;
+            checked.push(table_name.to_string());
+
+            let mut violations: Vec<String> = vec![];
+            for table in &checked {
+                let table_violations: Vec<String> =
+                    sqlx::query_scalar(r#"SELECT "table" FROM pragma_foreign_key_check(?)"#)
+                        .bind(table)
+                        .fetch_all(&mut *tx)
+                        .await?;
+                violations.extend(table_violations);
+            }
+            if !violations.is_empty() {
+                violations.sort();
+                violations.dedup();
+                return Err(TxError::TableRebuild(format!(
+                    "foreign key constraints are violated in {}",
+                    violations.join(", ")
+                )));
+            }
+        }
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// Raw default expressions of `table_name`'s columns.
+    ///
+    /// sea-schema strips the quotes from defaults, turning e.g `datetime('now')` into `datetime(now)`.
+    async fn default_values(
+        pool: &SqlitePool,
+        table_name: &str,
+    ) -> sqlx::Result<HashMap<String, Option<String>>> {
+        let defaults: Vec<(String, Option<String>)> =
+            sqlx::query_as("SELECT name, dflt_value FROM pragma_table_info(?)")
+                .bind(table_name)
+                .fetch_all(pool)
+                .await?;
+        Ok(defaults.into_iter().collect())
+    }
+
+    /// Indexes of `table_name`, excluding the primary key.
+    async fn indexes(pool: &SqlitePool, table_name: &str) -> sqlx::Result<Vec<IndexInfo>> {
+        let list: Vec<(String, bool, String)> =
+            sqlx::query_as(r#"SELECT name, "unique", origin FROM pragma_index_list(?)"#)
+                .bind(table_name)
+                .fetch_all(pool)
+                .await?;
+
+        let mut indexes = Vec::with_capacity(list.len());
+        for (name, unique, origin) in list {
+            if origin == "pk" {
+                continue;
+            }
+            // Expressions in indexes have no column name.
+            let columns: Vec<Option<String>> =
+                sqlx::query_scalar("SELECT name FROM pragma_index_info(?) ORDER BY seqno")
+                    .bind(&name)
+                    .fetch_all(pool)
+                    .await?;
+            indexes.push(IndexInfo {
+                name,
+                columns: columns.into_iter().flatten().collect(),
+                unique,
+            });
+        }
+        Ok(indexes)
+    }
+
+    async fn foreign_keys(
+        pool: &SqlitePool,
+        table_name: &str,
+    ) -> sqlx::Result<Vec<ForeignKeyInfo>> {
+        let rows: Vec<(i64, String, String, Option<String>, String, String)> = sqlx::query_as(
+            r#"
+            SELECT id, "table", "from", "to", on_update, on_delete
+            FROM pragma_foreign_key_list(?)
+            ORDER BY id, seq
+            "#,
+        )
+        .bind(table_name)
+        .fetch_all(pool)
+        .await?;
+
+        // Composite foreign keys span multiple rows sharing the same id.
+        let mut foreign_keys: Vec<(i64, ForeignKeyInfo)> = Vec::new();
+        for (id, table, from, to, on_update, on_delete) in rows {
+            if foreign_keys
+                .last()
+                .is_none_or(|(last_id, _)| *last_id != id)
+            {
+                foreign_keys.push((
+                    id,
+                    ForeignKeyInfo {
+                        name: None,
+                        columns: vec![],
+                        referenced_table: table,
+                        referenced_columns: vec![],
+                        on_update: on_update.as_str().into(),
+                        on_delete: on_delete.as_str().into(),
+                    },
+                ));
+            }
+            let (_, fk) = foreign_keys.last_mut().unwrap();
+            fk.columns.push(from);
+            fk.referenced_columns.extend(to);
+        }
+
+        let mut result = Vec::with_capacity(foreign_keys.len());
+        for (_, mut fk) in foreign_keys {
+            // `to` is NULL when referencing the parent's primary key implicitly.
+            if fk.referenced_columns.is_empty() {
+                fk.referenced_columns = sqlx::query_scalar(
+                    "SELECT name FROM pragma_table_info(?) WHERE pk > 0 ORDER BY pk",
+                )
+                .bind(&fk.referenced_table)
+                .fetch_all(pool)
+                .await?;
+            }
+            result.push(fk);
+        }
+        Ok(result)
+    }
 }
 
 impl From<sea_schema::sqlite::def::Schema> for Schema {
     fn from(value: sea_schema::sqlite::def::Schema) -> Self {
-        Self {
-            tables: value.tables.iter().map(|t| t.into()).collect(),
-        }
+        value.tables.iter().map(TableInfo::from).collect()
     }
 }
 
@@ -34,19 +660,59 @@ impl From<&sea_schema::sqlite::def::TableDef> for TableInfo {
             columns: value
                 .columns
                 .iter()
-                .map(|c| ColumnInfo {
-                    auto_generated: c.primary_key && value.auto_increment,
-                    name: c.name.clone(),
-                    nullable: !c.not_null,
-                    pk: c.primary_key,
-                    r#type: c.r#type.clone().into(),
+                .map(|c| {
+                    let (numeric_precision, numeric_scale) = numeric_precision_scale(&c.r#type);
+                    ColumnInfo {
+                        auto_generated: c.primary_key && value.auto_increment,
+                        name: c.name.clone(),
+                        nullable: !c.not_null,
+                        pk: c.primary_key,
+                        r#type: c.r#type.clone().into(),
+                        default_value: default_value(&c.default_value),
+                        max_length: max_length(&c.r#type),
+                        numeric_precision,
+                        numeric_scale,
+                        collation: None,
+                        comment: None,
+                    }
                 })
                 .collect(),
-            create_statement:value.write().to_string(SqliteQueryBuilder)
+            create_statement: value.write().to_string(SqliteQueryBuilder),
+            indexes: vec![],
+            foreign_keys: vec![],
         }
     }
 }
 
+fn default_value(default: &DefaultType) -> Option<String> {
+    match default {
+        DefaultType::Integer(v) => Some(v.to_string()),
+        DefaultType::Float(v) => Some(v.to_string()),
+        DefaultType::String(v) => Some(v.clone()),
+        DefaultType::Null => Some("NULL".to_string()),
+        DefaultType::CurrentTimestamp => Some("CURRENT_TIMESTAMP".to_string()),
+        DefaultType::Unspecified => None,
+    }
+}
+
+fn max_length(col_type: &SeaColumnType) -> Option<u32> {
+    match col_type {
+        SeaColumnType::Char(len) => *len,
+        SeaColumnType::String(StringLen::N(len))
+        | SeaColumnType::VarBinary(StringLen::N(len))
+        | SeaColumnType::Binary(len) => Some(*len),
+        _ => None,
+    }
+}
+
+fn numeric_precision_scale(col_type: &SeaColumnType) -> (Option<u32>, Option<u32>) {
+    match col_type {
+        SeaColumnType::Decimal(Some((precision, scale)))
+        | SeaColumnType::Money(Some((precision, scale))) => (Some(*precision), Some(*scale)),
+        _ => (None, None),
+    }
+}
+
 impl From<SeaColumnType> for CustomColumnType {
     fn from(value: SeaColumnType) -> Self {
         match value {
@@ -83,14 +749,14 @@ impl From<SeaColumnType> for CustomColumnType {
             SeaColumnType::Enum {
                 name: _,
                 variants: _,
-            } => todo!(),
-            SeaColumnType::Array(_column_type) => todo!(),
-            SeaColumnType::Vector(_) => todo!(),
-            SeaColumnType::Cidr => todo!(),
-            SeaColumnType::Inet => todo!(),
-            SeaColumnType::MacAddr => todo!(),
-            SeaColumnType::LTree => todo!(),
-            _ => todo!(),
+            } => CustomColumnType::UnSupported,
+            SeaColumnType::Array(_column_type) => CustomColumnType::UnSupported,
+            SeaColumnType::Vector(_) => CustomColumnType::UnSupported,
+            SeaColumnType::Cidr => CustomColumnType::UnSupported,
+            SeaColumnType::Inet => CustomColumnType::UnSupported,
+            SeaColumnType::MacAddr => CustomColumnType::UnSupported,
+            SeaColumnType::LTree => CustomColumnType::UnSupported,
+            _ => CustomColumnType::UnSupported,
         }
     }
 }
@@ -146,3 +812,161 @@ impl From<SqliteQueryResult> for ExecResult {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::DatabaseConnection;
+    use tx_lib::{connection::ConnectOptions, types::Drivers};
+
+    fn temp_path(name: &str) -> String {
+        let path = std::env::temp_dir()
+            .join(format!("tx-sqlite-{}-{name}", std::process::id()))
+            .to_string_lossy()
+            .into_owned();
+        let _ = std::fs::remove_file(&path);
+        path
+    }
+
+    /// Options of a connection to a SQLite database.
+    fn connect_options(sqlite: SqliteOptions) -> ConnectOptions {
+        ConnectOptions {
+            sqlite,
+            ..Default::default()
+        }
+    }
+
+    async fn create(path: &str, key: Option<&str>, stmt: &str) {
+        let options = connect_options(SqliteOptions {
+            key: key.map(|key| SecretString::new(key.to_string())),
+            ..Default::default()
+        });
+        let url = format!("sqlite://{path}?mode=rwc");
+        let conn = DatabaseConnection::connect(&url, &Drivers::SQLite, &options)
+            .await
+            .unwrap();
+        conn.execute(stmt).await.unwrap();
+        conn.close().await;
+    }
+
+    #[tokio::test]
+    async fn column_defaults_and_lengths_are_reported() {
+        let mut conn = DatabaseConnection::in_memory().await.unwrap();
+        conn.execute(
+            "CREATE TABLE t (
+                id INTEGER PRIMARY KEY,
+                code VARCHAR(8) NOT NULL DEFAULT 'new',
+                created TEXT DEFAULT (datetime('now')),
+                price DECIMAL(10, 2)
+            )",
+        )
+        .await
+        .unwrap();
+        conn.refresh_schema(None).await.unwrap();
+        let table = conn.table_info("t").await.unwrap();
+        let column = |name: &str| table.columns.iter().find(|c| c.name == name).unwrap();
+
+        assert_eq!(column("id").default_value, None);
+        assert_eq!(column("code").default_value.as_deref(), Some("'new'"));
+        assert_eq!(column("code").max_length, Some(8));
+        assert!(!column("code").nullable);
+        assert_eq!(
+            column("created").default_value.as_deref(),
+            Some("datetime('now')")
+        );
+        assert_eq!(column("price").numeric_precision, Some(10));
+        assert_eq!(column("price").numeric_scale, Some(2));
+    }
+
+    #[tokio::test]
+    async fn attached_tables_are_qualified_by_their_alias() {
+        let main = temp_path("main.db");
+        let logs = temp_path("logs.db");
+        create(&main, None, "CREATE TABLE \"x.y\" (id INTEGER PRIMARY KEY)").await;
+        create(&logs, None, "CREATE TABLE events (id INTEGER PRIMARY KEY)").await;
+
+        let options = connect_options(SqliteOptions {
+            attached_databases: vec![AttachedDatabase {
+                alias: "logs".to_string(),
+                path: logs.clone(),
+            }],
+            ..Default::default()
+        });
+        let mut conn =
+            DatabaseConnection::connect(&format!("sqlite://{main}"), &Drivers::SQLite, &options)
+                .await
+                .unwrap();
+        let events = conn.table_info("logs.events").await.unwrap();
+        let dotted = conn.table_info("x.y").await.unwrap();
+        conn.close().await;
+        std::fs::remove_file(&main).unwrap();
+        std::fs::remove_file(&logs).unwrap();
+
+        assert_eq!(events.name, "logs.events");
+        assert_eq!(dotted.name, "x.y");
+        assert_eq!(conn.attached_table("logs.events"), Some(("logs", "events")));
+        assert_eq!(conn.attached_table("x.y"), None);
+    }
+
+    #[tokio::test]
+    async fn failing_extensions_are_reported_by_their_path() {
+        let path = temp_path("extension.db");
+        let options = connect_options(SqliteOptions {
+            extensions: vec![SqliteExtension {
+                path: "/missing/libnothing".to_string(),
+                entry_point: None,
+            }],
+            ..Default::default()
+        });
+        let url = format!("sqlite://{path}?mode=rwc");
+        let res = DatabaseConnection::connect(&url, &Drivers::SQLite, &options).await;
+        let _ = std::fs::remove_file(&path);
+
+        let Err(TxError::LoadExtension(message)) = res else {
+            panic!("the missing extension wasn't reported");
+        };
+        assert!(message.contains("/missing/libnothing"), "{message}");
+    }
+
+    #[tokio::test]
+    async fn encrypted_databases_are_opened_with_their_key() {
+        let main = temp_path("encrypted.db");
+        let logs = temp_path("encrypted-logs.db");
+        create(
+            &main,
+            Some("secret"),
+            "CREATE TABLE t (id INTEGER PRIMARY KEY)",
+        )
+        .await;
+        create(
+            &logs,
+            Some("secret"),
+            "CREATE TABLE events (id INTEGER PRIMARY KEY)",
+        )
+        .await;
+        let url = format!("sqlite://{main}");
+        let mut options = connect_options(SqliteOptions {
+            attached_databases: vec![AttachedDatabase {
+                alias: "logs".to_string(),
+                path: logs.clone(),
+            }],
+            ..Default::default()
+        });
+
+        let missing = DatabaseConnection::ping(&url, &Drivers::SQLite, &options).await;
+        options.sqlite.key = Some(SecretString::new("wrong".to_string()));
+        let wrong = DatabaseConnection::ping(&url, &Drivers::SQLite, &options).await;
+        options.sqlite.key = Some(SecretString::new("secret".to_string()));
+        let mut conn = DatabaseConnection::connect(&url, &Drivers::SQLite, &options)
+            .await
+            .unwrap();
+        let events = conn.table_info("logs.events").await;
+        conn.close().await;
+        std::fs::remove_file(&main).unwrap();
+        std::fs::remove_file(&logs).unwrap();
+
+        assert!(matches!(missing, Err(TxError::DatabaseKey(_))));
+        assert!(matches!(wrong, Err(TxError::DatabaseKey(_))));
+        assert_eq!(events.unwrap().columns.len(), 1);
+    }
+}
@@ -0,0 +1,404 @@
+//! SSH tunnels, forwarding a local port to a database only reachable from an SSH server.
+
+use home::home_dir;
+use russh::{
+    Disconnect,
+    client::{self, Handle},
+    keys::{
+        self, PrivateKeyWithHashAlg,
+        ssh_key::{HashAlg, PublicKey},
+    },
+};
+use std::{path::PathBuf, sync::Arc};
+use tokio::{net::TcpListener, sync::Mutex, task::JoinHandle};
+use tx_lib::{
+    Result, TxError,
+    connection::{SshAuth, SshLogin},
+    types::SecretString,
+};
+
+/// Address the tunnel listens on, connections are made to it instead of the database.
+pub(crate) const LOCALHOST: &str = "127.0.0.1";
+
+fn error(message: impl Into<String>) -> TxError {
+    TxError::SshTunnel(message.into())
+}
+
+fn ssh_error(e: russh::Error) -> TxError {
+    error(e.to_string())
+}
+
+/// Errors of the SSH session, a rejected server key is explained instead of reported as
+/// russh's generic error.
+#[derive(Debug)]
+enum ClientError {
+    Ssh(russh::Error),
+    ServerKey(String),
+}
+
+impl From<russh::Error> for ClientError {
+    fn from(e: russh::Error) -> Self {
+        Self::Ssh(e)
+    }
+}
+
+impl From<ClientError> for TxError {
+    fn from(e: ClientError) -> Self {
+        match e {
+            ClientError::Ssh(e) => ssh_error(e),
+            ClientError::ServerKey(message) => error(message),
+        }
+    }
+}
+
+/// Verifies the server's key with a known_hosts file.
+struct Client {
+    host: String,
+    port: u16,
+    known_hosts: PathBuf,
+    /// Add the key of an unknown host to `known_hosts` instead of rejecting it.
+    accept_new_host_key: bool,
+}
+
+impl client::Handler for Client {
+    type Error = ClientError;
+
+    async fn check_server_key(
+        &mut self,
+        server_public_key: &PublicKey,
+    ) -> std::result::Result<bool, Self::Error> {
+        let known_hosts = self.known_hosts.display();
+        match keys::check_known_hosts_path(
+            &self.host,
+            self.port,
+            server_public_key,
+            &self.known_hosts,
+        ) {
+            Ok(true) => Ok(true),
+            Ok(false) if self.accept_new_host_key => {
+                keys::known_hosts::learn_known_hosts_path(
+                    &self.host,
+                    self.port,
+                    server_public_key,
+                    &self.known_hosts,
+                )
+                .map_err(|e| {
+                    ClientError::ServerKey(format!(
+                        "failed to add `{}` to {known_hosts}: {e}",
+                        self.host
+                    ))
+                })?;
+                log::info!(
+                    "Added `{}` to {known_hosts}, its key's fingerprint is {}",
+                    self.host,
+                    server_public_key.fingerprint(HashAlg::Sha256)
+                );
+                Ok(true)
+            }
+            Ok(false) => Err(ClientError::ServerKey(format!(
+                "`{}` isn't a known host in {known_hosts}, its key's fingerprint is {}",
+                self.host,
+                server_public_key.fingerprint(HashAlg::Sha256)
+            ))),
+            Err(keys::Error::KeyChanged { line }) => Err(ClientError::ServerKey(format!(
+                "the key of `{}` doesn't match line {line} of {known_hosts}",
+                self.host
+            ))),
+            Err(e) => Err(ClientError::ServerKey(format!(
+                "failed to read {known_hosts}: {e}"
+            ))),
+        }
+    }
+}
+
+/// A local port forwarded through an SSH server, closed when dropped.
+pub struct Tunnel {
+    local_port: u16,
+    /// Replaced by the forwarding when the server closed it, e.g after a network change.
+    session: Arc<Mutex<Handle<Client>>>,
+    forwarding: JoinHandle<()>,
+}
+
+impl Tunnel {
+    /// Connect to the SSH server of `login` and forward a random local port to
+    /// `remote_host:remote_port`, as resolved by the server.
+    pub async fn open(login: &SshLogin, remote_host: &str, remote_port: u16) -> Result<Self> {
+        let session = connect(login).await?;
+
+        let listener = TcpListener::bind((LOCALHOST, 0)).await?;
+        let local_port = listener.local_addr()?.port();
+        let session = Arc::new(Mutex::new(session));
+        let forwarding = tokio::spawn(forward(
+            listener,
+            session.clone(),
+            login.clone(),
+            remote_host.to_string(),
+            remote_port,
+        ));
+        log::info!(
+            "SSH tunnel opened from port {local_port} to {remote_host}:{remote_port} through {}",
+            login.tunnel.host
+        );
+
+        Ok(Self {
+            local_port,
+            session,
+            forwarding,
+        })
+    }
+
+    pub fn local_port(&self) -> u16 {
+        self.local_port
+    }
+
+    /// Stop forwarding and disconnect from the SSH server.
+    pub async fn close(&self) {
+        self.forwarding.abort();
+        if let Err(e) = self
+            .session
+            .lock()
+            .await
+            .disconnect(Disconnect::ByApplication, "", "en")
+            .await
+        {
+            log::debug!("Failed to disconnect SSH session: {e}");
+        }
+        log::debug!("SSH tunnel from port {} closed.", self.local_port);
+    }
+}
+
+impl Drop for Tunnel {
+    fn drop(&mut self) {
+        // The session is closed once its last handle, held by the forwarding, is dropped.
+        self.forwarding.abort();
+    }
+}
+
+/// Connect and authenticate to the SSH server of `login`.
+async fn connect(login: &SshLogin) -> Result<Handle<Client>> {
+    let tunnel = &login.tunnel;
+    let secret = login.secret.as_ref().map(SecretString::expose);
+    if tunnel.auth == SshAuth::Password && secret.is_none() {
+        return Err(TxError::InvalidConnectionParams(format!(
+            "the SSH password of `{}` isn't set",
+            tunnel.user
+        )));
+    }
+    let known_hosts = match &tunnel.known_hosts {
+        Some(path) => expand_home(path)?,
+        None => home_dir()
+            .ok_or(TxError::HomeDirResolution)?
+            .join(".ssh")
+            .join("known_hosts"),
+    };
+    let client = Client {
+        host: tunnel.host.clone(),
+        port: tunnel.port,
+        known_hosts,
+        accept_new_host_key: tunnel.accept_new_host_key,
+    };
+    let config = Arc::new(client::Config::default());
+    let mut session = client::connect(config, (tunnel.host.as_str(), tunnel.port), client).await?;
+
+    let auth = match &tunnel.auth {
+        SshAuth::Password => {
+            session
+                .authenticate_password(&tunnel.user, secret.unwrap_or_default())
+                .await
+        }
+        SshAuth::KeyFile { path } => {
+            let key = keys::load_secret_key(expand_home(path)?, secret)
+                .map_err(|e| error(format!("failed to read the key `{path}`: {e}")))?;
+            // RSA keys are signed with the strongest hash the server supports.
+            let hash_alg = session
+                .best_supported_rsa_hash()
+                .await
+                .map_err(ssh_error)?
+                .flatten();
+            session
+                .authenticate_publickey(
+                    &tunnel.user,
+                    PrivateKeyWithHashAlg::new(Arc::new(key), hash_alg),
+                )
+                .await
+        }
+    }
+    .map_err(ssh_error)?;
+    if !auth.success() {
+        return Err(error(format!(
+            "`{}` was rejected by {}",
+            tunnel.user, tunnel.host
+        )));
+    }
+    Ok(session)
+}
+
+/// Open `ssh`'s tunnel to `host:port`, if any.
+pub(crate) async fn open_tunnel(
+    ssh: Option<&SshLogin>,
+    host: &str,
+    port: u16,
+) -> Result<Option<Tunnel>> {
+    match ssh {
+        Some(login) => Ok(Some(Tunnel::open(login, host, port).await?)),
+        None => Ok(None),
+    }
+}
+
+/// Forward each connection accepted by `listener` through its own SSH channel. A session
+/// closed by the server is re-established with `login` when the next connection comes.
+async fn forward(
+    listener: TcpListener,
+    session: Arc<Mutex<Handle<Client>>>,
+    login: SshLogin,
+    host: String,
+    port: u16,
+) {
+    loop {
+        let (mut socket, address) = match listener.accept().await {
+            Ok(accepted) => accepted,
+            Err(e) => {
+                log::warn!("SSH tunnel stopped accepting connections: {e}");
+                return;
+            }
+        };
+        let mut session = session.lock().await;
+        if session.is_closed() {
+            log::info!(
+                "SSH session to {} was closed, reconnecting",
+                login.tunnel.host
+            );
+            match connect(&login).await {
+                Ok(new) => *session = new,
+                Err(e) => {
+                    log::warn!("Failed to reconnect to {}: {e}", login.tunnel.host);
+                    continue;
+                }
+            }
+        }
+        let channel = match session
+            .channel_open_direct_tcpip(
+                host.clone(),
+                port.into(),
+                address.ip().to_string(),
+                address.port().into(),
+            )
+            .await
+        {
+            Ok(channel) => channel,
+            Err(e) => {
+                log::warn!("Failed to open SSH channel to {host}:{port}: {e}");
+                continue;
+            }
+        };
+        drop(session);
+        tokio::spawn(async move {
+            let mut stream = channel.into_stream();
+            if let Err(e) = tokio::io::copy_bidirectional(&mut socket, &mut stream).await {
+                log::debug!("SSH tunnel connection closed: {e}");
+            }
+        });
+    }
+}
+
+/// Replace a leading `~` of `path` with the user's home dir.
+fn expand_home(path: &str) -> Result<PathBuf> {
+    match path.strip_prefix("~/") {
+        Some(rest) => Ok(home_dir().ok_or(TxError::HomeDirResolution)?.join(rest)),
+        None => Ok(PathBuf::from(path)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::DatabaseConnection;
+    use client::Handler;
+    use tx_lib::{
+        connection::{ConnectOptions, SshTunnel},
+        types::Drivers,
+    };
+
+    const SERVER_KEY: &str =
+        "ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAIFQdbJj1tdatuuKAXeC3o9cD6LaBKYcnwxj/1uC5I40v";
+
+    fn known_hosts_path(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("tx-ssh-{}-{name}", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        path
+    }
+
+    #[tokio::test]
+    async fn unknown_host_keys_are_only_added_when_accepted() {
+        let known_hosts = known_hosts_path("known_hosts");
+        let key = PublicKey::from_openssh(SERVER_KEY).unwrap();
+        let mut handler = Client {
+            host: "db.example.com".to_string(),
+            port: 2222,
+            known_hosts: known_hosts.clone(),
+            accept_new_host_key: false,
+        };
+
+        let rejected = handler.check_server_key(&key).await;
+        handler.accept_new_host_key = true;
+        let accepted = handler.check_server_key(&key).await;
+        handler.accept_new_host_key = false;
+        let known = handler.check_server_key(&key).await;
+        let contents = std::fs::read_to_string(&known_hosts).unwrap();
+        std::fs::remove_file(&known_hosts).unwrap();
+
+        assert!(matches!(rejected, Err(ClientError::ServerKey(_))));
+        assert!(accepted.unwrap());
+        assert!(known.unwrap());
+        assert!(contents.contains("[db.example.com]:2222 ssh-ed25519 "));
+    }
+
+    #[tokio::test]
+    async fn missing_ssh_passwords_are_a_configuration_error() {
+        let login = SshLogin {
+            tunnel: SshTunnel {
+                host: "db.example.com".to_string(),
+                port: 22,
+                user: "admin".to_string(),
+                auth: SshAuth::Password,
+                known_hosts: None,
+                accept_new_host_key: false,
+            },
+            secret: None,
+        };
+        assert!(matches!(
+            Tunnel::open(&login, "localhost", 5432).await,
+            Err(TxError::InvalidConnectionParams(_))
+        ));
+    }
+
+    /// Connects through the SSH server set by `TX_TEST_SSH_HOST`, `TX_TEST_SSH_PORT`,
+    /// `TX_TEST_SSH_USER` and `TX_TEST_SSH_PASSWORD` to the PostgreSQL database of
+    /// `TX_TEST_SSH_DATABASE_URL`, as reached from the SSH server.
+    #[tokio::test]
+    #[ignore = "needs an SSH server"]
+    async fn databases_are_reached_through_the_tunnel() {
+        let var = |name: &str| std::env::var(name).unwrap_or_else(|_| panic!("{name} isn't set"));
+        let known_hosts = known_hosts_path("tunnel_known_hosts");
+        let options = ConnectOptions {
+            ssh: Some(SshLogin {
+                tunnel: SshTunnel {
+                    host: var("TX_TEST_SSH_HOST"),
+                    port: std::env::var("TX_TEST_SSH_PORT")
+                        .map_or(22, |port| port.parse().unwrap()),
+                    user: var("TX_TEST_SSH_USER"),
+                    auth: SshAuth::Password,
+                    known_hosts: Some(known_hosts.to_string_lossy().into_owned()),
+                    accept_new_host_key: true,
+                },
+                secret: Some(SecretString::new(var("TX_TEST_SSH_PASSWORD"))),
+            }),
+            ..Default::default()
+        };
+
+        let url = var("TX_TEST_SSH_DATABASE_URL");
+        let pinged = DatabaseConnection::ping(&url, &Drivers::PostgreSQL, &options).await;
+        let _ = std::fs::remove_file(&known_hosts);
+        pinged.unwrap();
+    }
+}
@@ -0,0 +1,461 @@
+//! Copying a table's rows to another connection, which may use another driver.
+
+use crate::{
+    DatabaseConnection,
+    ddl::{SchemaChange, TableDefinition, inline, quoted, schema_builder},
+    decode_raw_rows,
+    query::RowValue,
+    schema::{ColumnInfo, CustomColumnType, RowRecord, TableInfo},
+};
+use futures_util::TryStreamExt;
+use sea_query::{Alias, Asterisk, Expr, Func, OnConflict, Query, SimpleExpr, Value};
+use sea_query_binder::{SqlxBinder, SqlxValues};
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use specta::Type;
+use tx_lib::{Result, TxError, types::Drivers};
+
+/// Most bound parameters a single statement may have, SQLite's limit being the lowest.
+pub(crate) const MAX_PARAMETERS: usize = 30_000;
+
+#[derive(Serialize, Deserialize, Type, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum TransferMode {
+    /// Delete the target table's rows before copying.
+    Truncate,
+    #[default]
+    Append,
+    /// Update the target rows that have the same primary key instead of inserting them.
+    Upsert,
+}
+
+#[derive(Serialize, Deserialize, Type, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct TransferOptions {
+    pub source_table: String,
+    pub target_table: String,
+    /// Create the target table from the source table's definition if it doesn't exist.
+    pub create_table: bool,
+    pub mode: TransferMode,
+    /// Number of rows inserted per statement.
+    pub batch_size: u32,
+}
+
+#[derive(Serialize, Deserialize, Type, Clone, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct TransferSummary {
+    pub created_table: bool,
+    /// Columns that were copied.
+    pub columns: Vec<String>,
+    /// Source columns that don't exist in the target table, or that it generates.
+    pub skipped_columns: Vec<String>,
+    pub rows_transferred: u64,
+}
+
+/// Copy the rows of `options.source_table` into `target`, converting each value to the
+/// type of the target column with the same name. Binary values are copied as is, and
+/// decimals as text so they aren't rounded.
+///
+/// Target columns the database generates are left for it to fill, except for primary key
+/// columns, which keep the source's values so rows referencing them stay valid.
+///
+/// The source rows are streamed and inserted in batches, all in a single transaction.
+/// `on_progress` is called after each batch with the number of rows transferred so far
+/// and the total number of rows.
+pub async fn transfer_table(
+    source: &mut DatabaseConnection,
+    target: &mut DatabaseConnection,
+    options: &TransferOptions,
+    mut on_progress: impl FnMut(u64, u64),
+) -> Result<TransferSummary> {
+    let source_info = source.table_info(&options.source_table).await?;
+    let mut summary = TransferSummary::default();
+
+    if options.create_table && !target.table_exists(&options.target_table).await? {
+        let definition = target_definition(
+            &source.driver(),
+            &target.driver(),
+            &source_info,
+            &options.target_table,
+        );
+        target
+            .apply_schema_change(&SchemaChange::Create(definition))
+            .await?;
+        summary.created_table = true;
+    }
+    let target_info = target.table_info(&options.target_table).await?;
+
+    let target_driver = target.driver();
+    let columns: Vec<(&ColumnInfo, &ColumnInfo)> = source_info
+        .columns
+        .iter()
+        .filter_map(|c| {
+            target_info
+                .columns
+                .iter()
+                .find(|t| t.name == c.name && (t.pk || !t.auto_generated))
+                .map(|t| (c, t))
+        })
+        .collect();
+    if columns.is_empty() {
+        return Err(TxError::Transfer(format!(
+            "tables `{}` and `{}` have no columns in common",
+            source_info.name, target_info.name
+        )));
+    }
+    summary.columns = columns.iter().map(|(c, _)| c.name.clone()).collect();
+    summary.skipped_columns = source_info
+        .columns
+        .iter()
+        .filter(|c| !summary.columns.contains(&c.name))
+        .map(|c| c.name.clone())
+        .collect();
+
+    let mut on_conflict = None;
+    if options.mode == TransferMode::Upsert {
+        let primary_key: Vec<&str> = target_info
+            .columns
+            .iter()
+            .filter(|c| c.pk)
+            .map(|c| c.name.as_str())
+            .collect();
+        if primary_key.is_empty()
+            || primary_key
+                .iter()
+                .any(|pk| !summary.columns.iter().any(|c| c == pk))
+        {
+            return Err(TxError::Transfer(format!(
+                "upserting requires the primary key of `{}` to be copied",
+                target_info.name
+            )));
+        }
+        let updated: Vec<Alias> = summary
+            .columns
+            .iter()
+            .filter(|c| !primary_key.contains(&c.as_str()))
+            .map(Alias::new)
+            .collect();
+        let mut conflict = OnConflict::columns(primary_key.into_iter().map(Alias::new));
+        if updated.is_empty() {
+            conflict.do_nothing();
+        } else {
+            conflict.update_columns(updated);
+        }
+        on_conflict = Some(conflict);
+    }
+
+    let total_rows = count_rows(source, &source_info.name).await?;
+    let batch_rows = (options.batch_size.max(1) as usize)
+        .min(MAX_PARAMETERS / columns.len())
+        .max(1);
+    let select = inline(
+        &source.driver(),
+        &Query::select()
+            .columns(summary.columns.iter().map(Alias::new))
+            .from(Alias::new(&source_info.name))
+            .to_owned(),
+    );
+    let insert = Query::insert()
+        .into_table(Alias::new(&target_info.name))
+        .columns(summary.columns.iter().map(Alias::new))
+        .to_owned();
+
+    let mut tx = target.begin().await?;
+    if options.mode == TransferMode::Truncate {
+        let (stmt, values) = Query::delete()
+            .from_table(Alias::new(&target_info.name))
+            .build_any_sqlx(target.into_builder().as_ref());
+        tx.execute_with(&stmt, values).await?;
+    }
+
+    let mut rows = source.fetch_raw_stream(&select, SqlxValues(sea_query::Values(vec![])));
+    let mut batch: Vec<Vec<SimpleExpr>> = Vec::with_capacity(batch_rows);
+    let mut exhausted = false;
+    while !exhausted {
+        match rows.try_next().await? {
+            Some(row) => {
+                let row_number = summary.rows_transferred + batch.len() as u64 + 1;
+                let values = row
+                    .values()
+                    .and_then(|mut row| {
+                        columns
+                            .iter()
+                            .map(|(source_column, target_column)| {
+                                let value = row.remove(&source_column.name).unwrap_or_default();
+                                transfer_value(&target_driver, target_column, value)
+                            })
+                            .collect::<Result<Vec<_>>>()
+                    })
+                    .map_err(|e| TxError::Transfer(format!("row {row_number}: {e}")))?;
+                batch.push(values);
+                if batch.len() < batch_rows {
+                    continue;
+                }
+            }
+            None => exhausted = true,
+        }
+        if batch.is_empty() {
+            continue;
+        }
+
+        let batch_len = batch.len() as u64;
+        let mut stmt = insert.clone();
+        for values in batch.drain(..) {
+            stmt.values_panic(values);
+        }
+        if let Some(conflict) = &on_conflict {
+            stmt.on_conflict(conflict.clone());
+        }
+        let (stmt, values) = stmt.build_any_sqlx(target.into_builder().as_ref());
+        tx.execute_with(&stmt, values).await?;
+        summary.rows_transferred += batch_len;
+        on_progress(summary.rows_transferred, total_rows);
+    }
+    drop(rows);
+
+    if target_driver == Drivers::PostgreSQL {
+        // Explicitly inserted ids don't advance the sequences generating them.
+        for (_, column) in columns.iter().filter(|(_, c)| c.auto_generated) {
+            let table = quoted(schema_builder(&target_driver).as_ref(), &target_info.name);
+            let name = quoted(schema_builder(&target_driver).as_ref(), &column.name);
+            let (stmt, values) = Query::select()
+                .expr(Expr::cust_with_values(
+                    format!(
+                        "setval(pg_get_serial_sequence($1, $2), COALESCE((SELECT MAX({name}) FROM {table}), 0) + 1, false)"
+                    ),
+                    [table.clone(), column.name.clone()],
+                ))
+                .build_any_sqlx(target.into_builder().as_ref());
+            tx.execute_with(&stmt, values).await?;
+        }
+    }
+    tx.commit().await?;
+
+    Ok(summary)
+}
+
+/// Definition of the table to create in the target database, foreign keys are left
+/// out since the tables they reference may not exist there and types without an
+/// equivalent are created as text.
+fn target_definition(
+    source_driver: &Drivers,
+    target_driver: &Drivers,
+    source: &TableInfo,
+    table_name: &str,
+) -> TableDefinition {
+    let mut definition = TableDefinition::from(source);
+    definition.foreign_keys.clear();
+    if definition.name != table_name {
+        // Index names must be unique in the whole database for some drivers.
+        for index in &mut definition.indexes {
+            index.name = format!("{table_name}_{}_idx", index.columns.join("_"));
+        }
+        definition.name = table_name.to_string();
+    }
+    for column in &mut definition.columns {
+        // Their values are read as text anyway.
+        if matches!(
+            column.r#type,
+            CustomColumnType::Custom | CustomColumnType::UnSupported
+        ) {
+            column.r#type = CustomColumnType::Text;
+        }
+    }
+    if source_driver != target_driver {
+        for column in &mut definition.columns {
+            // Defaults are SQL expressions in the source's dialect.
+            column.default_value = None;
+            if let CustomColumnType::Enum(_) = column.r#type {
+                column.r#type = CustomColumnType::String;
+            }
+        }
+    }
+    definition
+}
+
+/// Value of a source cell converted for `column` of the target table.
+fn transfer_value(driver: &Drivers, column: &ColumnInfo, value: RowValue) -> Result<SimpleExpr> {
+    let value = match value {
+        RowValue::Bytes(bytes) => {
+            return Ok(SimpleExpr::Value(Value::Bytes(Some(Box::new(bytes)))));
+        }
+        RowValue::Decoded(value) => value,
+    };
+    let decimal = matches!(
+        column.r#type,
+        CustomColumnType::Float | CustomColumnType::Integer
+    ) && column.numeric_scale.is_some();
+    let text = match &value {
+        JsonValue::String(text) if decimal => text.trim().to_string(),
+        JsonValue::Number(number) if decimal => number.to_string(),
+        _ => {
+            return RowRecord {
+                column_name: column.name.clone(),
+                value,
+                column_type: transfer_type(driver, &column.r#type),
+            }
+            .try_into_value()
+            .map(SimpleExpr::Value);
+        }
+    };
+    if text.parse::<f64>().is_err() {
+        return Err(TxError::InvalidValue(format!(
+            "{value} isn't a valid decimal for column `{}`",
+            column.name
+        )));
+    }
+    // Bound as text for the database to convert, Postgres only does so when it's cast.
+    let text = SimpleExpr::Value(Value::String(Some(Box::new(text))));
+    Ok(match driver {
+        Drivers::PostgreSQL => text.cast_as(Alias::new("NUMERIC")),
+        _ => text,
+    })
+}
+
+/// Type values are converted to for a target column, SQLite has no UUID type and would
+/// store them as blobs.
+pub(crate) fn transfer_type(driver: &Drivers, column_type: &CustomColumnType) -> CustomColumnType {
+    match (driver, column_type) {
+        (Drivers::SQLite, CustomColumnType::Uuid) => CustomColumnType::Text,
+        (_, column_type) => column_type.clone(),
+    }
+}
+
+async fn count_rows(conn: &DatabaseConnection, table_name: &str) -> Result<u64> {
+    let (stmt, values) = Query::select()
+        .expr_as(Func::count(Expr::col(Asterisk)), Alias::new("count"))
+        .from(Alias::new(table_name))
+        .build_any_sqlx(conn.into_builder().as_ref());
+    let rows = decode_raw_rows(conn.fetch_all(&stmt, values).await?)?;
+    Ok(rows
+        .first()
+        .and_then(|row| row.0.get("count"))
+        .and_then(|count| count.as_u64())
+        .unwrap_or_default())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sea_query_binder::SqlxValues;
+
+    #[tokio::test]
+    async fn rows_are_copied_into_a_created_table() {
+        let mut source = DatabaseConnection::in_memory().await.unwrap();
+        let mut target = DatabaseConnection::in_memory().await.unwrap();
+        source
+            .execute("CREATE TABLE t (id INTEGER PRIMARY KEY, name TEXT, born DATE)")
+            .await
+            .unwrap();
+        source
+            .execute("INSERT INTO t VALUES (1, 'a', '2020-01-02'), (2, NULL, NULL)")
+            .await
+            .unwrap();
+        source.refresh_schema(None).await.unwrap();
+
+        let options = TransferOptions {
+            source_table: "t".to_string(),
+            target_table: "copy".to_string(),
+            create_table: true,
+            mode: TransferMode::Append,
+            batch_size: 1,
+        };
+        let mut progress = vec![];
+        let summary = transfer_table(&mut source, &mut target, &options, |done, total| {
+            progress.push((done, total))
+        })
+        .await
+        .unwrap();
+        assert!(summary.created_table);
+        assert_eq!(summary.columns, vec!["id", "name", "born"]);
+        assert_eq!(progress, vec![(1, 2), (2, 2)]);
+
+        let rows = decode_raw_rows(
+            target
+                .fetch_all(
+                    "SELECT id, name FROM copy ORDER BY id",
+                    SqlxValues(sea_query::Values(vec![])),
+                )
+                .await
+                .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(rows[0].0["name"], "a");
+        assert_eq!(rows[1].0["name"], serde_json::Value::Null);
+    }
+
+    #[tokio::test]
+    async fn binary_values_are_copied_as_is() {
+        let mut source = DatabaseConnection::in_memory().await.unwrap();
+        let mut target = DatabaseConnection::in_memory().await.unwrap();
+        source
+            .execute("CREATE TABLE t (id INTEGER PRIMARY KEY, data BLOB)")
+            .await
+            .unwrap();
+        source
+            .execute("INSERT INTO t VALUES (1, X'00FF27'), (2, NULL)")
+            .await
+            .unwrap();
+        source.refresh_schema(None).await.unwrap();
+
+        let options = TransferOptions {
+            source_table: "t".to_string(),
+            target_table: "t".to_string(),
+            create_table: true,
+            mode: TransferMode::Append,
+            batch_size: 100,
+        };
+        transfer_table(&mut source, &mut target, &options, |_, _| {})
+            .await
+            .unwrap();
+
+        let rows = decode_raw_rows(
+            target
+                .fetch_all(
+                    "SELECT quote(data) AS data FROM t ORDER BY id",
+                    SqlxValues(sea_query::Values(vec![])),
+                )
+                .await
+                .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(rows[0].0["data"], "X'00FF27'");
+        assert_eq!(rows[1].0["data"], "NULL");
+    }
+
+    #[test]
+    fn decimals_are_copied_as_text() {
+        let column = ColumnInfo {
+            auto_generated: false,
+            name: "amount".to_string(),
+            nullable: true,
+            pk: false,
+            r#type: CustomColumnType::Integer,
+            default_value: None,
+            max_length: None,
+            numeric_precision: Some(30),
+            numeric_scale: Some(10),
+            collation: None,
+            comment: None,
+        };
+        let value = |driver: Drivers, value: JsonValue| {
+            let expr = transfer_value(&driver, &column, RowValue::Decoded(value))?;
+            Ok::<_, TxError>(inline(&driver, Query::select().expr(expr)))
+        };
+
+        let amount = JsonValue::from("12345678901234567890.0123456789");
+        assert_eq!(
+            value(Drivers::PostgreSQL, amount.clone()).unwrap(),
+            "SELECT CAST('12345678901234567890.0123456789' AS NUMERIC)"
+        );
+        assert_eq!(
+            value(Drivers::MySQL, amount).unwrap(),
+            "SELECT '12345678901234567890.0123456789'"
+        );
+        assert_eq!(
+            value(Drivers::SQLite, JsonValue::from(0.1)).unwrap(),
+            "SELECT '0.1'"
+        );
+        assert!(value(Drivers::SQLite, JsonValue::from("ten")).is_err());
+    }
+}
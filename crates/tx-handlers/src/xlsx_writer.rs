@@ -0,0 +1,139 @@
+//! XLSX workbooks with a single sheet, a header row and typed cells.
+
+use crate::export::{Cell, ExportColumn};
+use chrono::{Datelike, Timelike};
+use rust_decimal::prelude::ToPrimitive;
+use rust_xlsxwriter::{ExcelDateTime, Format, Workbook, XlsxError};
+use serde_json::Value as JsonValue;
+use tx_lib::{Result, TxError};
+
+/// Most rows a sheet can have, including the header.
+const MAX_ROWS: u32 = 1_048_576;
+
+/// Most columns a sheet can have.
+const MAX_COLUMNS: usize = 16_384;
+
+pub(crate) struct XlsxWriter {
+    path: String,
+    workbook: Workbook,
+    /// Index of the next row to write.
+    row: u32,
+    date_format: Format,
+    date_time_format: Format,
+    time_format: Format,
+}
+
+impl XlsxWriter {
+    pub(crate) fn new(path: &str) -> Self {
+        let mut workbook = Workbook::new();
+        // Rows are flushed to a temporary file as soon as the next one is written.
+        workbook.add_worksheet_with_constant_memory();
+        Self {
+            path: path.to_string(),
+            workbook,
+            row: 0,
+            date_format: Format::new().set_num_format("yyyy-mm-dd"),
+            date_time_format: Format::new().set_num_format("yyyy-mm-dd hh:mm:ss"),
+            time_format: Format::new().set_num_format("hh:mm:ss"),
+        }
+    }
+
+    pub(crate) fn start(&mut self, columns: &[ExportColumn]) -> Result<()> {
+        if columns.len() > MAX_COLUMNS {
+            return Err(TxError::Export(format!(
+                "XLSX sheets can't have more than {MAX_COLUMNS} columns"
+            )));
+        }
+        let header = Format::new().set_bold();
+        let sheet = self.workbook.worksheet_from_index(0).map_err(xlsx_error)?;
+        for (col, column) in columns.iter().enumerate() {
+            sheet
+                .write_string_with_format(0, col as u16, &column.name, &header)
+                .map_err(xlsx_error)?;
+        }
+        sheet.set_freeze_panes(1, 0).map_err(xlsx_error)?;
+        self.row = 1;
+        Ok(())
+    }
+
+    pub(crate) fn write_row(
+        &mut self,
+        columns: &[ExportColumn],
+        values: &[JsonValue],
+    ) -> Result<()> {
+        if self.row == MAX_ROWS {
+            return Err(TxError::Export(format!(
+                "XLSX sheets can't have more than {MAX_ROWS} rows"
+            )));
+        }
+        let row = self.row;
+        let sheet = self.workbook.worksheet_from_index(0).map_err(xlsx_error)?;
+        for (col, (column, value)) in columns.iter().zip(values).enumerate() {
+            let col = col as u16;
+            let Some(cell) = column.cell(value)? else {
+                continue;
+            };
+            match cell {
+                Cell::Boolean(b) => sheet.write_boolean(row, col, b),
+                Cell::Integer(i) => sheet.write_number(row, col, i as f64),
+                Cell::PositiveInteger(u) => sheet.write_number(row, col, u as f64),
+                Cell::Float(f) => sheet.write_number(row, col, f),
+                Cell::Decimal(d) => sheet.write_number(row, col, d.to_f64().unwrap_or_default()),
+                Cell::Date(date) => {
+                    let date = excel_date(date.year(), date.month(), date.day())?;
+                    sheet.write_datetime_with_format(row, col, &date, &self.date_format)
+                }
+                Cell::DateTime(date_time) => {
+                    let date_time =
+                        excel_date(date_time.year(), date_time.month(), date_time.day())?
+                            .and_hms(
+                                date_time.hour() as u16,
+                                date_time.minute() as u8,
+                                seconds(&date_time.time()),
+                            )
+                            .map_err(xlsx_error)?;
+                    sheet.write_datetime_with_format(row, col, &date_time, &self.date_time_format)
+                }
+                Cell::Time(time) => {
+                    let time = ExcelDateTime::from_hms(
+                        time.hour() as u16,
+                        time.minute() as u8,
+                        seconds(&time),
+                    )
+                    .map_err(xlsx_error)?;
+                    sheet.write_datetime_with_format(row, col, &time, &self.time_format)
+                }
+                Cell::Binary(bytes) => {
+                    let hex: String = bytes.iter().map(|b| format!("{b:02x}")).collect();
+                    sheet.write_string(row, col, hex)
+                }
+                Cell::Text(text) => sheet.write_string(row, col, text),
+            }
+            .map_err(|e| TxError::InvalidValue(format!("column `{}`: {e}", column.name)))?;
+        }
+        self.row += 1;
+        Ok(())
+    }
+
+    pub(crate) fn finish(mut self) -> Result<()> {
+        self.workbook.save(&self.path).map_err(xlsx_error)?;
+        Ok(())
+    }
+}
+
+/// Excel's dates start in 1900, earlier ones are rejected.
+fn excel_date(year: i32, month: u32, day: u32) -> Result<ExcelDateTime> {
+    let year = u16::try_from(year)
+        .map_err(|_| TxError::InvalidValue(format!("the year {year} can't be written to XLSX")))?;
+    ExcelDateTime::from_ymd(year, month as u8, day as u8)
+        .map_err(|e| TxError::InvalidValue(e.to_string()))
+}
+
+/// Seconds of the minute, with their fraction.
+fn seconds(time: &impl Timelike) -> f64 {
+    time.second() as f64 + time.nanosecond() as f64 / 1e9
+}
+
+fn xlsx_error(e: XlsxError) -> TxError {
+    TxError::Export(e.to_string())
+}
@@ -1,3 +1,4 @@
+use crate::types::Drivers;
 use serde::Serialize;
 use specta::{
     DataType, Generics, NamedType, SpectaID,
@@ -61,6 +62,103 @@ pub enum TxError {
 
     #[error("Unable to resolve home dir path")]
     HomeDirResolution,
+
+    #[error("Failed to discover schema: {0}")]
+    /// Represents errors raised while introspecting the database schema.
+    SchemaDiscovery(String),
+
+    #[error("Invalid table definition: {0}")]
+    /// Represents table definitions that can't be turned into DDL statements.
+    InvalidTableDefinition(String),
+
+    #[error("Unsupported schema change: {0}")]
+    /// For schema changes the database's `ALTER TABLE` can't perform.
+    UnsupportedSchemaChange(String),
+
+    #[error("Failed to rebuild table: {0}")]
+    /// Represents errors raised while rebuilding a SQLite table.
+    TableRebuild(String),
+
+    #[error("Connections use different drivers: {0} and {1}")]
+    /// For operations across two connections that must use the same driver.
+    DriverMismatch(Drivers, Drivers),
+
+    #[error("Can't compare table data: {0}")]
+    /// Represents tables whose rows can't be matched with each other.
+    DataComparison(String),
+
+    #[error("Invalid value: {0}")]
+    /// Represents values that can't be converted to their column's type.
+    InvalidValue(String),
+
+    #[error("Failed to transfer table: {0}")]
+    /// Represents errors raised while copying a table to another connection.
+    Transfer(String),
+
+    #[error("Failed to export rows: {0}")]
+    /// Represents errors raised while writing exported rows to a file.
+    Export(String),
+
+    #[error("Failed to import file: {0}")]
+    /// Represents errors raised while reading an imported file or inserting its rows.
+    Import(String),
+
+    #[error("Failed to restore database: {0}")]
+    /// Represents statements of a restored script that failed to execute.
+    Restore(String),
+
+    #[error("Failed to back up database: {0}")]
+    /// Represents errors raised by the SQLite online backup API.
+    Backup(String),
+
+    #[error("Failed to attach database: {0}")]
+    /// Represents invalid databases attached to a SQLite connection.
+    AttachDatabase(String),
+
+    #[error("Failed to decrypt database: {0}")]
+    /// Represents a missing or wrong key of an encrypted SQLite database.
+    DatabaseKey(String),
+
+    #[error("Failed to load extension: {0}")]
+    /// Represents a SQLite extension that failed to load.
+    LoadExtension(String),
+
+    #[error("Invalid connection settings: {0}")]
+    /// Represents a connection setting that is missing or malformed.
+    InvalidConnectionParams(String),
+
+    #[error("Failed to access secret: {0}")]
+    /// Represents secrets that fail to be encrypted or decrypted.
+    Secret(String),
+
+    #[error("Secrets are locked, unlock them with the master password")]
+    /// Represents reading secrets before the master password was given.
+    SecretsLocked,
+
+    #[error("The secrets keyfile {0} is missing, saved secrets can't be decrypted without it")]
+    /// Represents a keyfile deleted while secrets are still encrypted with its key.
+    KeyfileMissing(String),
+
+    #[error("Connection is read-only, statement not allowed: {0}")]
+    /// Represents statements other than queries run on a read-only connection.
+    ReadOnlyConnection(String),
+
+    #[error("Destructive statement must be run from the SQL editor: {0}")]
+    /// Represents destructive statements run where they can't be confirmed.
+    DestructiveStatement(String),
+
+    #[error("{0} must be confirmed on this connection")]
+    /// Represents destructive operations run without confirmation on a connection
+    /// requiring it.
+    ConfirmationRequired(String),
+
+    #[error("Only queries can be exported, statement not allowed: {0}")]
+    /// Represents statements other than queries whose rows were to be exported.
+    NotAQuery(String),
+
+    #[error("SSH tunnel failed: {0}")]
+    /// Represents failures to open or authenticate an SSH tunnel.
+    SshTunnel(String),
 }
 
 impl specta::NamedType for TxError {
@@ -150,6 +248,30 @@ enum TxErrorKind {
     InvalidConnectionString { message: String },
     UnsupportedDriver { message: String },
     HomeDirResolution { message: String },
+    SchemaDiscovery { message: String, details: String },
+    InvalidTableDefinition { message: String, details: String },
+    UnsupportedSchemaChange { message: String, details: String },
+    TableRebuild { message: String, details: String },
+    DriverMismatch { message: String, details: String },
+    DataComparison { message: String, details: String },
+    InvalidValue { message: String, details: String },
+    Transfer { message: String, details: String },
+    Export { message: String, details: String },
+    Import { message: String, details: String },
+    Restore { message: String, details: String },
+    Backup { message: String, details: String },
+    AttachDatabase { message: String, details: String },
+    DatabaseKey { message: String, details: String },
+    LoadExtension { message: String, details: String },
+    InvalidConnectionParams { message: String, details: String },
+    Secret { message: String, details: String },
+    SecretsLocked { message: String },
+    KeyfileMissing { message: String, details: String },
+    ReadOnlyConnection { message: String, details: String },
+    DestructiveStatement { message: String, details: String },
+    ConfirmationRequired { message: String, details: String },
+    NotAQuery { message: String, details: String },
+    SshTunnel { message: String, details: String },
 }
 
 impl Serialize for TxError {
@@ -201,6 +323,101 @@ impl Serialize for TxError {
             Self::HomeDirResolution => TxErrorKind::HomeDirResolution {
                 message: error_message,
             },
+            Self::SchemaDiscovery(_) => TxErrorKind::SchemaDiscovery {
+                message: "Failed to discover schema".to_string(),
+                details: error_message,
+            },
+            Self::InvalidTableDefinition(_) => TxErrorKind::InvalidTableDefinition {
+                message: "Invalid table definition".to_string(),
+                details: error_message,
+            },
+            Self::UnsupportedSchemaChange(_) => TxErrorKind::UnsupportedSchemaChange {
+                message: "Unsupported schema change".to_string(),
+                details: error_message,
+            },
+            Self::TableRebuild(_) => TxErrorKind::TableRebuild {
+                message: "Failed to rebuild table".to_string(),
+                details: error_message,
+            },
+            Self::DriverMismatch(..) => TxErrorKind::DriverMismatch {
+                message: "Connections use different drivers".to_string(),
+                details: error_message,
+            },
+            Self::DataComparison(_) => TxErrorKind::DataComparison {
+                message: "Can't compare table data".to_string(),
+                details: error_message,
+            },
+            Self::InvalidValue(_) => TxErrorKind::InvalidValue {
+                message: "Invalid value".to_string(),
+                details: error_message,
+            },
+            Self::Transfer(_) => TxErrorKind::Transfer {
+                message: "Failed to transfer table".to_string(),
+                details: error_message,
+            },
+            Self::Export(_) => TxErrorKind::Export {
+                message: "Failed to export rows".to_string(),
+                details: error_message,
+            },
+            Self::Import(_) => TxErrorKind::Import {
+                message: "Failed to import file".to_string(),
+                details: error_message,
+            },
+            Self::Restore(_) => TxErrorKind::Restore {
+                message: "Failed to restore database".to_string(),
+                details: error_message,
+            },
+            Self::Backup(_) => TxErrorKind::Backup {
+                message: "Failed to back up database".to_string(),
+                details: error_message,
+            },
+            Self::AttachDatabase(_) => TxErrorKind::AttachDatabase {
+                message: "Failed to attach database".to_string(),
+                details: error_message,
+            },
+            Self::DatabaseKey(_) => TxErrorKind::DatabaseKey {
+                message: "Failed to decrypt database".to_string(),
+                details: error_message,
+            },
+            Self::LoadExtension(_) => TxErrorKind::LoadExtension {
+                message: "Failed to load extension".to_string(),
+                details: error_message,
+            },
+            Self::InvalidConnectionParams(_) => TxErrorKind::InvalidConnectionParams {
+                message: "Invalid connection settings".to_string(),
+                details: error_message,
+            },
+            Self::Secret(_) => TxErrorKind::Secret {
+                message: "Failed to access secret".to_string(),
+                details: error_message,
+            },
+            Self::SecretsLocked => TxErrorKind::SecretsLocked {
+                message: error_message,
+            },
+            Self::KeyfileMissing(_) => TxErrorKind::KeyfileMissing {
+                message: "Secrets keyfile is missing".to_string(),
+                details: error_message,
+            },
+            Self::ReadOnlyConnection(_) => TxErrorKind::ReadOnlyConnection {
+                message: "Connection is read-only".to_string(),
+                details: error_message,
+            },
+            Self::DestructiveStatement(_) => TxErrorKind::DestructiveStatement {
+                message: "Destructive statement rejected".to_string(),
+                details: error_message,
+            },
+            Self::ConfirmationRequired(_) => TxErrorKind::ConfirmationRequired {
+                message: "Confirmation required".to_string(),
+                details: error_message,
+            },
+            Self::NotAQuery(_) => TxErrorKind::NotAQuery {
+                message: "Only queries can be exported".to_string(),
+                details: error_message,
+            },
+            Self::SshTunnel(_) => TxErrorKind::SshTunnel {
+                message: "SSH tunnel failed".to_string(),
+                details: error_message,
+            },
         };
         error_kind.serialize(serializer)
     }
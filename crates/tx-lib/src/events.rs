@@ -7,3 +7,60 @@ pub struct ConnectionsChanged;
 
 #[derive(Clone, Serialize, Deserialize, Type, Event, Debug)]
 pub struct TableContentsChanged;
+
+#[derive(Clone, Serialize, Deserialize, Type, Event, Debug)]
+pub struct SchemaChanged;
+
+#[derive(Clone, Serialize, Deserialize, Type, Event, Debug)]
+#[serde(rename_all = "camelCase")]
+/// Progress of a table transfer, emitted after each inserted batch.
+pub struct TransferProgress {
+    pub target_table: String,
+    pub rows_transferred: u64,
+    /// Number of rows in the source table when the transfer started.
+    pub total_rows: u64,
+}
+
+#[derive(Clone, Serialize, Deserialize, Type, Event, Debug)]
+#[serde(rename_all = "camelCase")]
+/// Progress of an export, emitted periodically while rows are written.
+pub struct ExportProgress {
+    pub path: String,
+    pub rows_exported: u64,
+}
+
+#[derive(Clone, Serialize, Deserialize, Type, Event, Debug)]
+#[serde(rename_all = "camelCase")]
+/// Progress of an import, emitted after each inserted batch.
+pub struct ImportProgress {
+    pub path: String,
+    pub rows_imported: u64,
+}
+
+#[derive(Clone, Serialize, Deserialize, Type, Event, Debug)]
+#[serde(rename_all = "camelCase")]
+/// Progress of a database dump, emitted periodically while rows are written.
+pub struct DumpProgress {
+    pub path: String,
+    pub rows_dumped: u64,
+}
+
+#[derive(Clone, Serialize, Deserialize, Type, Event, Debug)]
+#[serde(rename_all = "camelCase")]
+/// Progress of a script restore, emitted periodically while statements are executed.
+pub struct RestoreProgress {
+    pub path: String,
+    pub statements_executed: u64,
+    /// Bytes of the script read so far.
+    pub bytes_read: u64,
+    pub total_bytes: u64,
+}
+
+#[derive(Clone, Serialize, Deserialize, Type, Event, Debug)]
+#[serde(rename_all = "camelCase")]
+/// Progress of a SQLite backup, emitted after each copied step of pages.
+pub struct BackupProgress {
+    pub path: String,
+    pub pages_copied: u64,
+    pub total_pages: u64,
+}
@@ -5,9 +5,12 @@
 //!
 //! Other crates can depend on this crate, but this crate *Mustn't* depend on others.
 
+pub mod connection;
 mod error;
 pub mod events;
 pub mod fs;
+pub mod safety;
+pub mod secret;
 pub mod types;
 
 pub use error::TxError;
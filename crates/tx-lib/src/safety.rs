@@ -0,0 +1,331 @@
+//! Safety levels of saved connections, guarding production databases against statements
+//! run by mistake.
+
+use crate::{Result, TxError, types::Drivers};
+use serde::{Deserialize, Serialize};
+use specta::Type;
+use sqlparser::{
+    ast::{
+        AlterTableOperation, BinaryOperator, Expr, Query, SetExpr, Statement, UnaryOperator, Value,
+    },
+    dialect::{Dialect, MySqlDialect, PostgreSqlDialect, SQLiteDialect},
+    parser::Parser,
+};
+
+#[derive(Serialize, Deserialize, Debug, Default, Type, Clone, Copy, PartialEq, Eq, sqlx::Type)]
+#[serde(rename_all = "camelCase")]
+#[sqlx(rename_all = "snake_case")]
+pub enum SafetyLevel {
+    #[default]
+    Normal,
+    /// Destructive statements, e.g `DROP` or `DELETE` without `WHERE`, only run once
+    /// confirmed.
+    ConfirmDestructive,
+    /// Only queries are allowed, the database session is read-only too where the driver
+    /// supports it.
+    ReadOnly,
+}
+
+impl std::fmt::Display for SafetyLevel {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s = match self {
+            Self::Normal => "normal",
+            Self::ConfirmDestructive => "confirm_destructive",
+            Self::ReadOnly => "read_only",
+        };
+        write!(f, "{s}")
+    }
+}
+
+impl SafetyLevel {
+    /// Check `statements` can run under this level. Read-only connections reject anything
+    /// but queries, the destructive statements of other connections are returned so
+    /// they're confirmed before running.
+    pub fn check(self, statements: &[Statement]) -> Result<Vec<String>> {
+        match self {
+            Self::Normal => Ok(vec![]),
+            Self::ConfirmDestructive => Ok(statements
+                .iter()
+                .filter(|stmt| is_destructive(stmt))
+                .map(ToString::to_string)
+                .collect()),
+            Self::ReadOnly => match statements.iter().find(|stmt| !is_read_only(stmt)) {
+                Some(stmt) => Err(TxError::ReadOnlyConnection(stmt.to_string())),
+                None => Ok(vec![]),
+            },
+        }
+    }
+
+    /// Reject `operation` on read-only connections, for writes that don't go through
+    /// statements checked with [`Self::check`], e.g imports or maintenance.
+    pub fn require_write(self, operation: &str) -> Result<()> {
+        match self {
+            Self::ReadOnly => Err(TxError::ReadOnlyConnection(operation.to_string())),
+            Self::Normal | Self::ConfirmDestructive => Ok(()),
+        }
+    }
+
+    /// Like [`Self::require_write`] for operations that can drop or replace data, which
+    /// must be `confirmed` on connections confirming destructive statements.
+    pub fn confirm_destructive(self, operation: &str, confirmed: bool) -> Result<()> {
+        self.require_write(operation)?;
+        if self == Self::ConfirmDestructive && !confirmed {
+            return Err(TxError::ConfirmationRequired(operation.to_string()));
+        }
+        Ok(())
+    }
+
+    /// Like [`Self::check`] for statements built by the app, which can't be confirmed
+    /// so destructive ones are rejected.
+    pub fn check_sql(self, sql: &str, driver: &Drivers) -> Result<()> {
+        if self == Self::Normal {
+            return Ok(());
+        }
+        match self.check(&parse(sql, driver)?)?.into_iter().next() {
+            Some(stmt) => Err(TxError::DestructiveStatement(stmt)),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Parse `sql` with the dialect of `driver`.
+pub fn parse(sql: &str, driver: &Drivers) -> Result<Vec<Statement>> {
+    let dialect: &dyn Dialect = match driver {
+        Drivers::SQLite => &SQLiteDialect {},
+        Drivers::PostgreSQL => &PostgreSqlDialect {},
+        Drivers::MySQL => &MySqlDialect {},
+    };
+    Ok(Parser::parse_sql(dialect, sql)?)
+}
+
+/// Whether `stmt` only reads, e.g `SELECT`, `EXPLAIN` or `SHOW`.
+pub fn is_read_only(stmt: &Statement) -> bool {
+    match stmt {
+        Statement::Query(query) => is_read_only_query(query),
+        // `EXPLAIN ANALYZE` runs the statement.
+        Statement::Explain {
+            analyze, statement, ..
+        } => !analyze && is_read_only(statement),
+        // Pragmas set with a value write to the database or change the connection.
+        Statement::Pragma { value, .. } => value.is_none(),
+        Statement::ExplainTable { .. }
+        | Statement::ShowFunctions { .. }
+        | Statement::ShowVariable { .. }
+        | Statement::ShowStatus { .. }
+        | Statement::ShowVariables { .. }
+        | Statement::ShowCreate { .. }
+        | Statement::ShowColumns { .. }
+        | Statement::ShowDatabases { .. }
+        | Statement::ShowSchemas { .. }
+        | Statement::ShowTables { .. }
+        | Statement::ShowViews { .. }
+        | Statement::ShowCollation { .. } => true,
+        _ => false,
+    }
+}
+
+/// Queries can write through data-modifying CTEs, e.g `WITH d AS (DELETE ...) SELECT`, or
+/// create a table with `SELECT INTO`.
+fn is_read_only_query(query: &Query) -> bool {
+    let ctes_read_only = query.with.as_ref().is_none_or(|with| {
+        with.cte_tables
+            .iter()
+            .all(|cte| is_read_only_query(&cte.query))
+    });
+    ctes_read_only && is_read_only_set_expr(&query.body)
+}
+
+fn is_read_only_set_expr(expr: &SetExpr) -> bool {
+    match expr {
+        SetExpr::Select(select) => select.into.is_none(),
+        SetExpr::Query(query) => is_read_only_query(query),
+        SetExpr::SetOperation { left, right, .. } => {
+            is_read_only_set_expr(left) && is_read_only_set_expr(right)
+        }
+        SetExpr::Values(_) | SetExpr::Table(_) => true,
+        _ => false,
+    }
+}
+
+/// Check `sql` only reads, for statements whose rows are exported instead of run in the
+/// SQL editor where they could be confirmed.
+pub fn check_query(sql: &str, driver: &Drivers) -> Result<()> {
+    match parse(sql, driver)?.iter().find(|stmt| !is_read_only(stmt)) {
+        Some(stmt) => Err(TxError::NotAQuery(stmt.to_string())),
+        None => Ok(()),
+    }
+}
+
+/// Whether `stmt` drops or empties a whole table or column, e.g `DROP`, `TRUNCATE`,
+/// `ALTER TABLE ... DROP COLUMN`, or `DELETE` and `UPDATE` without a restrictive `WHERE`.
+pub fn is_destructive(stmt: &Statement) -> bool {
+    match stmt {
+        Statement::Delete(delete) => matches_all_rows(delete.selection.as_ref()),
+        Statement::Update { selection, .. } => matches_all_rows(selection.as_ref()),
+        Statement::AlterTable { operations, .. } => operations.iter().any(|op| {
+            matches!(
+                op,
+                AlterTableOperation::DropColumn { .. } | AlterTableOperation::DropPartitions { .. }
+            )
+        }),
+        Statement::Drop { .. } | Statement::Truncate { .. } => true,
+        _ => false,
+    }
+}
+
+/// Whether a `WHERE` clause is missing, or is always true, e.g `TRUE` as rendered by
+/// sea-query for an empty condition or `1 = 1`.
+fn matches_all_rows(selection: Option<&Expr>) -> bool {
+    selection.is_none_or(|expr| is_constant(expr, true))
+}
+
+/// Whether `expr` always evaluates to `value` whatever the row, for the constant
+/// conditions written to match every row. `col = col` counts as true even though it
+/// skips `NULL`s.
+fn is_constant(expr: &Expr, value: bool) -> bool {
+    match expr {
+        Expr::Value(literal) => match &literal.value {
+            Value::Boolean(b) => *b == value,
+            Value::Number(n, _) => n.parse::<f64>().is_ok_and(|n| (n != 0.0) == value),
+            _ => false,
+        },
+        Expr::Nested(expr) => is_constant(expr, value),
+        Expr::UnaryOp {
+            op: UnaryOperator::Not,
+            expr,
+        } => is_constant(expr, !value),
+        Expr::IsTrue(expr) | Expr::IsNotFalse(expr) => is_constant(expr, value),
+        Expr::IsFalse(expr) | Expr::IsNotTrue(expr) => is_constant(expr, !value),
+        Expr::BinaryOp { left, op, right } => match op {
+            BinaryOperator::Or if value => is_constant(left, true) || is_constant(right, true),
+            BinaryOperator::Or => is_constant(left, false) && is_constant(right, false),
+            BinaryOperator::And if value => is_constant(left, true) && is_constant(right, true),
+            BinaryOperator::And => is_constant(left, false) || is_constant(right, false),
+            BinaryOperator::Eq | BinaryOperator::GtEq | BinaryOperator::LtEq => {
+                value && left.to_string() == right.to_string()
+            }
+            BinaryOperator::NotEq | BinaryOperator::Gt | BinaryOperator::Lt => {
+                !value && left.to_string() == right.to_string()
+            }
+            _ => false,
+        },
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn check(level: SafetyLevel, sql: &str) -> Result<Vec<String>> {
+        level.check(&parse(sql, &Drivers::SQLite)?)
+    }
+
+    #[test]
+    fn read_only_connections_only_run_queries() {
+        let level = SafetyLevel::ReadOnly;
+        assert!(check(level, "SELECT * FROM t; PRAGMA user_version").is_ok());
+        assert!(check(level, "WITH d AS (SELECT 1) SELECT * FROM d").is_ok());
+        assert!(matches!(
+            check(level, "SELECT 1; UPDATE t SET a = 1 WHERE id = 1"),
+            Err(TxError::ReadOnlyConnection(_))
+        ));
+        assert!(matches!(
+            check(level, "PRAGMA user_version = 2"),
+            Err(TxError::ReadOnlyConnection(_))
+        ));
+        assert!(matches!(
+            check(level, "EXPLAIN ANALYZE DELETE FROM t"),
+            Err(TxError::ReadOnlyConnection(_))
+        ));
+    }
+
+    #[test]
+    fn destructive_statements_are_returned_for_confirmation() {
+        let level = SafetyLevel::ConfirmDestructive;
+        let destructive = check(
+            level,
+            "DELETE FROM t WHERE id = 1; DELETE FROM t; UPDATE t SET a = 1; DROP TABLE t",
+        )
+        .unwrap();
+        assert_eq!(destructive.len(), 3);
+        let destructive = check(
+            level,
+            "DELETE FROM t WHERE 1 = 1; DELETE FROM t WHERE (1) OR id = 1; \
+             UPDATE t SET a = 1 WHERE NOT 0; ALTER TABLE t DROP COLUMN a",
+        )
+        .unwrap();
+        assert_eq!(destructive.len(), 4);
+        let restricted = check(
+            level,
+            "DELETE FROM t WHERE id = 1 AND 1 = 1; UPDATE t SET a = 1 WHERE 1 <> 1; \
+             ALTER TABLE t ADD COLUMN b TEXT",
+        )
+        .unwrap();
+        assert!(restricted.is_empty(), "{restricted:?}");
+        assert!(
+            check(SafetyLevel::Normal, "DROP TABLE t")
+                .unwrap()
+                .is_empty()
+        );
+
+        assert!(matches!(
+            level.check_sql("DELETE FROM t WHERE TRUE", &Drivers::SQLite),
+            Err(TxError::DestructiveStatement(_))
+        ));
+        assert!(
+            level
+                .check_sql("DELETE FROM t WHERE id = 1", &Drivers::SQLite)
+                .is_ok()
+        );
+    }
+
+    #[test]
+    fn only_queries_are_exported() {
+        assert!(check_query("SELECT * FROM t; PRAGMA user_version", &Drivers::SQLite).is_ok());
+        for sql in [
+            "DELETE FROM t WHERE id = 1 RETURNING *",
+            "DROP TABLE t",
+            "SELECT 1; UPDATE t SET a = 1",
+        ] {
+            assert!(
+                matches!(
+                    check_query(sql, &Drivers::PostgreSQL),
+                    Err(TxError::NotAQuery(_))
+                ),
+                "{sql}"
+            );
+        }
+    }
+
+    #[test]
+    fn app_operations_are_guarded() {
+        assert!(
+            SafetyLevel::Normal
+                .confirm_destructive("Restore", false)
+                .is_ok()
+        );
+        assert!(
+            SafetyLevel::ConfirmDestructive
+                .require_write("Import")
+                .is_ok()
+        );
+        assert!(matches!(
+            SafetyLevel::ReadOnly.require_write("Import"),
+            Err(TxError::ReadOnlyConnection(_))
+        ));
+        assert!(matches!(
+            SafetyLevel::ConfirmDestructive.confirm_destructive("Restore", false),
+            Err(TxError::ConfirmationRequired(_))
+        ));
+        assert!(
+            SafetyLevel::ConfirmDestructive
+                .confirm_destructive("Restore", true)
+                .is_ok()
+        );
+        assert!(matches!(
+            SafetyLevel::ReadOnly.confirm_destructive("Restore", true),
+            Err(TxError::ReadOnlyConnection(_))
+        ));
+    }
+}
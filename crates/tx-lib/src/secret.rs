@@ -0,0 +1,189 @@
+//! Encryption of the secrets saved with connections, e.g passwords, with AES-256-GCM.
+//!
+//! The key is either read from a local keyfile, or derived from a master password with
+//! PBKDF2. The keyfile is kept next to the app's `data.db`, secrets saved in it can't be
+//! decrypted without the keyfile.
+
+use crate::{Result, TxError, types::SecretString};
+use base64::{Engine, engine::general_purpose::STANDARD};
+use ring::{
+    aead::{AES_256_GCM, Aad, LessSafeKey, NONCE_LEN, Nonce, UnboundKey},
+    pbkdf2,
+    rand::{SecureRandom, SystemRandom},
+};
+use std::{fs, io::ErrorKind, num::NonZeroU32, path::Path};
+
+const KEY_LEN: usize = 32;
+
+const SALT_LEN: usize = 16;
+
+/// Iterations of PBKDF2-HMAC-SHA256 deriving a key from a master password.
+const PBKDF2_ITERATIONS: NonZeroU32 = NonZeroU32::new(600_000).unwrap();
+
+/// Value sealed with a master password's key, opening it checks the password.
+const VERIFIER: &str = "tablex";
+
+fn error(message: &str) -> TxError {
+    TxError::Secret(message.to_string())
+}
+
+fn random<const N: usize>() -> Result<[u8; N]> {
+    let mut bytes = [0; N];
+    SystemRandom::new()
+        .fill(&mut bytes)
+        .map_err(|_| error("failed to generate random bytes"))?;
+    Ok(bytes)
+}
+
+pub struct SecretCipher {
+    key: LessSafeKey,
+}
+
+impl SecretCipher {
+    fn new(key: &[u8]) -> Result<Self> {
+        let key = UnboundKey::new(&AES_256_GCM, key)
+            .map_err(|_| error("the key must be 32 bytes long"))?;
+        Ok(Self {
+            key: LessSafeKey::new(key),
+        })
+    }
+
+    /// Read the key from `path`. With `create`, the file is created with a random key if
+    /// it doesn't exist, which must only be the case when no secret is encrypted yet.
+    pub fn from_keyfile(path: &Path, create: bool) -> Result<Self> {
+        match fs::read(path) {
+            Ok(key) => Self::new(&key),
+            Err(e) if e.kind() == ErrorKind::NotFound && !create => {
+                Err(TxError::KeyfileMissing(path.to_string_lossy().into_owned()))
+            }
+            Err(e) if e.kind() == ErrorKind::NotFound => {
+                let key = random::<KEY_LEN>()?;
+                write_keyfile(path, &key)?;
+                log::info!("Created secrets keyfile: {}", path.to_string_lossy());
+                Self::new(&key)
+            }
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Derive the key from a master password and the salt it was set with.
+    pub fn from_password(password: &SecretString, salt: &str) -> Result<Self> {
+        let salt = STANDARD
+            .decode(salt)
+            .map_err(|_| error("the salt isn't valid base64"))?;
+        let mut key = [0; KEY_LEN];
+        pbkdf2::derive(
+            pbkdf2::PBKDF2_HMAC_SHA256,
+            PBKDF2_ITERATIONS,
+            &salt,
+            password.expose().as_bytes(),
+            &mut key,
+        );
+        Self::new(&key)
+    }
+
+    /// A random salt for [`Self::from_password`], encoded as base64.
+    pub fn new_salt() -> Result<String> {
+        Ok(STANDARD.encode(random::<SALT_LEN>()?))
+    }
+
+    /// Encrypt `secret` with a random nonce, returned as base64 followed by the ciphertext.
+    pub fn encrypt(&self, secret: &SecretString) -> Result<String> {
+        let nonce = random::<NONCE_LEN>()?;
+        let mut sealed = secret.expose().as_bytes().to_vec();
+        self.key
+            .seal_in_place_append_tag(
+                Nonce::assume_unique_for_key(nonce),
+                Aad::empty(),
+                &mut sealed,
+            )
+            .map_err(|_| error("failed to encrypt"))?;
+        Ok(STANDARD.encode([&nonce[..], &sealed].concat()))
+    }
+
+    pub fn decrypt(&self, sealed: &str) -> Result<SecretString> {
+        let sealed = STANDARD
+            .decode(sealed)
+            .map_err(|_| error("the secret isn't valid base64"))?;
+        if sealed.len() < NONCE_LEN {
+            return Err(error("the secret is truncated"));
+        }
+        let (nonce, ciphertext) = sealed.split_at(NONCE_LEN);
+        let nonce = Nonce::try_assume_unique_for_key(nonce)
+            .map_err(|_| error("the secret's nonce is invalid"))?;
+        let mut ciphertext = ciphertext.to_vec();
+        let secret = self
+            .key
+            .open_in_place(nonce, Aad::empty(), &mut ciphertext)
+            .map_err(|_| error("the key is wrong, or the secret was tampered with"))?;
+        String::from_utf8(secret.to_vec())
+            .map(SecretString::new)
+            .map_err(|_| error("the secret isn't valid UTF-8"))
+    }
+
+    /// Seal the value [`Self::verify`] opens to check a master password.
+    pub fn verifier(&self) -> Result<String> {
+        self.encrypt(&SecretString::new(VERIFIER.to_string()))
+    }
+
+    pub fn verify(&self, verifier: &str) -> bool {
+        self.decrypt(verifier)
+            .is_ok_and(|value| value.expose() == VERIFIER)
+    }
+}
+
+/// Write a new keyfile only readable by its owner.
+fn write_keyfile(path: &Path, key: &[u8]) -> Result<()> {
+    use std::io::Write;
+
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    options.open(path)?.write_all(key)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn secret(value: &str) -> SecretString {
+        SecretString::new(value.to_string())
+    }
+
+    #[test]
+    fn secrets_are_only_decrypted_with_their_key() {
+        let salt = SecretCipher::new_salt().unwrap();
+        let cipher = SecretCipher::from_password(&secret("master"), &salt).unwrap();
+        let sealed = cipher.encrypt(&secret("hunter2")).unwrap();
+        assert_ne!(sealed, cipher.encrypt(&secret("hunter2")).unwrap());
+        assert_eq!(cipher.decrypt(&sealed).unwrap().expose(), "hunter2");
+
+        let other = SecretCipher::from_password(&secret("other"), &salt).unwrap();
+        assert!(other.decrypt(&sealed).is_err());
+
+        let verifier = cipher.verifier().unwrap();
+        assert!(cipher.verify(&verifier));
+        assert!(!other.verify(&verifier));
+    }
+
+    #[test]
+    fn keyfile_is_only_created_when_allowed() {
+        let path = std::env::temp_dir().join(format!("tx-secret-{}.key", std::process::id()));
+        let _ = fs::remove_file(&path);
+        assert!(matches!(
+            SecretCipher::from_keyfile(&path, false),
+            Err(TxError::KeyfileMissing(_))
+        ));
+
+        let cipher = SecretCipher::from_keyfile(&path, true).unwrap();
+        let sealed = cipher.encrypt(&secret("hunter2")).unwrap();
+        let reopened = SecretCipher::from_keyfile(&path, false).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(reopened.decrypt(&sealed).unwrap().expose(), "hunter2");
+    }
+}
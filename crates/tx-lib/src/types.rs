@@ -1,11 +1,15 @@
-use crate::TxError;
+use crate::{
+    TxError,
+    connection::{ConnectOptions, ConnectionParams, SshLogin},
+    safety::SafetyLevel,
+};
 use serde::{Deserialize, Serialize};
 use serde_json::{Map as JsonMap, Value as JsonValue};
 use specta::Type;
 
 pub type Result<T> = std::result::Result<T, TxError>;
 
-#[derive(Serialize, Deserialize, Debug, Default, Clone, Type, sqlx::Type)]
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq, Eq, Type, sqlx::Type)]
 #[serde(rename_all = "lowercase")]
 pub enum Drivers {
     #[default]
@@ -31,7 +35,219 @@ pub struct ConnConfig {
     pub id: i64,
     pub driver: Drivers,
     pub name: String,
-    pub connection_string: String,
+    /// Settings the connection's URL is rendered from.
+    #[sqlx(json)]
+    pub params: ConnectionParams,
+    /// Secret holding the password of PostgreSQL and MySQL connections.
+    pub password_secret_id: Option<i64>,
+    /// The password itself, read from the secret store before connecting.
+    #[sqlx(skip)]
+    #[serde(skip)]
+    pub password: Option<SecretString>,
+    /// Secret holding the password or key passphrase of the SSH tunnel.
+    pub ssh_secret_id: Option<i64>,
+    /// The SSH secret itself, read from the secret store before connecting.
+    #[sqlx(skip)]
+    #[serde(skip)]
+    pub ssh_secret: Option<SecretString>,
+    /// Which statements are allowed, and which must be confirmed before running.
+    pub safety_level: SafetyLevel,
+    /// Group, tags, color and environment the connection is organized by.
+    #[sqlx(flatten)]
+    pub labels: ConnectionLabels,
+    /// Options applied to SQLite connections, ignored by other drivers.
+    #[sqlx(flatten)]
+    pub sqlite: SqliteOptions,
+    /// Why the connection string the connection was saved with couldn't be migrated to
+    /// `params`, which are blank until the connection is edited.
+    #[sqlx(rename = "migration_error")]
+    pub needs_attention: Option<String>,
+}
+
+impl ConnConfig {
+    /// URL the connection is established with, including its password.
+    pub fn url(&self) -> Result<String> {
+        if let Some(reason) = &self.needs_attention {
+            return Err(TxError::InvalidConnectionParams(format!(
+                "the connection must be edited, its connection string couldn't be read: {reason}"
+            )));
+        }
+        self.params.to_url(self.password.as_ref())
+    }
+
+    /// SSH tunnel the connection goes through, with its secret.
+    pub fn ssh(&self) -> Option<SshLogin> {
+        self.params.ssh().map(|tunnel| SshLogin {
+            tunnel: tunnel.clone(),
+            secret: self.ssh_secret.clone(),
+        })
+    }
+
+    /// Options the connection is established with, its session is read-only if its
+    /// safety level is.
+    pub fn connect_options(&self) -> ConnectOptions {
+        ConnectOptions {
+            sqlite: self.sqlite.clone(),
+            read_only: self.safety_level == SafetyLevel::ReadOnly,
+            ssh: self.ssh(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Default, Type, Clone, PartialEq, Eq, sqlx::FromRow)]
+#[serde(rename_all = "camelCase")]
+pub struct ConnectionLabels {
+    /// Folder the connection is listed in, `None` lists it outside of any.
+    #[sqlx(rename = "group_name")]
+    pub group: Option<String>,
+    #[sqlx(json)]
+    pub tags: Vec<String>,
+    /// Hex color, e.g `#ff0000`.
+    pub color: Option<String>,
+    pub environment: Option<Environment>,
+}
+
+impl ConnectionLabels {
+    /// Trim the group and tags, dropping empty and repeated tags, and check the color.
+    pub fn normalize(self) -> Result<Self> {
+        let group = self
+            .group
+            .map(|group| group.trim().to_string())
+            .filter(|group| !group.is_empty());
+        let mut tags: Vec<String> = Vec::with_capacity(self.tags.len());
+        for tag in self.tags {
+            let tag = tag.trim();
+            if !tag.is_empty() && !tags.iter().any(|other| other == tag) {
+                tags.push(tag.to_string());
+            }
+        }
+        if let Some(color) = &self.color {
+            let hex = color.strip_prefix('#').unwrap_or_default();
+            if hex.len() != 6 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+                return Err(TxError::InvalidConnectionParams(format!(
+                    "`{color}` isn't a hex color, e.g #ff0000"
+                )));
+            }
+        }
+        Ok(Self {
+            group,
+            tags,
+            color: self.color.map(|color| color.to_lowercase()),
+            environment: self.environment,
+        })
+    }
+}
+
+/// Environment a connection's database belongs to.
+#[derive(Serialize, Deserialize, Debug, Type, Clone, Copy, PartialEq, Eq, sqlx::Type)]
+#[serde(rename_all = "camelCase")]
+#[sqlx(rename_all = "lowercase")]
+pub enum Environment {
+    Development,
+    Staging,
+    Production,
+}
+
+impl std::fmt::Display for Environment {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s = match self {
+            Self::Development => "development",
+            Self::Staging => "staging",
+            Self::Production => "production",
+        };
+        write!(f, "{s}")
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Default, Type, Clone, PartialEq, Eq, sqlx::FromRow)]
+#[serde(rename_all = "camelCase")]
+pub struct SqliteOptions {
+    #[sqlx(json)]
+    pub pragmas: SqlitePragmas,
+    /// Databases attached to each connection, their tables are listed as `alias.table`.
+    #[sqlx(json)]
+    pub attached_databases: Vec<AttachedDatabase>,
+    /// Extensions loaded by each connection, in order.
+    #[sqlx(rename = "sqlite_extensions", json)]
+    pub extensions: Vec<SqliteExtension>,
+    /// Secret holding the SQLCipher key of an encrypted database.
+    #[sqlx(rename = "sqlite_key_secret_id")]
+    pub key_secret_id: Option<i64>,
+    /// The key itself, read from the secret store before connecting.
+    #[sqlx(skip)]
+    #[serde(skip)]
+    pub key: Option<SecretString>,
+}
+
+/// A secret value, e.g a password, kept out of `Debug` output.
+#[derive(Clone, Default, PartialEq, Eq)]
+pub struct SecretString(String);
+
+impl SecretString {
+    pub fn new(secret: String) -> Self {
+        Self(secret)
+    }
+
+    pub fn expose(&self) -> &str {
+        &self.0
+    }
+}
+
+impl std::fmt::Debug for SecretString {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "SecretString(***)")
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Type, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct AttachedDatabase {
+    /// Schema name the database is attached as.
+    pub alias: String,
+    pub path: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Type, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct SqliteExtension {
+    /// Path of the extension's library, SQLite tries the platform's suffix if it's missing.
+    pub path: String,
+    /// Name of the extension's init function, `None` lets SQLite derive it from the path.
+    pub entry_point: Option<String>,
+}
+
+/// Pragmas set on each connection opened to a SQLite database, `None` keeps the value
+/// given by the connection string or SQLite's default.
+#[derive(Serialize, Deserialize, Debug, Default, Type, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct SqlitePragmas {
+    pub foreign_keys: Option<bool>,
+    pub journal_mode: Option<JournalMode>,
+    /// Milliseconds to wait for a locked database before failing.
+    pub busy_timeout: Option<u32>,
+    pub synchronous: Option<Synchronous>,
+    /// Number of pages cached in memory, or KiB if negative.
+    pub cache_size: Option<i64>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Type, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum JournalMode {
+    Delete,
+    Truncate,
+    Persist,
+    Memory,
+    Wal,
+    Off,
+}
+
+#[derive(Serialize, Deserialize, Debug, Type, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum Synchronous {
+    Off,
+    Normal,
+    Full,
+    Extra,
 }
 
 #[derive(Serialize, Deserialize, Debug, sqlx::FromRow, Type)]